@@ -55,6 +55,53 @@ Scenario: Submitting a POST request with data
   When I successfully run `delivery api post 'orgs' -s=localhost --api-port=9999 -e=bar -u=cukes -d '{"name":"dummy"}'`
   Then the exit status should be 0
 
+Scenario: Submitting a POST request with data from a file and extra headers
+  Given a file named ".delivery/api-tokens" with:
+  """
+  localhost:9999,bar,cukes|this_is_a_fake_token
+  """
+  And a file named "org.json" with:
+  """
+  {"name":"dummy"}
+  """
+  And the Delivery API server on port "9999":
+    """
+    post '/api/v0/e/bar/orgs' do
+      if params[:name] != "dummy"
+	status 500
+      else
+	status 201
+      end
+      { "trace" => headers['X-Trace'] }
+    end
+    """
+  When I successfully run `delivery api post 'orgs' -s=localhost --api-port=9999 -e=bar -u=cukes -d @org.json -H 'X-Trace: abc123'`
+  Then the exit status should be 0
+  And the output should contain:
+    """
+      "trace": "abc123"
+    """
+
+Scenario: The exit status follows the class of the HTTP status
+  Given a file named ".delivery/api-tokens" with:
+  """
+  localhost:9999,bar,cukes|this_is_a_fake_token
+  """
+  And the Delivery API server on port "9999":
+    """
+    get('/api/v0/e/bar/orgs/missing') do
+      status 404
+      { "error" => "not_found" }
+    end
+    """
+  When I run `delivery api get 'orgs/missing' -s=localhost --api-port=9999 -e=bar -u=cukes --include --raw`
+  Then the exit status should be 4
+  And the output should contain "404 Not Found"
+  And the output should contain:
+    """
+    {"error":"not_found"}
+    """
+
 Scenario: Without a token and non_interactive enabled
   Given the Delivery API server:
     """
@@ -128,3 +175,25 @@ Scenario: Without a token will first request one.
     localhost:8080,bar,cukes|xOsqI8qiBrUCGGRttfFy768R8ZAMJ24RC+0UGyX9/II=
     """
     
+
+Scenario: Following the pages of a listing and selecting fields
+  Given a file named ".delivery/api-tokens" with:
+  """
+  localhost:9999,bar,cukes|this_is_a_fake_token
+  """
+  And the Delivery API server on port "9999":
+    """
+    get('/api/v0/e/bar/orgs') do
+      offset = (params[:offset] || 0).to_i
+      limit = params[:limit].to_i
+      orgs = [{ "name" => "alpha" }, { "name" => "beta" }, { "name" => "gamma" }]
+      { "orgs" => orgs[offset, limit] }
+    end
+    """
+  When I successfully run `delivery api get 'orgs?limit=2' -s=localhost --api-port=9999 -e=bar -u=cukes --all --select 'orgs[].name'`
+  Then the output should contain:
+    """
+    alpha
+    beta
+    gamma
+    """
@@ -304,3 +304,54 @@ Scenario: For automation purposes I should be able to request a token
   And the ptty output should contain "MY_SUPER_DUPER_TOKEN"
   And the ptty output should not contain "Requesting Token"
   And the ptty output should not contain "saved API token to"
+
+Scenario: Listing the stored tokens masks them
+
+  Given a file named ".delivery/api-tokens" with:
+    """
+    127.0.0.1:8080,token,petrashka|SUPER_FAKE_TOKEN|verified=2016-10-17T12:00:00Z
+    delivery.shd.chef.co,chef,alice|ANOTHER_FAKE_TOKEN
+    """
+  When I successfully run `delivery token list`
+  Then the output should contain "127.0.0.1:8080 token/petrashka ********OKEN verified 2016-10-17T12:00:00Z"
+  And the output should contain "delivery.shd.chef.co chef/alice ********OKEN never verified"
+  And the output should not contain "SUPER_FAKE_TOKEN"
+
+Scenario: Revoking a token removes it from the server and the store
+
+  Given a file named ".delivery/cli.toml" with:
+    """
+    enterprise = "token"
+    server = "127.0.0.1"
+    api_port = "8080"
+    user = "petrashka"
+    """
+  And the Delivery API server:
+    """
+      post('/api/v0/e/token/users/petrashka/revoke-token') do
+        status 204
+      end
+    """
+  And a file named ".delivery/api-tokens" with:
+    """
+    127.0.0.1:8080,token,petrashka|SUPER_FAKE_TOKEN
+    127.0.0.1:8080,token,alice|ANOTHER_FAKE_TOKEN
+    """
+  When I successfully run `delivery token revoke`
+  Then the output should contain "revoked"
+  And the file ".delivery/api-tokens" should not contain "SUPER_FAKE_TOKEN"
+  And the file ".delivery/api-tokens" should contain "ANOTHER_FAKE_TOKEN"
+
+Scenario: Logging out of a server removes all of its tokens
+
+  Given a file named ".delivery/api-tokens" with:
+    """
+    127.0.0.1:8080,token,petrashka|SUPER_FAKE_TOKEN
+    127.0.0.1:8080,chef,alice|ANOTHER_FAKE_TOKEN
+    delivery.shd.chef.co,chef,alice|YET_ANOTHER_FAKE_TOKEN
+    """
+  When I successfully run `delivery logout --server 127.0.0.1`
+  Then the output should contain "Removed the API token of token/petrashka on 127.0.0.1:8080"
+  And the output should contain "Removed the API token of chef/alice on 127.0.0.1:8080"
+  And the file ".delivery/api-tokens" should not contain "SUPER_FAKE_TOKEN"
+  And the file ".delivery/api-tokens" should contain "YET_ANOTHER_FAKE_TOKEN"
@@ -28,6 +28,11 @@ pub struct ApiClapOptions<'n> {
     pub api_port: &'n str,
     pub ent: &'n str,
     pub user: &'n str,
+    pub headers: Vec<&'n str>,
+    pub raw: bool,
+    pub include: bool,
+    pub all: bool,
+    pub select: &'n str,
 }
 impl<'n> Default for ApiClapOptions<'n> {
     fn default() -> Self {
@@ -38,7 +43,12 @@ impl<'n> Default for ApiClapOptions<'n> {
             server: "",
             api_port: "",
             ent: "",
-            user: ""
+            user: "",
+            headers: vec![],
+            raw: false,
+            include: false,
+            all: false,
+            select: ""
         }
     }
 }
@@ -52,7 +62,12 @@ impl<'n> ApiClapOptions<'n> {
             server: value_of(&matches, "server"),
             api_port: value_of(&matches, "api-port"),
             ent: value_of(&matches, "ent"),
-            user: value_of(&matches, "user")
+            user: value_of(&matches, "user"),
+            headers: matches.values_of("header").map(|v| v.collect()).unwrap_or(vec![]),
+            raw: matches.is_present("raw"),
+            include: matches.is_present("include"),
+            all: matches.is_present("all"),
+            select: value_of(&matches, "select")
         }
     }
 }
@@ -63,17 +78,35 @@ pub fn clap_subcommand<'c>() -> App<'c, 'c> {
         .args(&vec![config_path_arg()])
         .arg(Arg::from_usage("<method> 'HTTP method for the request'")
              .takes_value(false)
-             .possible_values(&["get", "put", "post", "delete"]))
+             .possible_values(&["get", "put", "post", "delete", "patch", "head"]))
         .args_from_usage(
              "<path> 'Path for rqeuest URL'
              --api-port=[api-port] 'Port for Delivery server'")
         .arg(Arg::with_name("data")
              .long("data")
              .short("d")
-             .help("Data to send for PUT/POST request")
+             .help("Data to send for PUT/POST/PATCH request; @file reads it from \
+                    a file and @- from stdin")
              .takes_value(true)
              .multiple(false)
              .number_of_values(1)
              .use_delimiter(false))
+        .arg(Arg::with_name("header")
+             .long("header")
+             .short("H")
+             .help("Extra header for the request, as 'Name: value'")
+             .takes_value(true)
+             .multiple(true)
+             .number_of_values(1)
+             .use_delimiter(false))
+        .args_from_usage(
+             "-i --include 'Print the response status and headers'
+             -a --all 'Follow the pages of a listing and merge them (GET only)'")
+        .arg(Arg::from_usage("--raw 'Print the response body as received'")
+             .conflicts_with("select"))
+        .arg(Arg::from_usage("--select=[path] 'Print only part of the JSON response, \
+                              such as changes[].id'"))
         .args(&u_e_s_o_args())
+        .after_help("The exit status is 0 for 1xx and 2xx responses, 3 for 3xx, \
+                     4 for 4xx and 5 for 5xx.")
 }
@@ -16,6 +16,7 @@
 //
 
 use clap::{Arg, ArgMatches};
+use http::change::STAGES;
 
 pub fn value_of<'a>(matches: &'a ArgMatches, key: &str) -> &'a str {
     matches.value_of(key).unwrap_or("")
@@ -63,6 +64,24 @@ pub fn pipeline_arg<'a>() -> Vec<Arg<'a, 'a>> {
         ).visible_alias("for")]
 }
 
+pub fn tls_args<'a>() -> Vec<Arg<'a, 'a>> {
+    make_arg_vec![
+        "--ca-bundle=[file] 'CA certificates (PEM) to trust for the Delivery server'",
+        "--client-cert=[file] 'Client certificate (PEM) for mutual TLS'",
+        "--client-key=[file] 'Private key (PEM) of the client certificate'",
+        "--insecure 'Do not verify the TLS certificate of the Delivery server'"]
+}
+
+pub fn format_arg<'a>() -> Arg<'a, 'a> {
+    Arg::from_usage("--format=[format] 'Output format; json prints a result object on stdout'")
+        .possible_values(&["text", "json"])
+}
+
+pub fn until_arg<'a>() -> Arg<'a, 'a> {
+    Arg::from_usage("--until=[stage] 'Stage the change must pass (default: verify)'")
+        .possible_values(&STAGES)
+}
+
 fn_arg!(config_project_arg,
        "-c --config-json=[config-json] 'Path of a custom config.json file'");
 
@@ -0,0 +1,74 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::{project_arg, value_of, u_e_s_o_args};
+use clap::{Arg, App, SubCommand, ArgMatches};
+
+pub const SUBCOMMAND_NAME: &'static str = "changes";
+
+#[derive(Debug)]
+pub struct ChangesClapOptions<'n> {
+    pub state: &'n str,
+    pub submitter: &'n str,
+    pub pipeline: &'n str,
+    pub project: &'n str,
+    pub user: &'n str,
+    pub server: &'n str,
+    pub ent: &'n str,
+    pub org: &'n str,
+}
+impl<'n> Default for ChangesClapOptions<'n> {
+    fn default() -> Self {
+        ChangesClapOptions {
+            state: "open",
+            submitter: "",
+            pipeline: "",
+            project: "",
+            user: "",
+            server: "",
+            ent: "",
+            org: "",
+        }
+    }
+}
+
+impl<'n> ChangesClapOptions<'n> {
+    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
+        ChangesClapOptions {
+            state: value_of(&matches, "state"),
+            submitter: value_of(&matches, "submitter"),
+            pipeline: value_of(&matches, "pipeline"),
+            project: value_of(&matches, "project"),
+            user: value_of(&matches, "user"),
+            server: value_of(&matches, "server"),
+            ent: value_of(&matches, "ent"),
+            org: value_of(&matches, "org"),
+        }
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("List the changes of a project")
+        .args(&vec![project_arg()])
+        .arg(Arg::from_usage("--state=[state] 'Only list changes in this state'")
+             .possible_values(&["open", "merged", "delivered"])
+             .default_value("open"))
+        .arg(Arg::from_usage("-f --pipeline=[pipeline] 'Only list changes targeted for this pipeline'")
+             .visible_alias("for"))
+        .args_from_usage("--submitter=[submitter] 'Only list changes submitted by this user'")
+        .args(&u_e_s_o_args())
+}
@@ -0,0 +1,69 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::{pipeline_arg, patchset_arg, value_of};
+use clap::{Arg, App, SubCommand, ArgMatches};
+
+pub const SUBCOMMAND_NAME: &'static str = "comment";
+
+#[derive(Debug)]
+pub struct CommentClapOptions<'n> {
+    pub change: &'n str,
+    pub pipeline: &'n str,
+    pub patchset: &'n str,
+    pub message: &'n str,
+    pub file: &'n str,
+    pub line: &'n str,
+}
+impl<'n> Default for CommentClapOptions<'n> {
+    fn default() -> Self {
+        CommentClapOptions {
+            change: "",
+            pipeline: "master",
+            patchset: "latest",
+            message: "",
+            file: "",
+            line: "",
+        }
+    }
+}
+
+impl<'n> CommentClapOptions<'n> {
+    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
+        CommentClapOptions {
+            change: value_of(&matches, "change"),
+            pipeline: value_of(&matches, "pipeline"),
+            patchset: value_of(&matches, "patchset"),
+            message: value_of(&matches, "message"),
+            file: value_of(&matches, "file"),
+            line: value_of(&matches, "line"),
+        }
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Comment on a patchset of a change")
+        .args(&vec![patchset_arg()])
+        .args(&pipeline_arg())
+        .args_from_usage(
+            "[change] 'Change id (default: latest change for the current branch)'
+            -m --message=[message] 'Comment text (default: write it in $EDITOR)'
+            --file=[file] 'File of the patchset to comment on'")
+        .arg(Arg::from_usage(
+            "--line=[line] 'Line, or range of lines (e.g. 12-15), of --file to comment on'")
+             .requires("file"))
+}
@@ -0,0 +1,51 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::{pipeline_arg, value_of};
+use clap::{App, SubCommand, ArgMatches};
+
+pub const SUBCOMMAND_NAME: &'static str = "comments";
+
+#[derive(Debug)]
+pub struct CommentsClapOptions<'n> {
+    pub change: &'n str,
+    pub pipeline: &'n str,
+}
+impl<'n> Default for CommentsClapOptions<'n> {
+    fn default() -> Self {
+        CommentsClapOptions {
+            change: "",
+            pipeline: "master",
+        }
+    }
+}
+
+impl<'n> CommentsClapOptions<'n> {
+    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
+        CommentsClapOptions {
+            change: value_of(&matches, "change"),
+            pipeline: value_of(&matches, "pipeline"),
+        }
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("List the comment threads of a change")
+        .args(&pipeline_arg())
+        .args_from_usage(
+            "[change] 'Change id (default: latest change for the current branch)'")
+}
@@ -0,0 +1,54 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::{pipeline_arg, value_of};
+use clap::{App, SubCommand, ArgMatches};
+
+pub const SUBCOMMAND_NAME: &'static str = "deliver";
+
+#[derive(Debug)]
+pub struct DeliverClapOptions<'n> {
+    pub change: &'n str,
+    pub pipeline: &'n str,
+    pub non_interactive: bool,
+}
+impl<'n> Default for DeliverClapOptions<'n> {
+    fn default() -> Self {
+        DeliverClapOptions {
+            change: "",
+            pipeline: "master",
+            non_interactive: false,
+        }
+    }
+}
+
+impl<'n> DeliverClapOptions<'n> {
+    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
+        DeliverClapOptions {
+            change: value_of(&matches, "change"),
+            pipeline: value_of(&matches, "pipeline"),
+            non_interactive: matches.is_present("non-interactive"),
+        }
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Deliver a change that has passed acceptance")
+        .args(&pipeline_arg())
+        .args_from_usage(
+            "[change] 'Change id (default: latest change for the current branch)'")
+}
@@ -15,7 +15,7 @@
 // limitations under the License.
 //
 use cli::arguments::{pipeline_arg, patchset_arg, value_of};
-use clap::{App, SubCommand, ArgMatches};
+use clap::{Arg, App, SubCommand, ArgMatches};
 
 pub const SUBCOMMAND_NAME: &'static str = "diff";
 
@@ -25,6 +25,8 @@ pub struct DiffClapOptions<'n> {
     pub patchset: &'n str,
     pub pipeline: &'n str,
     pub local: bool,
+    pub from_patchset: &'n str,
+    pub to_patchset: &'n str,
 }
 impl<'n> Default for DiffClapOptions<'n> {
     fn default() -> Self {
@@ -33,6 +35,8 @@ impl<'n> Default for DiffClapOptions<'n> {
             patchset: "",
             pipeline: "master",
             local: false,
+            from_patchset: "",
+            to_patchset: "",
         }
     }
 }
@@ -44,6 +48,8 @@ impl<'n> DiffClapOptions<'n> {
             patchset: value_of(&matches, "patchset"),
             pipeline: value_of(&matches, "pipeline"),
             local: matches.is_present("local"),
+            from_patchset: value_of(&matches, "from-patchset"),
+            to_patchset: value_of(&matches, "to-patchset"),
         }
     }
 }
@@ -57,4 +63,10 @@ pub fn clap_subcommand<'c>() -> App<'c, 'c> {
             "<change> 'Name of the feature branch to compare'
             -l --local \
             'Diff against the local branch HEAD'")
+        .arg(Arg::from_usage(
+            "--from-patchset=[from] 'Diff from this patchset instead of the pipeline'")
+             .conflicts_with_all(&["local", "patchset"]))
+        .arg(Arg::from_usage(
+            "--to-patchset=[to] 'Patchset to diff up to (default: latest)'")
+             .requires("from-patchset"))
 }
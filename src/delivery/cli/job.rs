@@ -39,6 +39,7 @@ pub struct JobClapOptions<'n> {
     pub skip_default: bool,
     pub local: bool,
     pub docker_image: &'n str,
+    pub renew: bool,
 }
 
 impl<'n> Default for JobClapOptions<'n> {
@@ -62,6 +63,7 @@ impl<'n> Default for JobClapOptions<'n> {
             skip_default: false,
             local: false,
             docker_image: "",
+            renew: false,
         }
     }
 }
@@ -87,6 +89,7 @@ impl<'n> JobClapOptions<'n> {
             skip_default: matches.is_present("skip-default"),
             local: matches.is_present("local"),
             docker_image: value_of(&matches, "docker"),
+            renew: matches.is_present("renew"),
         }
     }
 }
@@ -103,7 +106,9 @@ pub fn clap_subcommand<'c>() -> App<'c, 'c> {
             "-S --shasum=[gitsha] 'Git SHA of change'",
             "--change-id=[id] 'The change ID'",
             "--skip-default 'skip default'",
-            "--docker=[image] 'Docker image'"])
+            "--docker=[image] 'Docker image'",
+            "--renew 'Renew the API token first if it expires soon \
+                      (SAML and token_helper tokens are only verified)'"])
         .args_from_usage("<stage> 'Stage for the run'
                           <phases> 'One or more phases'")
         .args(&u_e_s_o_args())
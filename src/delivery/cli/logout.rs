@@ -0,0 +1,51 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+use cli::arguments::value_of;
+use clap::{Arg, App, SubCommand, ArgMatches};
+
+pub const SUBCOMMAND_NAME: &'static str = "logout";
+
+#[derive(Debug)]
+pub struct LogoutClapOptions<'n> {
+    pub server: &'n str,
+    pub port: &'n str,
+}
+impl<'n> Default for LogoutClapOptions<'n> {
+    fn default() -> Self {
+        LogoutClapOptions {
+            server: "",
+            port: "",
+        }
+    }
+}
+
+impl<'n> LogoutClapOptions<'n> {
+    pub fn new(matches: &'n ArgMatches<'n>) -> Self {
+        LogoutClapOptions {
+            server: value_of(&matches, "server"),
+            port: value_of(&matches, "api-port"),
+        }
+    }
+}
+
+pub fn clap_subcommand<'c>() -> App<'c, 'c> {
+    SubCommand::with_name(SUBCOMMAND_NAME)
+        .about("Remove the stored API tokens of a Delivery server")
+        .args(&make_arg_vec![
+            "-s --server=[server] 'The Delivery server address'",
+            "--api-port=[api-port] 'Only remove the tokens for this port'"])
+}
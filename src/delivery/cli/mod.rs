@@ -25,7 +25,10 @@ use utils::say::{self, sayln, say};
 use errors::DeliveryError;
 use types::{ExitCode};
 use config::Config;
+use http;
+use project;
 use clap::{App, ArgMatches};
+use std::cell::RefCell;
 
 // Clap Arguments
 //
@@ -33,7 +36,7 @@ use clap::{App, ArgMatches};
 // the ClapAlias trait for arguments that we might depricate in the future
 #[macro_use]
 pub mod arguments;
-use cli::arguments::{non_interactive_arg, no_spinner_arg};
+use cli::arguments::{non_interactive_arg, no_spinner_arg, format_arg, tls_args};
 
 // Modules for setting up clap subcommand including their options and defaults,
 // as well as advanced subcommand match parsing (see local for an example).
@@ -47,6 +50,16 @@ pub mod job;
 pub mod token;
 pub mod setup;
 pub mod local;
+pub mod status;
+pub mod approve;
+pub mod deliver;
+pub mod changes;
+pub mod watch;
+pub mod comment;
+pub mod comments;
+pub mod patchsets;
+pub mod abandon;
+pub mod logout;
 mod spin;
 
 // Implemented sub-commands. Should handle everything after args have
@@ -58,6 +71,8 @@ pub fn run() {
 
     let app = make_app(&build_version);
     let app_matches = app.get_matches();
+    handle_format(&app_matches);
+    handle_tls(&app_matches);
 
     let cmd_result = match app_matches.subcommand() {
         (api::SUBCOMMAND_NAME, Some(matches)) => {
@@ -104,6 +119,56 @@ pub fn run() {
             let token_opts = token::TokenClapOptions::new(matches);
             command::token::run(token_opts)
         },
+        (logout::SUBCOMMAND_NAME, Some(matches)) => {
+            handle_spinner(&matches);
+            let logout_opts = logout::LogoutClapOptions::new(matches);
+            command::logout::run(logout_opts)
+        },
+        (status::SUBCOMMAND_NAME, Some(matches)) => {
+            handle_spinner(&matches);
+            let status_opts = status::StatusClapOptions::new(&matches);
+            command::status::run(status_opts)
+        },
+        (approve::SUBCOMMAND_NAME, Some(matches)) => {
+            handle_spinner(&matches);
+            let approve_opts = approve::ApproveClapOptions::new(&matches);
+            command::approve::run(approve_opts)
+        },
+        (deliver::SUBCOMMAND_NAME, Some(matches)) => {
+            handle_spinner(&matches);
+            let deliver_opts = deliver::DeliverClapOptions::new(&matches);
+            command::deliver::run(deliver_opts)
+        },
+        (changes::SUBCOMMAND_NAME, Some(matches)) => {
+            handle_spinner(&matches);
+            let changes_opts = changes::ChangesClapOptions::new(&matches);
+            command::changes::run(changes_opts)
+        },
+        (watch::SUBCOMMAND_NAME, Some(matches)) => {
+            handle_spinner(&matches);
+            let watch_opts = watch::WatchClapOptions::new(&matches);
+            command::watch::run(watch_opts)
+        },
+        (comment::SUBCOMMAND_NAME, Some(matches)) => {
+            handle_spinner(&matches);
+            let comment_opts = comment::CommentClapOptions::new(&matches);
+            command::comment::run(comment_opts)
+        },
+        (comments::SUBCOMMAND_NAME, Some(matches)) => {
+            handle_spinner(&matches);
+            let comments_opts = comments::CommentsClapOptions::new(&matches);
+            command::comments::run(comments_opts)
+        },
+        (patchsets::SUBCOMMAND_NAME, Some(matches)) => {
+            handle_spinner(&matches);
+            let patchsets_opts = patchsets::PatchsetsClapOptions::new(&matches);
+            command::patchsets::run(patchsets_opts)
+        },
+        (abandon::SUBCOMMAND_NAME, Some(matches)) => {
+            handle_spinner(&matches);
+            let abandon_opts = abandon::AbandonClapOptions::new(&matches);
+            command::abandon::run(abandon_opts)
+        },
         (local::SUBCOMMAND_NAME, Some(matches)) => {
             let local_opts = local::LocalClapOptions::new(matches);
             command::local::run(local_opts)
@@ -130,7 +195,13 @@ pub fn run() {
     match cmd_result {
         // You can exit with any integer, can also be used to bypass default
         // error handling if you handled an error and returned non-zero.
-        Ok(exit_status) => process::exit(exit_status),
+        Ok(exit_status) => {
+            say::result_set("status", if exit_status == 0 { "ok" } else { "failed" });
+            say::result_set("exit_code", &exit_status);
+            say::emit_result();
+            http::connection::log_request_stats();
+            process::exit(exit_status)
+        },
         // Handles DeliveryError and exits 1.
         Err(e) => exit_with(e, 1)
     }
@@ -141,6 +212,8 @@ fn make_app<'a>(version: &'a str) -> App<'a, 'a> {
         .version(version)
         .arg(no_spinner_arg().global(true))
         .arg(non_interactive_arg().global(true))
+        .arg(format_arg().global(true))
+        .args(&tls_args().into_iter().map(|a| a.global(true)).collect::<Vec<_>>())
         .subcommand(review::clap_subcommand())
         .subcommand(clone::clap_subcommand())
         .subcommand(checkout::clap_subcommand())
@@ -150,8 +223,18 @@ fn make_app<'a>(version: &'a str) -> App<'a, 'a> {
         .subcommand(job::clap_subcommand())
         .subcommand(api::clap_subcommand())
         .subcommand(token::clap_subcommand())
+        .subcommand(logout::clap_subcommand())
         .subcommand(spin::clap_subcommand())
         .subcommand(local::clap_subcommand())
+        .subcommand(status::clap_subcommand())
+        .subcommand(approve::clap_subcommand())
+        .subcommand(deliver::clap_subcommand())
+        .subcommand(changes::clap_subcommand())
+        .subcommand(watch::clap_subcommand())
+        .subcommand(comment::clap_subcommand())
+        .subcommand(comments::clap_subcommand())
+        .subcommand(patchsets::clap_subcommand())
+        .subcommand(abandon::clap_subcommand())
 }
 
 fn handle_spinner(matches: &ArgMatches) {
@@ -160,6 +243,69 @@ fn handle_spinner(matches: &ArgMatches) {
     };
 }
 
+// The value of a global argument, given either before or after the
+// subcommand
+fn global_value_of<'a>(app_matches: &'a ArgMatches, name: &str) -> Option<&'a str> {
+    let sub_value = match app_matches.subcommand() {
+        (_, Some(matches)) => matches.value_of(name),
+        _ => None
+    };
+    sub_value.or(app_matches.value_of(name))
+}
+
+fn global_is_present(app_matches: &ArgMatches, name: &str) -> bool {
+    match app_matches.subcommand() {
+        (_, Some(matches)) if matches.is_present(name) => true,
+        _ => app_matches.is_present(name)
+    }
+}
+
+fn handle_format(app_matches: &ArgMatches) {
+    if let Some(name) = app_matches.subcommand_name() {
+        say::result_set("command", name);
+    }
+    if global_value_of(app_matches, "format") == Some("json") {
+        say::turn_on_json_output()
+    }
+}
+
+// The TLS flags override the settings of cli.toml for every command
+#[derive(Default)]
+struct TlsFlags {
+    ca_bundle: String,
+    client_cert: String,
+    client_key: String,
+    insecure: bool
+}
+
+thread_local! {
+    static TLS_FLAGS: RefCell<TlsFlags> = RefCell::new(TlsFlags::default())
+}
+
+fn handle_tls(app_matches: &ArgMatches) {
+    let flags = TlsFlags {
+        ca_bundle: global_value_of(app_matches, "ca-bundle").unwrap_or("").to_string(),
+        client_cert: global_value_of(app_matches, "client-cert").unwrap_or("").to_string(),
+        client_key: global_value_of(app_matches, "client-key").unwrap_or("").to_string(),
+        insecure: global_is_present(app_matches, "insecure")
+    };
+    TLS_FLAGS.with(|f| *f.borrow_mut() = flags);
+}
+
+/// Apply the global flags of the command line to a loaded `Config`
+pub fn with_global_flags(config: Config) -> Config {
+    TLS_FLAGS.with(|f| {
+        let flags = f.borrow();
+        let mut config = config.set_ca_bundle(&flags.ca_bundle)
+            .set_client_cert(&flags.client_cert)
+            .set_client_key(&flags.client_key);
+        if flags.insecure {
+            config.insecure = Some(true);
+        }
+        config
+    })
+}
+
 fn exit_with(e: DeliveryError, i: isize) {
     sayln("red", e.description());
     match e.detail() {
@@ -167,6 +313,11 @@ fn exit_with(e: DeliveryError, i: isize) {
         None => {}
     }
     let x = i as ExitCode;
+    say::result_set("status", "error");
+    say::result_set("exit_code", &x);
+    say::result_set("error", &e.to_json());
+    say::emit_result();
+    http::connection::log_request_stats();
     process::exit(x)
 }
 
@@ -175,7 +326,26 @@ pub fn load_config(path: &PathBuf) -> Result<Config, DeliveryError> {
     let msg = format!("{}", path.display());
     sayln("yellow", &msg);
     let config = try!(Config::load_config(&cwd()));
-    Ok(config)
+    Ok(with_global_flags(config))
+}
+
+/// The root of the project the current directory belongs to
+pub fn project_path() -> Result<PathBuf, DeliveryError> {
+    project::root_dir(&cwd())
+}
+
+/// The name of the project the current directory belongs to
+pub fn project_from_cwd() -> Result<String, DeliveryError> {
+    project::project_from_dir(&cwd())
+}
+
+/// Return the project name or the one the current directory belongs to
+pub fn project_or_from_cwd(proj: &str) -> Result<String, DeliveryError> {
+    if proj.is_empty() {
+        project_from_cwd()
+    } else {
+        Ok(proj.to_string())
+    }
 }
 
 fn version() -> String {
@@ -191,7 +361,10 @@ fn build_git_sha() -> String {
 #[cfg(test)]
 mod tests {
     use cli;
-    use cli::{api, review, clone, checkout, diff, init, job, spin, token, setup};
+    use config::Config;
+    use cli::{api, review, clone, checkout, diff, init, job, spin, token, setup,
+              status, approve, deliver, changes, watch, comment, comments, patchsets, abandon,
+              logout};
 
     #[test]
     fn test_clap_api_options() {
@@ -213,6 +386,49 @@ mod tests {
         assert_eq!(api_opts.user, "vader");
     }
 
+    #[test]
+    fn test_clap_api_request_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "api", "patch", "orgs/sith",
+                                           "-d", "@-", "-H", "If-Match: abc",
+                                           "--header", "X-Trace: 1", "--raw", "-i"]);
+        let api_matches = matches.subcommand_matches(api::SUBCOMMAND_NAME).unwrap();
+        let api_opts = api::ApiClapOptions::new(&api_matches);
+        assert_eq!(api_opts.method, "patch");
+        assert_eq!(api_opts.data, "@-");
+        assert_eq!(api_opts.headers, vec!["If-Match: abc", "X-Trace: 1"]);
+        assert!(api_opts.raw);
+        assert!(api_opts.include);
+
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "api", "head", "orgs"]);
+        let api_matches = matches.subcommand_matches(api::SUBCOMMAND_NAME).unwrap();
+        let api_opts = api::ApiClapOptions::new(&api_matches);
+        assert_eq!(api_opts.method, "head");
+        assert!(api_opts.headers.is_empty());
+        assert!(!api_opts.raw);
+        assert!(!api_opts.include);
+    }
+
+    #[test]
+    fn test_clap_api_listing_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "api", "get",
+                                           "orgs/o/projects/p/changes?limit=10",
+                                           "--all", "--select", "changes[].id"]);
+        let api_matches = matches.subcommand_matches(api::SUBCOMMAND_NAME).unwrap();
+        let api_opts = api::ApiClapOptions::new(&api_matches);
+        assert!(api_opts.all);
+        assert_eq!(api_opts.select, "changes[].id");
+
+        let app = cli::make_app(&build_version);
+        let result = app.get_matches_from_safe(vec!["delivery", "api", "get", "orgs",
+                                                "--raw", "--select", "orgs"]);
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_clap_review_options() {
         let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
@@ -226,6 +442,22 @@ mod tests {
         assert_eq!(review_opts.no_open, true);
         assert_eq!(review_opts.auto_bump, true);
         assert_eq!(review_opts.edit, true);
+        assert_eq!(review_opts.wait, false);
+
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "review", "--wait",
+                                           "--until", "build"]);
+        let review_matches = matches.subcommand_matches(review::SUBCOMMAND_NAME).unwrap();
+        let review_opts = review::ReviewClapOptions::new(&review_matches);
+        assert_eq!(review_opts.wait, true);
+        assert_eq!(review_opts.until, "build");
+        assert_eq!(review_opts.stack, false);
+
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "review", "--stack"]);
+        let review_matches = matches.subcommand_matches(review::SUBCOMMAND_NAME).unwrap();
+        let review_opts = review::ReviewClapOptions::new(&review_matches);
+        assert_eq!(review_opts.stack, true);
     }
 
     #[test]
@@ -274,6 +506,15 @@ mod tests {
         assert_eq!(diff_opts.patchset, "p4tchs3t");
         assert_eq!(diff_opts.pipeline, "coolest");
         assert_eq!(diff_opts.local, true);
+
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "diff", "change-me",
+                                           "--from-patchset", "1", "--to-patchset", "3"]);
+        let diff_matches = matches.subcommand_matches(diff::SUBCOMMAND_NAME).unwrap();
+        let diff_opts = diff::DiffClapOptions::new(&diff_matches);
+        assert_eq!(diff_opts.from_patchset, "1");
+        assert_eq!(diff_opts.to_patchset, "3");
+        assert_eq!(diff_opts.local, false);
     }
 
     #[test]
@@ -314,7 +555,8 @@ mod tests {
                         "-u", "naruto", "-s", "manga.com", "-e", "shippuden", "-o",
                         "akatsuki", "-f", "sharingan", "-j", "/path", "-p", "uchiha",
                         "-P", "latest", "--change-id", "super-cool-id", "-g", "powerful-url",
-                        "-S", "SHA", "-b", "evil", "--skip-default", "-l", "--docker", "uzumaki"];
+                        "-S", "SHA", "-b", "evil", "--skip-default", "-l", "--docker", "uzumaki",
+                        "--renew"];
         let matches = app.get_matches_from(job_cmd);
         assert_eq!(Some("job"), matches.subcommand_name());
         let job_matches = matches.subcommand_matches(job::SUBCOMMAND_NAME).unwrap();
@@ -337,6 +579,7 @@ mod tests {
         assert_eq!(job_opts.docker_image, "uzumaki");
         assert_eq!(job_opts.local, true);
         assert_eq!(job_opts.skip_default, true);
+        assert_eq!(job_opts.renew, true);
     }
 
     #[test]
@@ -356,7 +599,8 @@ mod tests {
         let app = cli::make_app(&build_version);
         let matches = app.get_matches_from(vec!["delivery", "token", "-e", "fellowship",
                                            "-u", "gandalf", "-s", "lord.of.the.rings.com",
-                                           "--api-port", "1111", "--verify", "--saml=true"]);
+                                           "--api-port", "1111", "--verify", "--saml=true",
+                                           "--saml-callback", "--renew"]);
         assert_eq!(Some("token"), matches.subcommand_name());
         let token_matches = matches.subcommand_matches(token::SUBCOMMAND_NAME).unwrap();
         let token_opts = token::TokenClapOptions::new(&token_matches);
@@ -366,6 +610,38 @@ mod tests {
         assert_eq!(token_opts.user, "gandalf");
         assert_eq!(token_opts.verify, true);
         assert_eq!(token_opts.saml, Some(true));
+        assert_eq!(token_opts.saml_callback, true);
+        assert_eq!(token_opts.renew, true);
+        assert_eq!(token_opts.action, "");
+    }
+
+    #[test]
+    fn test_clap_token_action_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "token", "revoke",
+                                           "-s", "lord.of.the.rings.com", "-u", "gandalf"]);
+        let token_matches = matches.subcommand_matches(token::SUBCOMMAND_NAME).unwrap();
+        let token_opts = token::TokenClapOptions::new(&token_matches);
+        assert_eq!(token_opts.action, "revoke");
+        assert_eq!(token_opts.server, "lord.of.the.rings.com");
+        assert_eq!(token_opts.user, "gandalf");
+
+        let app = cli::make_app(&build_version);
+        assert!(app.get_matches_from_safe(vec!["delivery", "token", "forget"]).is_err());
+    }
+
+    #[test]
+    fn test_clap_logout_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "logout", "-s", "mordor.com",
+                                           "--api-port", "8443"]);
+        assert_eq!(Some("logout"), matches.subcommand_name());
+        let logout_matches = matches.subcommand_matches(logout::SUBCOMMAND_NAME).unwrap();
+        let logout_opts = logout::LogoutClapOptions::new(&logout_matches);
+        assert_eq!(logout_opts.server, "mordor.com");
+        assert_eq!(logout_opts.port, "8443");
     }
 
     #[test]
@@ -385,4 +661,169 @@ mod tests {
         assert_eq!(setup_opts.pipeline, "p");
         assert_eq!(setup_opts.path, "/my/config/cli.toml");
     }
+
+    #[test]
+    fn test_clap_status_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "status", "abc-123",
+                                           "-f", "custom-pipe"]);
+        assert_eq!(Some("status"), matches.subcommand_name());
+        let status_matches = matches.subcommand_matches(status::SUBCOMMAND_NAME).unwrap();
+        let status_opts = status::StatusClapOptions::new(&status_matches);
+        assert_eq!(status_opts.change, "abc-123");
+        assert_eq!(status_opts.pipeline, "custom-pipe");
+    }
+
+    #[test]
+    fn test_clap_approve_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "approve", "abc-123",
+                                           "--non-interactive"]);
+        assert_eq!(Some("approve"), matches.subcommand_name());
+        let approve_matches = matches.subcommand_matches(approve::SUBCOMMAND_NAME).unwrap();
+        let approve_opts = approve::ApproveClapOptions::new(&approve_matches);
+        assert_eq!(approve_opts.change, "abc-123");
+        assert_eq!(approve_opts.non_interactive, true);
+    }
+
+    #[test]
+    fn test_clap_deliver_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "deliver", "-f", "custom-pipe"]);
+        assert_eq!(Some("deliver"), matches.subcommand_name());
+        let deliver_matches = matches.subcommand_matches(deliver::SUBCOMMAND_NAME).unwrap();
+        let deliver_opts = deliver::DeliverClapOptions::new(&deliver_matches);
+        assert_eq!(deliver_opts.change, "");
+        assert_eq!(deliver_opts.pipeline, "custom-pipe");
+        assert_eq!(deliver_opts.non_interactive, false);
+    }
+
+    #[test]
+    fn test_clap_changes_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "changes", "--state", "merged",
+                                           "--submitter", "kirk", "-f", "custom-pipe",
+                                           "-p", "enterprise", "-o", "starfleet"]);
+        assert_eq!(Some("changes"), matches.subcommand_name());
+        let changes_matches = matches.subcommand_matches(changes::SUBCOMMAND_NAME).unwrap();
+        let changes_opts = changes::ChangesClapOptions::new(&changes_matches);
+        assert_eq!(changes_opts.state, "merged");
+        assert_eq!(changes_opts.submitter, "kirk");
+        assert_eq!(changes_opts.pipeline, "custom-pipe");
+        assert_eq!(changes_opts.project, "enterprise");
+        assert_eq!(changes_opts.org, "starfleet");
+
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "changes"]);
+        let changes_matches = matches.subcommand_matches(changes::SUBCOMMAND_NAME).unwrap();
+        let changes_opts = changes::ChangesClapOptions::new(&changes_matches);
+        assert_eq!(changes_opts.state, "open");
+        assert_eq!(changes_opts.pipeline, "");
+    }
+
+    #[test]
+    fn test_clap_watch_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "watch", "abc123",
+                                           "--until", "acceptance", "-f", "custom-pipe"]);
+        assert_eq!(Some("watch"), matches.subcommand_name());
+        let watch_matches = matches.subcommand_matches(watch::SUBCOMMAND_NAME).unwrap();
+        let watch_opts = watch::WatchClapOptions::new(&watch_matches);
+        assert_eq!(watch_opts.change, "abc123");
+        assert_eq!(watch_opts.until, "acceptance");
+        assert_eq!(watch_opts.pipeline, "custom-pipe");
+    }
+
+    #[test]
+    fn test_clap_comment_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "comment", "abc123",
+                                           "-m", "Make it so", "-P", "2",
+                                           "--file", "recipes/default.rb",
+                                           "--line", "12-15"]);
+        assert_eq!(Some("comment"), matches.subcommand_name());
+        let comment_matches = matches.subcommand_matches(comment::SUBCOMMAND_NAME).unwrap();
+        let comment_opts = comment::CommentClapOptions::new(&comment_matches);
+        assert_eq!(comment_opts.change, "abc123");
+        assert_eq!(comment_opts.message, "Make it so");
+        assert_eq!(comment_opts.patchset, "2");
+        assert_eq!(comment_opts.file, "recipes/default.rb");
+        assert_eq!(comment_opts.line, "12-15");
+    }
+
+    #[test]
+    fn test_clap_comments_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "comments", "abc123",
+                                           "--for", "custom-pipe"]);
+        assert_eq!(Some("comments"), matches.subcommand_name());
+        let comments_matches = matches.subcommand_matches(comments::SUBCOMMAND_NAME).unwrap();
+        let comments_opts = comments::CommentsClapOptions::new(&comments_matches);
+        assert_eq!(comments_opts.change, "abc123");
+        assert_eq!(comments_opts.pipeline, "custom-pipe");
+    }
+
+    #[test]
+    fn test_clap_patchsets_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "patchsets", "add-lint",
+                                           "--for", "custom-pipe"]);
+        assert_eq!(Some("patchsets"), matches.subcommand_name());
+        let patchsets_matches = matches.subcommand_matches(patchsets::SUBCOMMAND_NAME).unwrap();
+        let patchsets_opts = patchsets::PatchsetsClapOptions::new(&patchsets_matches);
+        assert_eq!(patchsets_opts.change, "add-lint");
+        assert_eq!(patchsets_opts.pipeline, "custom-pipe");
+    }
+
+    #[test]
+    fn test_clap_abandon_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "abandon", "abc123",
+                                           "-f", "custom-pipe", "--non-interactive"]);
+        assert_eq!(Some("abandon"), matches.subcommand_name());
+        let abandon_matches = matches.subcommand_matches(abandon::SUBCOMMAND_NAME).unwrap();
+        let abandon_opts = abandon::AbandonClapOptions::new(&abandon_matches);
+        assert_eq!(abandon_opts.change, "abc123");
+        assert_eq!(abandon_opts.pipeline, "custom-pipe");
+        assert_eq!(abandon_opts.non_interactive, true);
+    }
+
+    #[test]
+    fn test_clap_global_format_option() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "--format", "json",
+                                           "status", "abc123"]);
+        assert_eq!(Some("json"), matches.value_of("format"));
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "status", "abc123",
+                                           "--format", "json"]);
+        let status_matches = matches.subcommand_matches(status::SUBCOMMAND_NAME).unwrap();
+        assert_eq!(Some("json"), status_matches.value_of("format"));
+        let app = cli::make_app(&build_version);
+        let result = app.get_matches_from_safe(vec!["delivery", "--format", "yaml", "status"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_clap_global_tls_options() {
+        let build_version = format!("{} {}", cli::version(), cli::build_git_sha());
+        let app = cli::make_app(&build_version);
+        let matches = app.get_matches_from(vec!["delivery", "--ca-bundle", "/tmp/ca.pem",
+                                           "status", "--insecure"]);
+        cli::handle_tls(&matches);
+        let config = cli::with_global_flags(Config::default());
+        assert_eq!(Some("/tmp/ca.pem".to_string()), config.ca_bundle);
+        assert_eq!(None, config.client_cert);
+        assert_eq!(Some(true), config.insecure);
+    }
 }
@@ -14,8 +14,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 //
-use cli::arguments::{pipeline_arg, no_open_arg, value_of, auto_bump};
-use clap::{App, SubCommand, ArgMatches};
+use cli::arguments::{pipeline_arg, no_open_arg, value_of, auto_bump, until_arg};
+use clap::{Arg, App, SubCommand, ArgMatches};
 
 pub const SUBCOMMAND_NAME: &'static str = "review";
 
@@ -25,6 +25,9 @@ pub struct ReviewClapOptions<'n> {
     pub no_open: bool,
     pub auto_bump: bool,
     pub edit: bool,
+    pub wait: bool,
+    pub until: &'n str,
+    pub stack: bool,
 }
 impl<'n> Default for ReviewClapOptions<'n> {
     fn default() -> Self {
@@ -33,6 +36,9 @@ impl<'n> Default for ReviewClapOptions<'n> {
             no_open: false,
             auto_bump: false,
             edit: false,
+            wait: false,
+            until: "verify",
+            stack: false,
         }
     }
 }
@@ -44,6 +50,9 @@ impl<'n> ReviewClapOptions<'n> {
             no_open: matches.is_present("no-open"),
             auto_bump: matches.is_present("auto-bump"),
             edit: matches.is_present("edit"),
+            wait: matches.is_present("wait"),
+            until: value_of(&matches, "until"),
+            stack: matches.is_present("stack"),
         }
     }
 }
@@ -52,6 +61,12 @@ pub fn clap_subcommand<'c>() -> App<'c, 'c> {
     SubCommand::with_name(SUBCOMMAND_NAME)
         .about("Submit current branch for review")
         .args(&vec![no_open_arg(), auto_bump()])
-        .args_from_usage("-e --edit 'Edit change title and description'")
+        .args_from_usage("-e --edit 'Edit change title and description'
+                          -w --wait 'Wait for the change to pass a stage of the pipeline'")
+        .arg(until_arg().requires("wait"))
+        .arg(Arg::from_usage(
+            "--stack 'Submit every branch between the pipeline and the current branch \
+             as its own change'")
+             .conflicts_with_all(&["edit", "wait"]))
         .args(&pipeline_arg())
 }
@@ -21,25 +21,32 @@ pub const SUBCOMMAND_NAME: &'static str = "token";
 
 #[derive(Debug)]
 pub struct TokenClapOptions<'n> {
+    // "list" or "revoke"; empty to request or verify a token
+    pub action: &'n str,
     pub server: &'n str,
     pub port: &'n str,
     pub ent: &'n str,
     pub user: &'n str,
     pub verify: bool,
+    pub renew: bool,
     pub raw: bool,
     // if None, use what the server tells us on its /e/<ent>/saml/enabled endpoint
     pub saml: Option<bool>,
+    pub saml_callback: bool,
 }
 impl<'n> Default for TokenClapOptions<'n> {
     fn default() -> Self {
         TokenClapOptions {
+            action: "",
             server: "",
             port: "",
             ent: "",
             user: "",
             verify: false,
+            renew: false,
             raw: false,
             saml: None,
+            saml_callback: false,
         }
     }
 }
@@ -47,28 +54,37 @@ impl<'n> Default for TokenClapOptions<'n> {
 impl<'n> TokenClapOptions<'n> {
     pub fn new(matches: &'n ArgMatches<'n>) -> Self {
         TokenClapOptions {
+            action: value_of(&matches, "action"),
             server: value_of(&matches, "server"),
             port: value_of(&matches, "api-port"),
             ent: value_of(&matches, "ent"),
             user: value_of(&matches, "user"),
             verify: matches.is_present("verify"),
+            renew: matches.is_present("renew"),
             raw: matches.is_present("raw"),
             saml: match value_of(&matches, "saml") {
               "true" => Some(true),
               "false" => Some(false),
               _ => None,
             },
+            saml_callback: matches.is_present("saml-callback"),
         }
     }
 }
 
 pub fn clap_subcommand<'c>() -> App<'c, 'c> {
     SubCommand::with_name(SUBCOMMAND_NAME)
-        .about("Create a local API token")
+        .about("Create a local API token, or list and revoke the stored ones")
+        .arg(Arg::from_usage("[action] 'list: show the stored tokens, \
+                              revoke: revoke the token of the user'")
+             .possible_values(&["list", "revoke"]))
         .args(&u_e_s_o_args())
         .args(&make_arg_vec![
             "--raw 'Output only the raw token string'",
             "--verify 'Verify the Token has expired'",
+            "--renew 'Request a new token if the stored one expires soon \
+                      (SAML and token_helper tokens are only verified)'",
             "--api-port=[api-port] 'Port for Delivery server'",
-            "--saml=[true/false] 'Use SAML authentication (overrides Delivery server)'"])
+            "--saml=[true/false] 'Use SAML authentication (overrides Delivery server)'",
+            "--saml-callback 'Get the SAML token from the browser instead of pasting it'"])
 }
@@ -0,0 +1,67 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli::abandon::AbandonClapOptions;
+use project;
+use utils;
+use utils::say::{self, say, sayln};
+use types::{DeliveryResult, ExitCode};
+use git;
+use http::change;
+use command::approve::{change_config, confirm, ask, exit_code_for};
+
+pub fn run(opts: AbandonClapOptions) -> DeliveryResult<ExitCode> {
+    sayln("green", "Chef Delivery");
+    let config = try!(change_config(opts.pipeline, opts.non_interactive));
+    let change_id = try!(project::change_id_or_from_head(&utils::cwd(), &config, opts.change));
+    let change = try!(change::get_change(&config, &change_id));
+    say::result_set("change_id", &change_id[..]);
+    say::result_set("abandoned", &false);
+
+    if !try!(confirm(&config, "Abandon", &change)) {
+        sayln("white", "Nothing abandoned.");
+        return Ok(0)
+    }
+    if let Err(e) = change::abandon(&config, &change_id) {
+        return exit_code_for(e)
+    }
+    say("white", "Change ");
+    say("yellow", &change_id);
+    sayln("green", " abandoned");
+    say::result_set("abandoned", &true);
+
+    let branches = try!(git::review_branches(&utils::cwd(), &change.topic, &change.target));
+    if branches.is_empty() {
+        return Ok(0)
+    }
+    let question = format!("Delete the local review branches {}?", branches.join(", "));
+    if !try!(ask(&config, &question)) {
+        return Ok(0)
+    }
+    let head = git::get_head(&utils::cwd()).ok();
+    for branch in branches.iter() {
+        if head.as_ref() == Some(branch) {
+            sayln("yellow", &format!("Not deleting {}, it is checked out", branch));
+            continue;
+        }
+        try!(git::delete_branch(&utils::cwd(), branch));
+        say("white", "Deleted branch ");
+        sayln("yellow", branch);
+        say::result_push("deleted_branches", &branch[..]);
+    }
+    Ok(0)
+}
@@ -15,38 +15,200 @@
 // limitations under the License.
 //
 
+use cli;
 use cli::api::ApiClapOptions;
-use types::{DeliveryResult, ExitCode};
+use types::{DeliveryResult, ExitCode, EXIT_HTTP_REDIRECTION,
+            EXIT_HTTP_CLIENT_ERROR, EXIT_HTTP_SERVER_ERROR};
 use errors::{DeliveryError, Kind};
 use config::Config;
-use utils::cwd;
+use utils::{cwd, read_file};
 use http::APIClient;
-use hyper::status::StatusCode;
+use http::paging::Listing;
+use hyper::client::response::Response as HyperResponse;
+use hyper::header::Headers;
+use hyper::status::{StatusClass, StatusCode};
+use rustc_serialize::json::Json;
+use utils::say;
+use utils::select::Selector;
+use std::collections::BTreeMap;
+use std::io;
+use std::io::prelude::*;
+use std::path::PathBuf;
 
 pub fn run(opts: ApiClapOptions) -> DeliveryResult<ExitCode> {
-    let mut config = try!(Config::load_config(&cwd()));
+    let mut config = cli::with_global_flags(try!(Config::load_config(&cwd())));
     config = config.set_user(opts.user)
         .set_server(opts.server)
         .set_api_port(opts.api_port)
         .set_enterprise(opts.ent);
-    let client = try!(APIClient::from_config(&config));
+    let mut client = try!(APIClient::from_config(&config));
+    for header in opts.headers.iter() {
+        let (name, value) = try!(parse_header(header));
+        client.add_header(name, value);
+    }
+    let data = try!(request_body(opts.data));
+    let selector = if opts.select.is_empty() {
+        None
+    } else {
+        Some(try!(Selector::parse(opts.select)))
+    };
+    if opts.all && opts.method != "get" {
+        let msg = "--all only applies to get requests".to_string();
+        return Err(DeliveryError{ kind: Kind::OptionConstraint, detail: Some(msg) })
+    }
     let mut result = match opts.method {
         "get" => try!(client.get(opts.path)),
-        "post" => try!(client.post(opts.path, opts.data)),
-        "put" => try!(client.put(opts.path, opts.data)),
+        "post" => try!(client.post(opts.path, &data)),
+        "put" => try!(client.put(opts.path, &data)),
+        "patch" => try!(client.patch(opts.path, &data)),
         "delete" => try!(client.delete(opts.path)),
+        "head" => try!(client.head(opts.path)),
         _ => return Err(DeliveryError{ kind: Kind::UnsupportedHttpMethod,
                                        detail: None })
     };
-    match result.status {
-        StatusCode::NoContent => {},
-        StatusCode::InternalServerError => {
-            return Err(DeliveryError{ kind: Kind::InternalServerError, detail: None})
+    let mut body = Vec::new();
+    try!(result.read_to_end(&mut body));
+    if opts.all && result.status.is_success() {
+        match try!(fetch_all_pages(&client, opts.path, &body)) {
+            Ok(merged) => body = merged,
+            Err((failed, failed_body)) => {
+                result = failed;
+                body = failed_body;
+            }
+        }
+    }
+    let text = String::from_utf8_lossy(&body).into_owned();
+    let json = if opts.raw {
+        None
+    } else {
+        Json::from_str(&text).ok().map(|j| match selector {
+            Some(ref s) => s.apply(&j),
+            None => j
+        })
+    };
+    say::result_set("status_code", &result.status.to_u16());
+    if say::is_json_output() {
+        if opts.include {
+            say::result_set("headers", &headers_map(&result.headers));
+        }
+        if !body.is_empty() {
+            match json {
+                Some(ref j) => say::result_set("body", j),
+                None => say::result_set("body", &text)
+            }
+        }
+    } else {
+        if opts.include {
+            println!("{} {}", result.version, result.status);
+            for header in result.headers.iter() {
+                println!("{}: {}", header.name(), header.value_string());
+            }
+            println!("");
+        }
+        if opts.raw {
+            try!(io::stdout().write_all(&body));
+        } else if !body.is_empty() {
+            match json {
+                Some(ref j) if selector.is_some() => print_selected(j),
+                Some(ref j) => println!("{}", j.pretty()),
+                None => println!("{}", text)
+            }
+        }
+    }
+    Ok(status_exit_code(result.status))
+}
+
+// Follow the pages of the listing whose first page is `first`. Returns
+// the merged listing, or the first page that did not come back with a
+// success, along with its body.
+fn fetch_all_pages(client: &APIClient, path: &str, first: &[u8])
+                   -> DeliveryResult<Result<Vec<u8>, (HyperResponse, Vec<u8>)>> {
+    let mut listing = try!(Listing::start(&client.api_url(""), path,
+                                          &String::from_utf8_lossy(first)));
+    while let Some(next) = listing.next_path() {
+        debug!("Fetching the next page: {}", next);
+        let mut response = try!(client.get(&next));
+        let mut body = Vec::new();
+        try!(response.read_to_end(&mut body));
+        if !response.status.is_success() {
+            return Ok(Err((response, body)))
+        }
+        try!(listing.add(&next, &String::from_utf8_lossy(&body)));
+    }
+    Ok(Ok(listing.merged().to_string().into_bytes()))
+}
+
+// Print a selection the way scripts want it: strings without quotes,
+// and arrays of plain values one per line.
+fn print_selected(value: &Json) {
+    match *value {
+        Json::Array(ref items) if items.iter().all(is_plain) => {
+            for item in items.iter() {
+                println!("{}", plain(item));
+            }
+        },
+        _ if is_plain(value) => println!("{}", plain(value)),
+        _ => println!("{}", value.pretty())
+    }
+}
+
+fn is_plain(value: &Json) -> bool {
+    !value.is_array() && !value.is_object()
+}
+
+fn plain(value: &Json) -> String {
+    match *value {
+        Json::String(ref s) => s.clone(),
+        _ => value.to_string()
+    }
+}
+
+// The body of the request: the `--data` value itself, the content of
+// the file it names as `@file`, or stdin when it is `@-`.
+fn request_body(data: &str) -> DeliveryResult<String> {
+    if data == "@-" {
+        let mut body = String::new();
+        try!(io::stdin().read_to_string(&mut body));
+        Ok(body)
+    } else if data.starts_with("@") {
+        let path = PathBuf::from(&data[1..]);
+        read_file(&path).map_err(|e| {
+            let msg = format!("Unable to read {}: {:?}", path.display(), e.kind);
+            DeliveryError{ kind: Kind::OptionConstraint, detail: Some(msg) }
+        })
+    } else {
+        Ok(data.to_string())
+    }
+}
+
+// Split a `-H 'Name: value'` argument into its name and value.
+fn parse_header(header: &str) -> DeliveryResult<(&str, &str)> {
+    let mut parts = header.splitn(2, ':');
+    match (parts.next(), parts.next()) {
+        (Some(name), Some(value)) if !name.trim().is_empty() => {
+            Ok((name.trim(), value.trim()))
         },
         _ => {
-            let pretty_json = try!(APIClient::extract_pretty_json(&mut result));
-            println!("{}", pretty_json);
+            let msg = format!("Invalid header '{}'; expected 'Name: value'", header);
+            Err(DeliveryError{ kind: Kind::OptionConstraint, detail: Some(msg) })
         }
-    };
-    Ok(0)
+    }
+}
+
+fn headers_map(headers: &Headers) -> BTreeMap<String, String> {
+    headers.iter()
+        .map(|h| (h.name().to_string(), h.value_string()))
+        .collect()
+}
+
+// Scripts can tell the outcome of the request from the exit status
+// alone: 0 on success, 3, 4 or 5 for the 3xx, 4xx and 5xx classes.
+fn status_exit_code(status: StatusCode) -> ExitCode {
+    match status.class() {
+        StatusClass::Informational | StatusClass::Success => 0,
+        StatusClass::Redirection => EXIT_HTTP_REDIRECTION,
+        StatusClass::ClientError => EXIT_HTTP_CLIENT_ERROR,
+        StatusClass::ServerError => EXIT_HTTP_SERVER_ERROR,
+        StatusClass::NoClass => 1
+    }
 }
@@ -0,0 +1,109 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::io;
+use cli::approve::ApproveClapOptions;
+use cli::{load_config, project_from_cwd};
+use config::Config;
+use project;
+use utils;
+use utils::say::{self, say, sayln};
+use errors::{DeliveryError, Kind};
+use types::{DeliveryResult, ExitCode, EXIT_NOT_READY, EXIT_FORBIDDEN};
+use http::change::{self, Change};
+
+pub fn run(opts: ApproveClapOptions) -> DeliveryResult<ExitCode> {
+    sayln("green", "Chef Delivery");
+    let config = try!(change_config(opts.pipeline, opts.non_interactive));
+    let change_id = try!(project::change_id_or_from_head(&utils::cwd(), &config, opts.change));
+    let change = try!(change::get_change(&config, &change_id));
+    say::result_set("change_id", &change_id[..]);
+    say::result_set("approved", &false);
+
+    if !change.stage_passed("verify") {
+        sayln("red", &format!("Change {} has not passed verify and can not \
+                               be approved yet.", change_id));
+        return Ok(EXIT_NOT_READY)
+    }
+
+    if !try!(confirm(&config, "Approve", &change)) {
+        sayln("white", "Nothing approved.");
+        return Ok(0)
+    }
+    match change::approve(&config, &change_id) {
+        Ok(_) => {
+            say("white", "Change ");
+            say("yellow", &change_id);
+            sayln("green", " approved");
+            say::result_set("approved", &true);
+            Ok(0)
+        },
+        Err(e) => exit_code_for(e)
+    }
+}
+
+// Load the config for an action on a change of the current project
+pub fn change_config(pipeline: &str, non_interactive: bool) -> DeliveryResult<Config> {
+    let mut config = try!(load_config(&utils::cwd()));
+    let proj = try!(project_from_cwd());
+    config = config.set_pipeline(pipeline)
+        .set_project(&proj);
+    if non_interactive {
+        config.non_interactive = Some(true);
+    }
+    Ok(config)
+}
+
+// Ask the user to confirm the action, unless we are non-interactive
+pub fn confirm(config: &Config, action: &str, change: &Change) -> DeliveryResult<bool> {
+    let title = change.title.clone().unwrap_or(change.topic.clone());
+    ask(config, &format!("{} change {} ({})?", action, change.id, title))
+}
+
+// Ask a yes/no question; the answer is yes when we are non-interactive
+pub fn ask(config: &Config, question: &str) -> DeliveryResult<bool> {
+    if config.non_interactive.unwrap_or(false) {
+        return Ok(true)
+    }
+    let mut answer = String::new();
+    say("yellow", &format!("{} y/n: ", question));
+    try!(io::stdin().read_line(&mut answer));
+    debug!("You answered '{}'", answer.trim());
+    Ok(answer.trim() == "y")
+}
+
+// Turn the refusals of the server into their own exit codes, so
+// scripts can tell them apart from other failures
+pub fn exit_code_for(e: DeliveryError) -> DeliveryResult<ExitCode> {
+    match e.kind {
+        Kind::ChangeNotReady => {
+            sayln("red", &format!("{}", e));
+            if let Some(detail) = e.detail() {
+                sayln("red", &detail);
+            }
+            Ok(EXIT_NOT_READY)
+        },
+        Kind::Forbidden => {
+            sayln("red", &format!("{}", e));
+            if let Some(detail) = e.detail() {
+                sayln("red", &detail);
+            }
+            Ok(EXIT_FORBIDDEN)
+        },
+        _ => Err(e)
+    }
+}
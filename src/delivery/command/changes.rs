@@ -0,0 +1,82 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli::changes::ChangesClapOptions;
+use cli::{load_config, project_or_from_cwd};
+use utils;
+use utils::say::{self, say, sayln};
+use types::{DeliveryResult, ExitCode};
+use git;
+use http::change::{self, ChangeFilter, ChangeSummary};
+
+pub fn run(opts: ChangesClapOptions) -> DeliveryResult<ExitCode> {
+    sayln("green", "Chef Delivery");
+    let mut config = try!(load_config(&utils::cwd()));
+    let proj = try!(project_or_from_cwd(opts.project));
+    config = config.set_user(opts.user)
+        .set_server(opts.server)
+        .set_enterprise(opts.ent)
+        .set_organization(opts.org)
+        .set_project(&proj);
+    let filter = ChangeFilter {
+        state: opts.state.to_string(),
+        pipeline: non_empty(opts.pipeline),
+        submitter: non_empty(opts.submitter)
+    };
+    let changes = try!(change::list(&config, &filter));
+    say::result_set_encoded("changes", &changes);
+    if changes.is_empty() {
+        sayln("white", &format!("No {} changes found for project {}", filter.state, proj));
+        return Ok(0)
+    }
+    sayln("magenta", &format!("{:<36}  {:<30}  {:<12}  {:<24}  {:<10}  {}",
+                              "ID", "TITLE", "SUBMITTER", "TOPIC", "PIPELINE", "STAGE"));
+    for c in changes.iter() {
+        say_change_line(c);
+    }
+    Ok(0)
+}
+
+fn say_change_line(c: &ChangeSummary) {
+    let title = c.title.clone().unwrap_or(String::new());
+    let submitter = c.submit_by.clone().unwrap_or(String::new());
+    let pipeline = c.target.clone().unwrap_or(String::new());
+    // The topic is what 'delivery checkout' expects
+    let topic = git::checkout_branch_name(&c.topic, "latest");
+    say("yellow", &format!("{:<36}  ", c.id));
+    say("white", &format!("{:<30}  {:<12}  ", truncate(&title, 30), submitter));
+    say("cyan", &format!("{:<24}  ", topic));
+    say("magenta", &format!("{:<10}  ", pipeline));
+    sayln("white", c.current_stage().unwrap_or("-"));
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        text.to_string()
+    } else {
+        let cut: String = text.chars().take(width - 3).collect();
+        format!("{}...", cut)
+    }
+}
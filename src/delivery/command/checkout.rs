@@ -16,7 +16,7 @@
 //
 
 use cli;
-use git;
+use git::{self, CheckoutResult};
 use cli::checkout::CheckoutClapOptions;
 use types::{DeliveryResult, ExitCode};
 use utils::say::{sayln, say};
@@ -43,6 +43,12 @@ pub fn run(opts: CheckoutClapOptions) -> DeliveryResult<ExitCode> {
             p
         }
     };
-    try!(git::checkout_review(opts.change, pset, &target));
+    match try!(git::checkout_review(&cwd(), opts.change, pset, &target)) {
+        CheckoutResult::NewBranch => {},
+        CheckoutResult::ExistingBranch(status) => {
+            sayln("white", "Branch already exists, checking it out.");
+            sayln("white", &status);
+        }
+    }
     Ok(0)
 }
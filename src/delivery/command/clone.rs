@@ -40,7 +40,7 @@ pub fn run(opts: CloneClapOptions) -> DeliveryResult<ExitCode> {
     say("yellow", &clone_url);
     say("white", " to ");
     sayln("magenta", &format!("{}", opts.project));
-    try!(git::clone(opts.project, &clone_url));
+    try!(git::clone(&cwd(), opts.project, &clone_url));
     let project_root = cwd().join(opts.project);
     try!(git::config_repo(&delivery_url,
                           &project_root));
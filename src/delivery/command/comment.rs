@@ -0,0 +1,93 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli::comment::CommentClapOptions;
+use cli::{load_config, project_from_cwd};
+use project;
+use utils;
+use utils::say::{self, say, sayln};
+use errors::{DeliveryError, Kind};
+use types::{DeliveryResult, ExitCode};
+use http::change::{self, Change};
+use http::comment::{self, NewComment};
+
+pub fn run(opts: CommentClapOptions) -> DeliveryResult<ExitCode> {
+    sayln("green", "Chef Delivery");
+    let mut config = try!(load_config(&utils::cwd()));
+    let proj = try!(project_from_cwd());
+    config = config.set_pipeline(opts.pipeline)
+        .set_project(&proj);
+    let change_id = try!(project::change_id_or_from_head(&utils::cwd(), &config, opts.change));
+    let change = try!(change::get_change(&config, &change_id));
+    let patchset = try!(patchset_number(&change, opts.patchset));
+    let lines = if opts.line.is_empty() {
+        None
+    } else {
+        Some(try!(comment::parse_line_range(opts.line)))
+    };
+    let content = if opts.message.is_empty() {
+        try!(edit_comment(&change_id))
+    } else {
+        opts.message.to_string()
+    };
+    if content.trim().is_empty() {
+        return Err(DeliveryError{ kind: Kind::EmptyComment, detail: None })
+    }
+    let new_comment = NewComment {
+        content: content.trim().to_string(),
+        patchset: patchset,
+        file: if opts.file.is_empty() { None } else { Some(opts.file.to_string()) },
+        lines: lines
+    };
+    try!(comment::create(&config, &change_id, &new_comment));
+    say("white", "Commented on patchset ");
+    say("yellow", &patchset.to_string());
+    say("white", " of change ");
+    sayln("yellow", &change_id);
+    say::result_set("change_id", &change_id[..]);
+    say::result_set("patchset", &patchset);
+    Ok(0)
+}
+
+// Resolve the patchset option, where `latest` (or nothing) means the
+// most recent patchset of the change.
+fn patchset_number(change: &Change, patchset: &str) -> DeliveryResult<u64> {
+    let wanted = if patchset.is_empty() { "latest" } else { patchset };
+    match change.patchset(wanted) {
+        Some(p) => Ok(p.sequence_number),
+        None => {
+            let msg = format!("Patchset {} not found for change {}", wanted, change.id);
+            Err(DeliveryError{ kind: Kind::OptionConstraint, detail: Some(msg) })
+        }
+    }
+}
+
+// Let the user write the comment in their editor, git commit style.
+fn edit_comment(change_id: &str) -> DeliveryResult<String> {
+    let template = format!("\n# Write your comment for change {} above.\n\
+                            # Lines starting with '#' are ignored, and an \
+                            empty comment aborts.\n", change_id);
+    let text = try!(utils::open::edit_str("COMMENT_EDITMSG", &template));
+    Ok(strip_comment_lines(&text))
+}
+
+fn strip_comment_lines(text: &str) -> String {
+    text.lines()
+        .filter(|l| !l.starts_with("#"))
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
@@ -0,0 +1,113 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli::comments::CommentsClapOptions;
+use cli::{load_config, project_from_cwd};
+use project;
+use utils;
+use utils::say::{self, say, sayln};
+use types::{DeliveryResult, ExitCode};
+use git;
+use http::change::{self, Change};
+use http::comment;
+use http::models::Comment;
+
+pub fn run(opts: CommentsClapOptions) -> DeliveryResult<ExitCode> {
+    sayln("green", "Chef Delivery");
+    let mut config = try!(load_config(&utils::cwd()));
+    let proj = try!(project_from_cwd());
+    config = config.set_pipeline(opts.pipeline)
+        .set_project(&proj);
+    let change_id = try!(project::change_id_or_from_head(&utils::cwd(), &config, opts.change));
+    let change = try!(change::get_change(&config, &change_id));
+    let comments = try!(comment::list(&config, &change_id));
+    say::result_set("change_id", &change_id[..]);
+    say::result_set_encoded("comments", &comments);
+    if comments.is_empty() {
+        sayln("white", &format!("No comments on change {}", change_id));
+        return Ok(0)
+    }
+    // The line context comes from the review refs; without them we
+    // still list the comments.
+    if let Err(e) = git::git_command(&["fetch", "delivery"], &utils::cwd()) {
+        debug!("Unable to fetch the review refs: {:?}", e);
+    }
+    for c in comments.iter() {
+        say_thread(&change, c);
+    }
+    Ok(0)
+}
+
+fn say_thread(change: &Change, c: &Comment) {
+    say("yellow", &format!("#{} ", c.id));
+    say("white", &c.author.clone().unwrap_or(String::from("unknown")));
+    say("white", " on patchset ");
+    say("yellow", &c.patchset.to_string());
+    match c.datetime {
+        Some(ref date) => sayln("white", &format!(" ({})", date)),
+        None => sayln("white", "")
+    }
+    if let (Some(ref file), Some((first, last))) = (c.file.clone(), c.lines()) {
+        sayln("cyan", &format!("  {}:{}", file, range_str(first, last)));
+        say_line_context(change, c.patchset, file, first, last);
+    }
+    say_content(&c.content, "  ");
+    if let Some(ref replies) = c.children {
+        for reply in replies.iter() {
+            say("white", "  > ");
+            sayln("white", &reply.author.clone().unwrap_or(String::from("unknown")));
+            say_content(&reply.content, "    ");
+        }
+    }
+    sayln("white", "");
+}
+
+fn say_line_context(change: &Change, patchset: u64, file: &str, first: u64, last: u64) {
+    match git::show_review_file(&utils::cwd(), &change.topic, &patchset.to_string(),
+                                &change.target, file) {
+        Ok(content) => {
+            for (n, line) in numbered_lines(&content, first, last) {
+                say("white", &format!("  {:>5} | ", n));
+                sayln("magenta", line);
+            }
+        },
+        Err(e) => debug!("No context for {}: {:?}", file, e)
+    }
+}
+
+fn say_content(content: &str, indent: &str) {
+    for line in content.lines() {
+        sayln("white", &format!("{}{}", indent, line));
+    }
+}
+
+fn range_str(first: u64, last: u64) -> String {
+    if first == last {
+        first.to_string()
+    } else {
+        format!("{}-{}", first, last)
+    }
+}
+
+// The lines `first` to `last` of `content`, numbered from 1
+fn numbered_lines(content: &str, first: u64, last: u64) -> Vec<(u64, &str)> {
+    content.lines()
+        .enumerate()
+        .map(|(i, l)| (i as u64 + 1, l))
+        .filter(|&(n, _)| n >= first && n <= last)
+        .collect()
+}
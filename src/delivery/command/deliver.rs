@@ -0,0 +1,56 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli::deliver::DeliverClapOptions;
+use command::approve::{change_config, confirm, exit_code_for};
+use project;
+use utils;
+use utils::say::{self, say, sayln};
+use types::{DeliveryResult, ExitCode, EXIT_NOT_READY};
+use http::change;
+
+pub fn run(opts: DeliverClapOptions) -> DeliveryResult<ExitCode> {
+    sayln("green", "Chef Delivery");
+    let config = try!(change_config(opts.pipeline, opts.non_interactive));
+    let change_id = try!(project::change_id_or_from_head(&utils::cwd(), &config, opts.change));
+    let change = try!(change::get_change(&config, &change_id));
+    say::result_set("change_id", &change_id[..]);
+    say::result_set("delivered", &false);
+
+    for stage in ["verify", "acceptance"].iter() {
+        if !change.stage_passed(stage) {
+            sayln("red", &format!("Change {} has not passed {} and can not \
+                                   be delivered yet.", change_id, stage));
+            return Ok(EXIT_NOT_READY)
+        }
+    }
+
+    if !try!(confirm(&config, "Deliver", &change)) {
+        sayln("white", "Nothing delivered.");
+        return Ok(0)
+    }
+    match change::deliver(&config, &change_id) {
+        Ok(_) => {
+            say("white", "Change ");
+            say("yellow", &change_id);
+            sayln("green", " delivered");
+            say::result_set("delivered", &true);
+            Ok(0)
+        },
+        Err(e) => exit_code_for(e)
+    }
+}
@@ -32,12 +32,27 @@ pub fn run(opts: DiffClapOptions) -> DeliveryResult<ExitCode> {
     say("white", " targeted for pipeline ");
     say("magenta", &target);
 
+    if !opts.from_patchset.is_empty() {
+        let to = if opts.to_patchset.is_empty() { "latest" } else { opts.to_patchset };
+        say("white", " from patchset ");
+        say("yellow", opts.from_patchset);
+        say("white", " to patchset ");
+        sayln("yellow", to);
+        let diff = try!(git::diff_patchsets(&cwd(), opts.change,
+                                            opts.from_patchset, to, &target));
+        say("white", "\n");
+        sayln("white", &diff);
+        return Ok(0)
+    }
+
     if opts.patchset == "latest" {
         sayln("white", " latest patchset");
     } else {
         say("white", " at patchset ");
         sayln("yellow", opts.patchset);
     }
-    try!(git::diff(opts.change, opts.patchset, &target, &opts.local));
+    let diff = try!(git::diff(&cwd(), opts.change, opts.patchset, &target, &opts.local));
+    say("white", "\n");
+    sayln("white", &diff);
     Ok(0)
 }
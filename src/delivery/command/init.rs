@@ -22,13 +22,13 @@
 
 use cli::init::InitClapOptions;
 use delivery_config::DeliveryConfig;
-use cli::load_config;
+use cli::{load_config, project_from_cwd, project_or_from_cwd, project_path};
 use config::Config;
 use std::path::{Path, PathBuf};
 use project;
 use git;
 use utils;
-use utils::say::{say, sayln};
+use utils::say::{self, say, sayln};
 use std::io;
 use http::APIClient;
 use errors::{Kind, DeliveryError};
@@ -38,7 +38,7 @@ use hyper::status::StatusCode;
 pub fn run(init_opts: InitClapOptions) -> DeliveryResult<ExitCode> {
     sayln("green", "Chef Delivery");
     let mut config = try!(load_config(&utils::cwd()));
-    let final_proj = try!(project::project_or_from_cwd(init_opts.project));
+    let final_proj = try!(project_or_from_cwd(init_opts.project));
 
     config = config.set_user(init_opts.user)
         .set_server(init_opts.server)
@@ -74,24 +74,24 @@ pub fn run(init_opts: InitClapOptions) -> DeliveryResult<ExitCode> {
     };
 
     // Initalize the repo.
-    let project_path = project::project_path();
-    project::create_dot_delivery();
+    let project_path = try!(project_path());
+    try!(project::create_dot_delivery(&project_path));
 
     if !init_opts.local {
-        try!(create_on_server(&config, scp.clone()))
+        try!(create_on_server(&config, &project_path, scp.clone()))
     }
 
 
     // Generate build cookbook, either custom or default.
     let custom_build_cookbook_generated = if !init_opts.skip_build_cookbook {
-        try!(generate_build_cookbook(&config))
+        try!(generate_build_cookbook(&config, &project_path))
     } else {
         false
     };
 
     // Generate delivery config if passed
     let custom_config_passed = try!(
-        generate_delivery_config(config.config_json().ok())
+        generate_delivery_config(config.config_json().ok(), &project_path)
     );
 
     // Verify that the project has a config file
@@ -126,17 +126,17 @@ pub fn run(init_opts: InitClapOptions) -> DeliveryResult<ExitCode> {
         review_needed = true;
         sayln("cyan", "Committing unmerged Delivery content and submitting for review...");
         if !try!(project::create_feature_branch_if_missing(&project_path, branch_name)) {
-            sayln("white", &format!("  Skipping: A branch named '{}' already exists, \
+            skipped(&format!("  Skipping: A branch named '{}' already exists, \
                                      switching to it.", branch_name))
         } else {
-            sayln("green", &format!("  Feature branch named '{}' created.", branch_name))
+            action(&format!("  Feature branch named '{}' created.", branch_name))
         }
 
         if custom_build_cookbook_generated {
-            if try!(project::add_commit_build_cookbook(&custom_config_passed)) {
-              sayln("green", "  Custom build cookbook committed to feature branch.")
+            if try!(project::add_commit_build_cookbook(&project_path, &custom_config_passed)) {
+              action("  Custom build cookbook committed to feature branch.")
             } else {
-              sayln("white", "  Skipping: Build cookbook was not modified, no need to commit.");
+              skipped("  Skipping: Build cookbook was not modified, no need to commit.");
             }
         }
 
@@ -144,9 +144,9 @@ pub fn run(init_opts: InitClapOptions) -> DeliveryResult<ExitCode> {
         // so if a custom build cookbook was passed, the delivery config was already committed.
         if custom_config_passed && !custom_build_cookbook_generated {
             if try!(DeliveryConfig::git_add_commit_config(&project_path)) {
-              sayln("green", "  Custom delivery config committed to feature branch.")
+              action("  Custom delivery config committed to feature branch.")
             } else {
-              sayln("white", "  Skipping: Delivery config was not modified, no need to commit.");
+              skipped("  Skipping: Delivery config was not modified, no need to commit.");
             }
         }
     } else {
@@ -154,20 +154,20 @@ pub fn run(init_opts: InitClapOptions) -> DeliveryResult<ExitCode> {
         // Create a commit to send to review.
         sayln("cyan", "Creating and committing DELIVERY.md readme...");
         if !try!(project::create_feature_branch_if_missing(&project_path, branch_name)) {
-            sayln("white", &format!("  Skipping: A branch named '{}' already exists, \
+            skipped(&format!("  Skipping: A branch named '{}' already exists, \
                                      switching to it.", branch_name))
         } else {
-            sayln("green", &format!("  Feature branch named '{}' created.", branch_name))
+            action(&format!("  Feature branch named '{}' created.", branch_name))
         }
 
         // Create and commit DELIVERY.md readme if it doesn't exist.
-        if try!(project::create_delivery_readme()) {
+        if try!(project::create_delivery_readme(&project_path)) {
             review_needed = true;
-            sayln("green", "  DELIVERY.md created.");
-            try!(project::commit_delivery_readme());
-            sayln("green", &format!("  DELIVERY.md committed in branch '{}'.", branch_name))
+            action("  DELIVERY.md created.");
+            try!(project::commit_delivery_readme(&project_path));
+            action(&format!("  DELIVERY.md committed in branch '{}'.", branch_name))
         } else {
-            sayln("white", "  Skipping: DELIVERY.md already exists, no need to create or commit.");
+            skipped("  Skipping: DELIVERY.md already exists, no need to create or commit.");
         }
 
     }
@@ -176,24 +176,38 @@ pub fn run(init_opts: InitClapOptions) -> DeliveryResult<ExitCode> {
     if !init_opts.local {
         if review_needed {
             sayln("cyan", &format!("Submitting feature branch '{}' for review...", branch_name));
-            try!(trigger_review(config, scp, &init_opts.no_open));
+            try!(trigger_review(config, &project_path, scp, &init_opts.no_open));
         } else {
-            sayln("white", "  Skipping: All changes have already be submitted for review, skipping.");
+            skipped("  Skipping: All changes have already be submitted for review, skipping.");
         }
     } else {
-        sayln("white", " Skipping:  You passed --local, skipping review submission.");
+        skipped(" Skipping:  You passed --local, skipping review submission.");
     }
 
     sayln("green", "\nYour new Delivery project is ready!");
     Ok(0)
 }
 
+// Report a step init carried out; it is also listed under `actions`
+// in the json result.
+fn action(msg: &str) {
+    sayln("green", msg);
+    say::result_push("actions", msg.trim());
+}
+
+// Report a step init didn't need to carry out; it is also listed
+// under `skipped` in the json result.
+fn skipped(msg: &str) {
+    sayln("white", msg);
+    say::result_push("skipped", msg.trim());
+}
+
 // Create a Delivery Project
 //
 // This method will create a Delivery Project depending on the SCP that we specify,
 // either a Github, Bitbucket or Delivery (default). It also creates a pipeline,
 // adds the `delivery` remote and push the content of the local repo to the Server.
-fn create_on_server(config: &Config,
+fn create_on_server(config: &Config, project_path: &PathBuf,
                     scp: Option<project::SourceCodeProvider>) -> DeliveryResult<()> {
     let client = try!(APIClient::from_config(config));
     let git_url = try!(config.delivery_git_ssh_url());
@@ -226,30 +240,29 @@ fn create_on_server(config: &Config,
 
             match response {
                 StatusCode::Conflict => {
-                    sayln("white", &format!("  Skipping: {} backed Delivery project named {} \
+                    skipped(&format!("  Skipping: {} backed Delivery project named {} \
                                              already exists.", fancy_kind, proj));
 
                 },
                 _ => {
-                    sayln("green", &format!("  {} backed Delivery project named {} \
+                    action(&format!("  {} backed Delivery project named {} \
                                              created.", fancy_kind, proj));
                 }
             }
-            try!(setup_delivery_remote(&git_url));
-            try!(push_project_content_to_delivery(&pipe));
+            try!(setup_delivery_remote(project_path, &git_url));
+            try!(push_project_content_to_delivery(project_path, &pipe));
         },
         // If the user isn't using an scp, just delivery itself.
         None => {
             // Create delivery project on server unless it already exists.
             sayln("cyan", "Creating Delivery project...");
             if try!(project::create_delivery_project(&client, &org, &proj)) {
-                sayln("green", &format!("  Delivery project named {} was created.", proj));
+                action(&format!("  Delivery project named {} was created.", proj));
             } else {
-                sayln("white",
-                      &format!("  Skipping: Delivery project named {} already exists.", proj));
+                skipped(&format!("  Skipping: Delivery project named {} already exists.", proj));
             }
-            try!(setup_delivery_remote(&git_url));
-            try!(push_project_content_to_delivery(&pipe));
+            try!(setup_delivery_remote(project_path, &git_url));
+            try!(push_project_content_to_delivery(project_path, &pipe));
             try!(create_delivery_pipeline(&client, &org, &proj, &pipe));
         }
     }
@@ -257,24 +270,24 @@ fn create_on_server(config: &Config,
 }
 
 // Setup delivery remote
-fn setup_delivery_remote(git_url: &str) -> DeliveryResult<()> {
+fn setup_delivery_remote(project_path: &PathBuf, git_url: &str) -> DeliveryResult<()> {
     sayln("cyan", "Creating Delivery git remote...");
-    if try!(project::create_delivery_remote_if_missing(&git_url)) {
-        sayln("green", &format!("  Remote 'delivery' added as {}.", git_url))
+    if try!(project::create_delivery_remote_if_missing(project_path, &git_url)) {
+        action(&format!("  Remote 'delivery' added as {}.", git_url))
     } else {
-        sayln("white", "  Skipping: Remote named 'delivery' already exists and is correct.")
+        skipped("  Skipping: Remote named 'delivery' already exists and is correct.")
     }
     Ok(())
 }
 
 // Push content to Delivery if no upstream commits.
-fn push_project_content_to_delivery(pipeline: &str) -> DeliveryResult<()> {
+fn push_project_content_to_delivery(project_path: &Path, pipeline: &str) -> DeliveryResult<()> {
     sayln("cyan", "Pushing initial git history...");
-    if !try!(project::push_project_content_to_delivery(&pipeline)) {
-        sayln("white", &format!("  Skipping: Found commits on remote for pipeline {}, \
+    if !try!(project::push_project_content_to_delivery(project_path, &pipeline)) {
+        skipped(&format!("  Skipping: Found commits on remote for pipeline {}, \
                                  not pushing local commits.", pipeline))
     } else {
-        sayln("green", &format!("  No git history found for pipeline {}, \
+        action(&format!("  No git history found for pipeline {}, \
                                  pushing local commits from branch {}.", pipeline, pipeline))
     }
     Ok(())
@@ -285,10 +298,10 @@ fn create_delivery_pipeline(client: &APIClient, org: &str,
                             proj: &str, pipe: &str) -> DeliveryResult<()> {
     sayln("cyan", "Creating pipeline on Delivery server...");
     if try!(project::create_delivery_pipeline(client, org, proj, pipe)) {
-        sayln("green", &format!("  Created Delivery pipeline {} for project {}.",
+        action(&format!("  Created Delivery pipeline {} for project {}.",
                               pipe, proj))
     } else {
-        sayln("white", &format!("  Skipping: Delivery pipeline \
+        skipped(&format!("  Skipping: Delivery pipeline \
                             named {} already exists for project {}.", pipe, proj))
     }
     Ok(())
@@ -300,28 +313,26 @@ fn create_delivery_pipeline(client: &APIClient, org: &str,
 // build_cookbook generator from the ChefDK.
 //
 // Returns true if a CUSTOM build cookbook was generated, else it returns false.
-fn generate_build_cookbook(config: &Config) -> DeliveryResult<bool> {
+fn generate_build_cookbook(config: &Config, project_path: &PathBuf) -> DeliveryResult<bool> {
     let cache_path = try!(project::generator_cache_path());
-    let project_path = project::project_path();
     match config.generator().ok() {
         Some(generator_str) => {
             sayln("cyan", "Generating custom build cookbook...");
-            generate_custom_build_cookbook(generator_str, cache_path, project_path)
+            generate_custom_build_cookbook(generator_str, cache_path, project_path.clone())
         },
         // Default build cookbook
         None => {
             sayln("cyan", "Generating default build cookbook...");
-            if project::project_path().join(".delivery/build_cookbook").exists() {
-                sayln("white", "  Skipping: build cookbook already exists at \
+            if project_path.join(".delivery/build_cookbook").exists() {
+                skipped("  Skipping: build cookbook already exists at \
                                 .delivery/build_cookbook.");
                 Ok(false)
             } else {
                 let pipeline = try!(config.pipeline());
-                try!(project::create_default_build_cookbook(&pipeline));
-                sayln("green", "  Build cookbook generated at .delivery/build_cookbook.");
-                try!(git::git_push(&pipeline));
-                sayln("green",
-                      &format!("  Build cookbook committed to git and pushed to pipeline named {}.", pipeline));
+                try!(project::create_default_build_cookbook(project_path, &pipeline));
+                action("  Build cookbook generated at .delivery/build_cookbook.");
+                try!(git::git_push(project_path, &pipeline));
+                action(&format!("  Build cookbook committed to git and pushed to pipeline named {}.", pipeline));
                 Ok(false)
             }
         }
@@ -336,36 +347,36 @@ fn generate_custom_build_cookbook(generator_str: String,
     generator_path.push(gen_path.file_stem().unwrap());
     match try!(project::download_or_mv_custom_build_cookbook_generator(&gen_path, &cache_path)) {
         project::CustomCookbookSource::Disk => {
-            sayln("green", "  Copying custom build cookbook generator to the cache.")
+            action("  Copying custom build cookbook generator to the cache.")
         },
         project::CustomCookbookSource::Cached => {
-            sayln("white", "  Skipping: Using cached copy of custom build cookbook generator.")
+            skipped("  Skipping: Using cached copy of custom build cookbook generator.")
         },
         project::CustomCookbookSource::Git => {
-            sayln("green", &format!("  Downloading build_cookbook generator from {}.", generator_str))
+            action(&format!("  Downloading build_cookbook generator from {}.", generator_str))
         }
     }
 
     try!(project::chef_generate_build_cookbook_from_generator(&generator_path, &project_path));
-    sayln("green", "  Custom build cookbook generated at .delivery/build_cookbook.");
+    action("  Custom build cookbook generated at .delivery/build_cookbook.");
     return Ok(true)
 }
 
-fn generate_delivery_config(config_json: Option<String>) -> DeliveryResult<bool> {
+fn generate_delivery_config(config_json: Option<String>,
+                            project_path: &PathBuf) -> DeliveryResult<bool> {
     if let Some(json) = config_json {
         sayln("cyan", "Copying custom Delivery config...");
-        let proj_path = project::project_path();
         let json_path = PathBuf::from(&json);
 
         // Create config
-        match try!(DeliveryConfig::copy_config_file(&json_path, &proj_path)) {
+        match try!(DeliveryConfig::copy_config_file(&json_path, project_path)) {
             Some(_) => {
-                sayln("green", &format!("  Custom Delivery config copied \
+                action(&format!("  Custom Delivery config copied \
                                          from {} to .delivery/config.json.", &json));
                 Ok(true)
             },
             None => {
-                sayln("white", &format!("  Skipped: Content of custom config passed from {} exactly \
+                skipped(&format!("  Skipped: Content of custom config passed from {} exactly \
                                          matches existing .delivery/config.json.", &json));
                 Ok(false)
             }
@@ -376,18 +387,19 @@ fn generate_delivery_config(config_json: Option<String>) -> DeliveryResult<bool>
 }
 
 // Triggers an delivery review.
-fn trigger_review(config: Config, scp: Option<project::SourceCodeProvider>,
+fn trigger_review(config: Config, project_path: &Path,
+                  scp: Option<project::SourceCodeProvider>,
                   no_open: &bool) -> DeliveryResult<()> {
     let pipeline = try!(config.pipeline());
-    let head = try!(git::get_head());
+    let head = try!(git::get_head(project_path));
 
     // We now trigger a review for every single project type
-    let review = try!(project::review(&pipeline, &head));
+    let review = try!(project::review(project_path, &pipeline, &head));
     try!(project::handle_review_result(&review, no_open));
     match scp {
-        Some(s) => sayln("green", &format!("  Review submitted to Delivery with {} \
+        Some(s) => action(&format!("  Review submitted to Delivery with {} \
                                            intergration enabled.", try!(s.kind_to_fancy_str()))),
-        None => sayln("green", "  Review submitted to Delivery.")
+        None => action("  Review submitted to Delivery.")
     }
     Ok(())
 }
@@ -398,7 +410,7 @@ fn compare_directory_name(repo_name: &str) -> DeliveryResult<()> {
     let c_dir = utils::cwd();
     if !c_dir.ends_with(repo_name) {
         let mut answer = String::new();
-        let project_name = try!(project::project_from_cwd());
+        let project_name = try!(project_from_cwd());
         sayln("yellow", &format!(
                 "WARN: This project will be named '{}', but the repository name is '{}'.",
                 project_name, repo_name));
@@ -26,9 +26,10 @@ use job::workspace::{Workspace, Privilege};
 use job::change::Change;
 use types::{DeliveryResult, ExitCode};
 use errors::{DeliveryError, Kind};
-use utils::say::{say, sayln};
+use utils::say::{self, say, sayln};
 use utils::path_join_many::PathJoinMany;
 use utils::{self, cwd, privileged_process};
+use token::TokenStore;
 
 pub fn run(opts: JobClapOptions) -> DeliveryResult<ExitCode> {
     sayln("green", "Chef Delivery");
@@ -55,10 +56,16 @@ pub fn run(opts: JobClapOptions) -> DeliveryResult<ExitCode> {
     let e = try!(config.enterprise());
     let o = try!(config.organization());
     let pi = try!(config.pipeline());
+    if opts.renew {
+        try!(TokenStore::renew_token(&config));
+    }
     say("white", "Starting job for ");
     say("green", &format!("{}", &p));
     say("yellow", &format!(" {}", opts.stage));
     sayln("magenta", &format!(" {}", opts.phases));
+    say::result_set("project", &p[..]);
+    say::result_set("pipeline", &pi[..]);
+    say::result_set("stage", opts.stage);
     let phases: Vec<&str> = opts.phases.split(" ").collect();
     let phase_dir = phases.join("-");
     // Builder nodes are expected to be running this command via
@@ -84,6 +91,7 @@ pub fn run(opts: JobClapOptions) -> DeliveryResult<ExitCode> {
     let ws = Workspace::new(&job_root_path);
     sayln("white", &format!("Creating workspace in {}", job_root_path.to_string_lossy()));
     try!(ws.build());
+    say::result_set("workspace", &*job_root_path.to_string_lossy());
     say("white", "Cloning repository, and merging");
     let mut local_change = false;
     let patch = if opts.patchset.is_empty() {
@@ -102,7 +110,7 @@ pub fn run(opts: JobClapOptions) -> DeliveryResult<ExitCode> {
         String::new()
     } else {
         local_change = true;
-        let v = try!(git::get_head());
+        let v = try!(git::get_head(&cwd()));
         say("yellow", &format!(" {}", &v));
         v
     };
@@ -155,6 +163,7 @@ pub fn run(opts: JobClapOptions) -> DeliveryResult<ExitCode> {
     };
     sayln("magenta", &format!("Running {} {}", phase_msg, phases.join(", ")));
     try!(ws.run_job(opts.phases, &privilege_drop, &local_change));
+    say::result_set("phases", &opts.phases.split(" ").map(String::from).collect::<Vec<_>>());
     Ok(0)
 }
 
@@ -197,7 +206,8 @@ fn run_docker_job(opts: JobClapOptions) -> DeliveryResult<ExitCode> {
     }
 
     let flags = vec![("--skip-default", &opts.skip_default),
-                     ("--local", &opts.local)];
+                     ("--local", &opts.local),
+                     ("--renew", &opts.renew)];
 
     for (flag, value) in flags {
         maybe_add_flag(&mut docker, flag, value);
@@ -230,7 +240,7 @@ fn run_docker_job(opts: JobClapOptions) -> DeliveryResult<ExitCode> {
                 let buf_string = String::from_utf8(buf_vec).unwrap();
                 line.push_str(&buf_string);
                 if line.contains("\n") {
-                    print!("{}", line);
+                    say("white", &line);
                     line = String::with_capacity(256);
                 }
             }
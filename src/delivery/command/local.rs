@@ -16,27 +16,29 @@
 //
 
 use cli::local::LocalClapOptions;
+use cli::project_path;
 use types::{DeliveryResult, ExitCode};
 use utils::say::{sayln, say};
 use std::process::{Stdio};
 use delivery_config::project::ProjectToml;
-use project;
+use std::path::Path;
 use utils;
 
 pub fn run(opts: LocalClapOptions) -> DeliveryResult<ExitCode> {
     sayln("green", "Chef Delivery");
+    let project_path = try!(project_path());
     let project_toml: ProjectToml = try!(
-        ProjectToml::load_toml_file(project::project_path())
+        ProjectToml::load_toml_file(project_path.clone())
     );
     let phase_cmd = try!(project_toml.local_phase(opts.phase.clone()));
     say("white", "Running ");
     say("magenta", &format!("{:?}", opts.phase.unwrap()));
     sayln("white", " Phase");
     debug!("Executing command: {}", phase_cmd);
-    Ok(exec_command(&phase_cmd))
+    Ok(exec_command(&phase_cmd, &project_path))
 }
 
-pub fn exec_command(cmd: &str) -> ExitCode {
+pub fn exec_command(cmd: &str, project_path: &Path) -> ExitCode {
     // TODO: I just copy paste the old code and modified a little bit
     // so it works but we have to work on UW-75 to make it right!
     // We should maybe create a tempfile to stick the command coming from
@@ -48,7 +50,7 @@ pub fn exec_command(cmd: &str) -> ExitCode {
         .args(&args_vec)
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit())
-        .current_dir(project::project_path())
+        .current_dir(project_path)
         .output()
         .unwrap_or_else(|e| { panic!("Unexpected error: Failed to execute process: {}", e) });
 
@@ -0,0 +1,50 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli;
+use cli::logout::LogoutClapOptions;
+use rustc_serialize::json::Json;
+use command::token::{entry_json, warn_token_helper};
+use types::{DeliveryResult, ExitCode};
+use utils::say::{self, say, sayln};
+use utils::cwd;
+use token::TokenStore;
+
+pub fn run(opts: LogoutClapOptions) -> DeliveryResult<ExitCode> {
+    sayln("green", "Chef Delivery");
+    let config = try!(cli::load_config(&cwd()))
+        .set_server(opts.server)
+        .set_api_port(opts.port);
+    warn_token_helper(&config);
+    let server = try!(config.api_host_and_port());
+    let mut tstore = try!(TokenStore::from_config(&config));
+    let removed = try!(tstore.remove_server(&server));
+    say::result_set("server", &server[..]);
+    say::result_set("removed", &Json::Array(vec![]));
+    if removed.is_empty() {
+        sayln("white", &format!("No API tokens stored for {}", server));
+        return Ok(0)
+    }
+    for e in removed.iter() {
+        say("white", "Removed the API token of ");
+        say("yellow", &format!("{}/{}", e.ent, e.user));
+        say("white", " on ");
+        sayln("yellow", &e.server);
+        say::result_push("removed", &entry_json(e));
+    }
+    Ok(0)
+}
@@ -25,3 +25,13 @@ pub mod diff;
 pub mod clone;
 pub mod api;
 pub mod job;
+pub mod status;
+pub mod approve;
+pub mod deliver;
+pub mod changes;
+pub mod watch;
+pub mod comment;
+pub mod comments;
+pub mod patchsets;
+pub mod abandon;
+pub mod logout;
@@ -0,0 +1,62 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli;
+use git;
+use cli::patchsets::PatchsetsClapOptions;
+use types::{DeliveryResult, ExitCode};
+use utils::say::{self, say, sayln};
+use utils::cwd;
+use rustc_serialize::json::{Json, ToJson};
+use std::collections::BTreeMap;
+
+pub fn run(opts: PatchsetsClapOptions) -> DeliveryResult<ExitCode> {
+    sayln("green", "Chef Delivery");
+    let mut config = try!(cli::load_config(&cwd()));
+    config = config.set_pipeline(opts.pipeline);
+    let target = validate!(config, pipeline);
+    say("white", "Patchsets for change ");
+    say("yellow", opts.change);
+    say("white", " targeted for pipeline ");
+    sayln("magenta", &target);
+    let patchsets = try!(git::review_patchsets(&cwd(), opts.change, &target));
+    say::result_set("change", opts.change);
+    say::result_set("patchsets", &Json::Array(vec![]));
+    if patchsets.is_empty() {
+        sayln("white", "No patchsets found on the delivery remote");
+        return Ok(0)
+    }
+    // The commit dates are only known once the review refs are fetched
+    try!(git::git_command(&["fetch", "delivery"], &cwd()));
+    let latest = patchsets.last().map(|p| p.number);
+    for p in patchsets.iter() {
+        let date = try!(git::commit_date(&cwd(), &p.sha));
+        let mut entry = BTreeMap::new();
+        entry.insert("number".to_string(), p.number.to_json());
+        entry.insert("sha".to_string(), p.sha.to_json());
+        entry.insert("date".to_string(), date.to_json());
+        say::result_push("patchsets", &entry);
+        say("yellow", &format!("{:>4}  ", p.number));
+        say("white", &format!("{}  {}", p.sha, date));
+        if Some(p.number) == latest {
+            sayln("green", "  (latest)");
+        } else {
+            sayln("white", "");
+        }
+    }
+    Ok(0)
+}
@@ -21,12 +21,16 @@ use cli::load_config;
 use config::Config;
 use project;
 use utils;
-use utils::say::{sayln, say};
+use utils::say::{self, sayln, say};
 use errors::DeliveryError;
 use types::{DeliveryResult, ExitCode};
-use cookbook;
+use cookbook::{self, VersionBump};
 use git::{self, ReviewResult};
 use http;
+use command::watch;
+use rustc_serialize::json::ToJson;
+use std::collections::BTreeMap;
+use std::path::Path;
 
 pub fn run(review_opts: ReviewClapOptions) -> DeliveryResult<ExitCode> {
     sayln("green", "Chef Delivery");
@@ -42,19 +46,27 @@ pub fn run(review_opts: ReviewClapOptions) -> DeliveryResult<ExitCode> {
 
     if let Some(should_bump) = config.auto_bump {
         if should_bump {
-            try!(cookbook::bump_version(&project_root, &target))
+            let bump = try!(cookbook::bump_version(&project_root, &target));
+            say_version_bump(&project_root, &bump);
         }
     }
 
-    let head = try!(git::get_head());
+    let head = try!(git::get_head(&project_root));
+    if review_opts.stack {
+        let project = try!(project::project_from_dir(&project_root));
+        config = config.set_project(&project);
+        return review_stack(&config, &project_root, &target, &head)
+    }
     say("white", "Review for change ");
     say("yellow", &head);
     say("white", " targeted for pipeline ");
     sayln("magenta", &target);
-    let review = try!(project::review(&target, &head));
+    let review = try!(project::review(&project_root, &target, &head));
+    say::result_set("change", &head[..]);
+    say::result_set("pipeline", &target[..]);
 
     if review_opts.edit {
-        let project = try!(project::project_from_cwd());
+        let project = try!(project::project_from_dir(&project_root));
         config = config.set_pipeline(review_opts.pipeline)
             .set_project(&project);
         try!(edit_change(&config, &review));
@@ -63,11 +75,27 @@ pub fn run(review_opts: ReviewClapOptions) -> DeliveryResult<ExitCode> {
     for line in review.messages.iter() {
         sayln("white", line);
     }
+    say::result_set("messages", &review.messages);
+    say::result_set("change_id", &review.change_id);
 
-    match try!(project::handle_review_result(&review, &review_opts.no_open)) {
+    // Scripts consuming the json output have no use for a browser tab
+    let no_open = review_opts.no_open || say::is_json_output();
+    let url = try!(project::handle_review_result(&review, &no_open));
+    say::result_set("url", &url);
+    match url {
         Some(url) => {sayln("magenta", &url)},
         None => {}
     }
+
+    if review_opts.wait {
+        let project = try!(project::project_from_dir(&project_root));
+        config = config.set_project(&project);
+        let change_id = match review.change_id {
+            Some(ref change_id) => change_id.clone(),
+            None => try!(http::change::latest_id_for_topic(&config, &target, &head))
+        };
+        return watch::watch(&config, &change_id, watch::until_or_default(review_opts.until))
+    }
     Ok(0)
 }
 
@@ -86,3 +114,79 @@ fn edit_change(config: &Config,
         None => Ok(())
     }
 }
+
+// Submit the stack ending at `head` and report the change of each
+// of its branches
+fn review_stack(config: &Config, project_root: &Path, target: &str,
+                head: &str) -> DeliveryResult<ExitCode> {
+    say("white", "Review for the stack ending at ");
+    say("yellow", head);
+    say("white", " targeted for pipeline ");
+    sayln("magenta", target);
+    say::result_set("pipeline", target);
+    let changes = try!(project::review_stack(project_root, config, target, head));
+    // With one change per branch we print the links rather than
+    // opening a browser tab for each of them.
+    for c in changes.iter() {
+        let mut entry = BTreeMap::new();
+        entry.insert("change".to_string(), c.branch.to_json());
+        entry.insert("submitted".to_string(), c.submitted.to_json());
+        say("yellow", &format!("  {}", c.branch));
+        match c.change_id {
+            Some(ref change_id) => {
+                let url = match c.url {
+                    Some(ref url) => url.clone(),
+                    None => try!(change_url(config, change_id))
+                };
+                if c.submitted {
+                    say("white", " submitted: ");
+                } else {
+                    say("white", " is unchanged: ");
+                }
+                sayln("magenta", &url);
+                entry.insert("change_id".to_string(), change_id.to_json());
+                entry.insert("url".to_string(), url.to_json());
+            },
+            None => sayln("white", " is unchanged and has no open change")
+        }
+        say::result_push("changes", &entry);
+    }
+    Ok(0)
+}
+
+fn say_version_bump(project_root: &Path, bump: &VersionBump) {
+    if *bump == VersionBump::NotACookbook {
+        return
+    }
+    if let Ok(project) = project::project_from_dir(project_root) {
+        say("white", "Project ");
+        say("yellow", &project);
+        sayln("white", " is a cookbook");
+    }
+    sayln("white", "Validating version in metadata");
+    match *bump {
+        VersionBump::AlreadyUpdated{ ref pipeline, ref current } => {
+            say("white", "Version already updated (");
+            say("magenta", pipeline);
+            say("white", "/");
+            say("green", current);
+            sayln("white", ")");
+        },
+        VersionBump::Bumped{ ref pipeline, ref new } => {
+            say("yellow", "The version hasn't been updated (");
+            say("red", pipeline);
+            sayln("yellow", ")");
+            say("white", "Bumping version to: ");
+            sayln("green", new);
+        },
+        VersionBump::NotACookbook => {}
+    }
+}
+
+// The web UI page of a change, on the same host and protocol as the API
+fn change_url(config: &Config, change_id: &str) -> DeliveryResult<String> {
+    Ok(format!("{}://{}/e/{}/#/organizations/{}/projects/{}/changes/{}",
+               try!(config.api_protocol()), try!(config.api_host_and_port()),
+               try!(config.enterprise()), try!(config.organization()),
+               try!(config.project()), change_id))
+}
@@ -0,0 +1,81 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli::status::StatusClapOptions;
+use cli::{load_config, project_from_cwd};
+use project;
+use utils;
+use utils::say::{self, say, sayln};
+use types::{DeliveryResult, ExitCode};
+use http::change::{self, Change};
+
+pub fn run(opts: StatusClapOptions) -> DeliveryResult<ExitCode> {
+    sayln("green", "Chef Delivery");
+    let mut config = try!(load_config(&utils::cwd()));
+    let proj = try!(project_from_cwd());
+    config = config.set_pipeline(opts.pipeline)
+        .set_project(&proj);
+    let change_id = try!(project::change_id_or_from_head(&utils::cwd(), &config, opts.change));
+    let change = try!(change::get_change(&config, &change_id));
+    say_change(&change);
+    say::result_set_encoded("change", &change);
+    Ok(0)
+}
+
+// Print the change with the results of every stage and phase
+pub fn say_change(change: &Change) {
+    say("white", "Change ");
+    sayln("yellow", &change.id);
+    if let Some(ref title) = change.title {
+        sayln("white", &format!("  {}", title));
+    }
+    say("white", "  Topic: ");
+    say("yellow", &change.topic);
+    say("white", "  Pipeline: ");
+    say("magenta", &change.target);
+    say("white", "  State: ");
+    sayln("white", &change.state);
+    if let Some(ref submitter) = change.submit_by {
+        say("white", "  Submitted by: ");
+        sayln("white", submitter);
+    }
+    for name in change::STAGES.iter() {
+        match change.stage(name) {
+            Some(stage) => {
+                say("white", &format!("{:<14}", stage.stage));
+                sayln(status_color(&stage.status), &stage.status);
+                for phase in stage.phases.iter() {
+                    say("white", &format!("  {:<12}", phase.name));
+                    sayln(status_color(&phase.status), &phase.status);
+                }
+            },
+            None => {
+                say("white", &format!("{:<14}", name));
+                sayln("white", "-");
+            }
+        }
+    }
+}
+
+pub fn status_color(status: &str) -> &'static str {
+    match status {
+        "passed" => "green",
+        "failed" => "red",
+        "running" => "yellow",
+        _ => "white"
+    }
+}
@@ -17,10 +17,15 @@
 
 use cli;
 use cli::token::TokenClapOptions;
+use config::Config;
+use errors::{DeliveryError, Kind};
+use http;
+use rustc_serialize::json::{Json, ToJson};
+use std::collections::BTreeMap;
 use types::{DeliveryResult, ExitCode};
-use utils::say::{turn_on_output, turn_off_output, sayln};
+use utils::say::{self, turn_on_output, turn_off_output, say, sayln};
 use utils::cwd;
-use token::TokenStore;
+use token::{TokenStore, TokenEntry};
 
 pub fn run(opts: TokenClapOptions) -> DeliveryResult<ExitCode> {
     // If we want the raw token, we wont print any output
@@ -39,17 +44,109 @@ pub fn run(opts: TokenClapOptions) -> DeliveryResult<ExitCode> {
     if opts.saml.is_some() {
         config.saml = opts.saml;
     }
+    if opts.saml_callback {
+        config.saml_callback = Some(true);
+    }
+
+    match opts.action {
+        "list" => return list(&config, opts.server),
+        "revoke" => return revoke(&config),
+        _ => {}
+    }
 
-    let token: String = if opts.verify {
+    let token: String = if opts.renew {
+        try!(TokenStore::renew_token(&config))
+    } else if opts.verify {
         try!(TokenStore::verify_token(&config))
     } else {
         try!(TokenStore::request_token(&config))
     };
 
-    if opts.raw {
+    say::result_set("server", &try!(config.server()));
+    say::result_set("enterprise", &try!(config.enterprise()));
+    say::result_set("user", &try!(config.user()));
+    say::result_set("verified", &opts.verify);
+    say::result_set("token", &token[..]);
+
+    if opts.raw && !say::is_json_output() {
         turn_on_output();
         sayln("white", &format!("{}", &token));
     }
 
     Ok(0)
 }
+
+// List the stored tokens, only those of `server` if one is given
+fn list(config: &Config, server: &str) -> DeliveryResult<ExitCode> {
+    warn_token_helper(config);
+    let tstore = try!(TokenStore::from_config(config));
+    let entries: Vec<&TokenEntry> = tstore.entries().into_iter()
+        .filter(|e| server.is_empty() || e.is_from(server))
+        .collect();
+    say::result_set("tokens", &Json::Array(vec![]));
+    if entries.is_empty() {
+        sayln("white", &format!("No API tokens stored in {}", tstore.path().display()));
+        return Ok(0)
+    }
+    for e in entries.iter() {
+        say("yellow", &e.server);
+        say("white", &format!(" {}/{} ", e.ent, e.user));
+        say("magenta", &e.masked_token());
+        match e.verified {
+            Some(ref v) => sayln("white", &format!(" verified {}", v)),
+            None => sayln("white", " never verified")
+        }
+        say::result_push("tokens", &entry_json(e));
+    }
+    Ok(0)
+}
+
+// Revoke the token of the user on the server and forget it
+fn revoke(config: &Config) -> DeliveryResult<ExitCode> {
+    warn_token_helper(config);
+    let server = try!(config.api_host_and_port());
+    let ent = try!(config.enterprise());
+    let user = try!(config.user());
+    let mut tstore = try!(TokenStore::from_config(config));
+    let token = match tstore.lookup(&server, &ent, &user) {
+        Some(token) => token.clone(),
+        None => {
+            let msg = format!("server: {}, ent: {}, user: {}", server, ent, user);
+            return Err(DeliveryError{ kind: Kind::NoToken, detail: Some(msg) })
+        }
+    };
+    say("yellow", "Revoking Token: ");
+    let revoked = try!(http::token::revoke(config, &user, &token));
+    if revoked {
+        sayln("green", "revoked");
+    } else {
+        sayln("yellow", "already expired");
+    }
+    try!(tstore.remove_token(&server, &ent, &user));
+    sayln("white", &format!("Removed the API token from {}", tstore.path().display()));
+    say::result_set("server", &server[..]);
+    say::result_set("enterprise", &ent[..]);
+    say::result_set("user", &user[..]);
+    say::result_set("revoked", &revoked);
+    Ok(0)
+}
+
+/// The json result of a stored token, with the token masked
+pub fn entry_json(e: &TokenEntry) -> BTreeMap<String, Json> {
+    let mut entry = BTreeMap::new();
+    entry.insert("server".to_string(), e.server.to_json());
+    entry.insert("enterprise".to_string(), e.ent.to_json());
+    entry.insert("user".to_string(), e.user.to_json());
+    entry.insert("token".to_string(), e.masked_token().to_json());
+    entry.insert("verified".to_string(), e.verified.to_json());
+    entry
+}
+
+/// Tokens saved with a `token_helper` are out of reach of the
+/// token store
+pub fn warn_token_helper(config: &Config) {
+    if let Some(ref cmd) = config.token_helper {
+        sayln("yellow", &format!("Tokens saved with the token_helper `{}` \
+                                  are managed by it", cmd));
+    }
+}
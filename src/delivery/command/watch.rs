@@ -0,0 +1,143 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use cli::watch::WatchClapOptions;
+use cli::{load_config, project_from_cwd};
+use config::Config;
+use project;
+use utils;
+use utils::backoff::Backoff;
+use utils::say::{self, say, sayln, clear_lines};
+use types::{DeliveryResult, ExitCode, EXIT_PHASE_FAILED, EXIT_WATCH_TIMED_OUT};
+use errors::{DeliveryError, Kind};
+use http::change::{self, Change, Progress};
+use command::status::status_color;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to watch a change, in seconds, unless `watch_timeout`
+/// says otherwise. A `watch_timeout` of 0 watches without a deadline.
+pub const DEFAULT_TIMEOUT: u64 = 3600;
+
+// A change that was just pushed may not be visible to the API yet
+const NOT_FOUND_GRACE: u64 = 30;
+
+pub fn run(opts: WatchClapOptions) -> DeliveryResult<ExitCode> {
+    sayln("green", "Chef Delivery");
+    let mut config = try!(load_config(&utils::cwd()));
+    let proj = try!(project_from_cwd());
+    config = config.set_pipeline(opts.pipeline)
+        .set_project(&proj);
+    let change_id = try!(project::change_id_or_from_head(&utils::cwd(), &config, opts.change));
+    watch(&config, &change_id, until_or_default(opts.until))
+}
+
+pub fn until_or_default(until: &str) -> &str {
+    if until.is_empty() {
+        "verify"
+    } else {
+        until
+    }
+}
+
+/// Poll the change until it passes the stage `until`, fails a phase or
+/// the `watch_timeout` runs out, redrawing its pipeline state every time.
+/// Polling starts every 5 seconds and backs off up to once a minute while
+/// nothing changes.
+pub fn watch(config: &Config, change_id: &str, until: &str) -> DeliveryResult<ExitCode> {
+    say("white", "Watching change ");
+    say("yellow", change_id);
+    sayln("white", &format!(" until it passes {}", until));
+    say::result_set("change_id", change_id);
+    say::result_set("until", until);
+    let timeout = config.watch_timeout.unwrap_or(DEFAULT_TIMEOUT);
+    let started = Instant::now();
+    let mut backoff = Backoff::new(Duration::from_secs(5),
+                                   Duration::from_secs(60), 1.5);
+    let mut last: Option<Change> = None;
+    let mut drawn = 0;
+    loop {
+        let change = match change::get_change(config, change_id) {
+            Ok(change) => change,
+            Err(DeliveryError{ kind: Kind::ChangeNotFound, .. })
+                if last.is_none() && started.elapsed() < Duration::from_secs(NOT_FOUND_GRACE) => {
+                thread::sleep(Duration::from_secs(5));
+                continue
+            },
+            Err(e) => return Err(e)
+        };
+        if last.as_ref() != Some(&change) {
+            if !clear_lines(drawn) && drawn > 0 {
+                // Keep the appended tables apart
+                sayln("white", "");
+            }
+            drawn = say_stage_table(&change);
+            backoff.reset();
+        }
+        match change.progress(until) {
+            Progress::Reached => {
+                say::result_set("watch", "passed");
+                sayln("green", &format!("Change {} passed {}", change_id, until));
+                return Ok(0)
+            },
+            Progress::Failed(stage, phase) => {
+                say::result_set("watch", "failed");
+                say::result_set("failed_stage", &stage[..]);
+                say::result_set("failed_phase", &phase[..]);
+                if phase.is_empty() {
+                    sayln("red", &format!("Change {} failed {}", change_id, stage));
+                } else {
+                    sayln("red", &format!("Change {} failed {} {}",
+                                          change_id, stage, phase));
+                }
+                return Ok(EXIT_PHASE_FAILED)
+            },
+            Progress::Waiting => {
+                last = Some(change);
+                let delay = backoff.next_delay();
+                if timeout > 0 && started.elapsed() + delay > Duration::from_secs(timeout) {
+                    say::result_set("watch", "timed_out");
+                    sayln("red", &format!("Change {} did not pass {} within {} seconds",
+                                          change_id, until, timeout));
+                    return Ok(EXIT_WATCH_TIMED_OUT)
+                }
+                thread::sleep(delay);
+            }
+        }
+    }
+}
+
+// Print one line per stage with the status of each of its phases,
+// returning the number of lines written.
+fn say_stage_table(change: &Change) -> usize {
+    for name in change::STAGES.iter() {
+        say("white", &format!("{:<12}", name));
+        match change.stage(name) {
+            Some(stage) => {
+                for phase in stage.phases.iter() {
+                    say(status_color(&phase.status), &format!(" {}", phase.name));
+                }
+                if stage.phases.is_empty() {
+                    say(status_color(&stage.status), &format!(" {}", stage.status));
+                }
+                sayln("white", "");
+            },
+            None => sayln("white", " -")
+        }
+    }
+    change::STAGES.len()
+}
@@ -39,11 +39,27 @@ pub struct Config {
     pub git_port: Option<String>,
     pub pipeline: Option<String>,
     pub token_file: Option<String>,
+    pub token_helper: Option<String>,
+    pub encrypt_tokens: Option<bool>,
+    pub token_passphrase_command: Option<String>,
+    pub token_expiry_warning: Option<u64>,
     pub generator: Option<String>,
     pub non_interactive: Option<bool>,
     pub auto_bump: Option<bool>,
     pub config_json: Option<String>,
     pub saml: Option<bool>,
+    pub saml_callback: Option<bool>,
+    pub saml_callback_timeout: Option<u64>,
+    pub ca_bundle: Option<String>,
+    pub client_cert: Option<String>,
+    pub client_key: Option<String>,
+    pub insecure: Option<bool>,
+    pub proxy: Option<String>,
+    pub no_proxy: Option<String>,
+    pub connect_timeout: Option<u64>,
+    pub read_timeout: Option<u64>,
+    pub retries: Option<u64>,
+    pub watch_timeout: Option<u64>,
 }
 
 impl Default for Config {
@@ -59,11 +75,27 @@ impl Default for Config {
             git_port: Some(String::from("8989")),
             pipeline: Some(String::from("master")),
             token_file: None,
+            token_helper: None,
+            encrypt_tokens: None,
+            token_passphrase_command: None,
+            token_expiry_warning: None,
             generator: None,
             non_interactive: None,
             auto_bump: None,
             config_json: None,
             saml: None,
+            saml_callback: None,
+            saml_callback_timeout: None,
+            ca_bundle: None,
+            client_cert: None,
+            client_key: None,
+            insecure: None,
+            proxy: None,
+            no_proxy: None,
+            connect_timeout: None,
+            read_timeout: None,
+            retries: None,
+            watch_timeout: None,
         }
     }
 }
@@ -98,8 +130,13 @@ config_accessor_for!(project, set_project, "Project not set; try --project or se
 config_accessor_for!(git_port, set_git_port, "Git Port not set; please set it in your .toml config file");
 config_accessor_for!(pipeline, set_pipeline, "Pipeline not set; try --for or set it in your .toml config file");
 config_accessor_for!(token_file, set_token_file, "token_file not set; set it in your cli.toml");
+config_accessor_for!(token_helper, set_token_helper, "token_helper not set; set it in your cli.toml");
+config_accessor_for!(token_passphrase_command, set_token_passphrase_command, "token_passphrase_command not set; set it in your cli.toml");
 config_accessor_for!(generator, set_generator, "build_cookbook generator not set; set it in your cli.toml");
 config_accessor_for!(config_json, set_config_json, "config_json not set; set it in your cli.toml");
+config_accessor_for!(ca_bundle, set_ca_bundle, "ca_bundle not set; try --ca-bundle or set it in your cli.toml");
+config_accessor_for!(client_cert, set_client_cert, "client_cert not set; try --client-cert or set it in your cli.toml");
+config_accessor_for!(client_key, set_client_key, "client_key not set; try --client-key or set it in your cli.toml");
 
 impl Config {
 
@@ -188,11 +225,30 @@ impl Config {
         config.user = stringify_or("user", &table, config.user);
         config.git_port = stringify_or("git_port", &table, config.git_port);
         config.token_file = stringify_or("token_file", &table, config.token_file);
+        config.token_helper = stringify_or("token_helper", &table, config.token_helper);
+        config.encrypt_tokens = boolify_or("encrypt_tokens", &table, config.encrypt_tokens);
+        config.token_passphrase_command = stringify_or("token_passphrase_command", &table,
+                                                       config.token_passphrase_command);
+        config.token_expiry_warning = intify_or("token_expiry_warning", &table,
+                                                config.token_expiry_warning);
         config.generator = stringify_or("generator", &table, config.generator);
         config.non_interactive = boolify_or("non_interactive", &table, config.non_interactive);
         config.auto_bump = boolify_or("auto_bump", &table, config.auto_bump);
         config.config_json = stringify_or("config_json", &table, config.config_json);
         config.saml = boolify_or("saml", &table, config.saml);
+        config.saml_callback = boolify_or("saml_callback", &table, config.saml_callback);
+        config.saml_callback_timeout = intify_or("saml_callback_timeout", &table,
+                                                 config.saml_callback_timeout);
+        config.ca_bundle = stringify_or("ca_bundle", &table, config.ca_bundle);
+        config.client_cert = stringify_or("client_cert", &table, config.client_cert);
+        config.client_key = stringify_or("client_key", &table, config.client_key);
+        config.insecure = boolify_or("insecure", &table, config.insecure);
+        config.proxy = stringify_or("proxy", &table, config.proxy);
+        config.no_proxy = stringify_or("no_proxy", &table, config.no_proxy);
+        config.connect_timeout = intify_or("connect_timeout", &table, config.connect_timeout);
+        config.read_timeout = intify_or("read_timeout", &table, config.read_timeout);
+        config.retries = intify_or("retries", &table, config.retries);
+        config.watch_timeout = intify_or("watch_timeout", &table, config.watch_timeout);
         return Ok(config);
     }
 
@@ -224,6 +280,14 @@ impl Config {
         })
     }
 
+    fn intify_values(toml_value: Option<&toml::Value>) -> Option<u64> {
+        toml_value.and_then(|v| {
+            v.as_integer()
+        }).and_then(|i| {
+            if i >= 0 { Some(i as u64) } else { None }
+        })
+    }
+
     fn check_dot_delivery_cli(path: PathBuf) -> Option<PathBuf> {
         let dot_git = path.join_many(&[".delivery", "cli.toml"]);
         debug!("Checking {}", dot_git.display());
@@ -257,6 +321,10 @@ fn boolify_or(key: &str, table: &toml::Table, default: Option<bool>) -> Option<b
     Config::boolify_values(table.get(key)).or(default)
 }
 
+fn intify_or(key: &str, table: &toml::Table, default: Option<u64>) -> Option<u64> {
+    Config::intify_values(table.get(key)).or(default)
+}
+
 #[cfg(test)]
 mod tests {
     use super::Config;
@@ -278,11 +346,27 @@ mod tests {
                 assert_eq!(Some("master".to_string()), config.pipeline);
                 assert_eq!(None, config.organization);
                 assert_eq!(None, config.token_file);
+                assert_eq!(None, config.token_helper);
+                assert_eq!(None, config.encrypt_tokens);
+                assert_eq!(None, config.token_passphrase_command);
+                assert_eq!(None, config.token_expiry_warning);
                 assert_eq!(None, config.generator);
                 assert_eq!(None, config.non_interactive);
                 assert_eq!(None, config.auto_bump);
                 assert_eq!(None, config.config_json);
                 assert_eq!(None, config.saml);
+                assert_eq!(None, config.saml_callback);
+                assert_eq!(None, config.saml_callback_timeout);
+                assert_eq!(None, config.ca_bundle);
+                assert_eq!(None, config.client_cert);
+                assert_eq!(None, config.client_key);
+                assert_eq!(None, config.insecure);
+                assert_eq!(None, config.proxy);
+                assert_eq!(None, config.no_proxy);
+                assert_eq!(None, config.connect_timeout);
+                assert_eq!(None, config.read_timeout);
+                assert_eq!(None, config.retries);
+                assert_eq!(None, config.watch_timeout);
             },
             Err(e) => {
                 panic!("Failed to parse: {:?}", e.detail)
@@ -304,6 +388,22 @@ mod tests {
             auto_bump = true
             config_json = "/path/to/my/custom/config.json"
             saml = true
+            saml_callback = true
+            saml_callback_timeout = 30
+            token_helper = "vault-delivery-token"
+            encrypt_tokens = true
+            token_passphrase_command = "secret-tool lookup service delivery"
+            token_expiry_warning = 3600
+            ca_bundle = "/etc/delivery/ca.pem"
+            client_cert = "/etc/delivery/client.pem"
+            client_key = "/etc/delivery/client.key"
+            insecure = true
+            proxy = "http://proxy.example.com:3128"
+            no_proxy = "localhost,.internal"
+            connect_timeout = 5
+            read_timeout = 120
+            retries = 4
+            watch_timeout = 7200
 "#;
         let config_result = Config::parse_config(toml);
         match config_result {
@@ -319,6 +419,23 @@ mod tests {
                 assert_eq!(Some("/path/to/my/custom/config.json".to_string()),
                           config.config_json);
                 assert_eq!(Some(true), config.saml);
+                assert_eq!(Some(true), config.saml_callback);
+                assert_eq!(Some(30), config.saml_callback_timeout);
+                assert_eq!(Some("vault-delivery-token".to_string()), config.token_helper);
+                assert_eq!(Some(true), config.encrypt_tokens);
+                assert_eq!(Some("secret-tool lookup service delivery".to_string()),
+                           config.token_passphrase_command);
+                assert_eq!(Some(3600), config.token_expiry_warning);
+                assert_eq!(Some("/etc/delivery/ca.pem".to_string()), config.ca_bundle);
+                assert_eq!(Some("/etc/delivery/client.pem".to_string()), config.client_cert);
+                assert_eq!(Some("/etc/delivery/client.key".to_string()), config.client_key);
+                assert_eq!(Some(true), config.insecure);
+                assert_eq!(Some("http://proxy.example.com:3128".to_string()), config.proxy);
+                assert_eq!(Some("localhost,.internal".to_string()), config.no_proxy);
+                assert_eq!(Some(5), config.connect_timeout);
+                assert_eq!(Some(120), config.read_timeout);
+                assert_eq!(Some(4), config.retries);
+                assert_eq!(Some(7200), config.watch_timeout);
             },
             Err(e) => {
                 panic!("Failed to parse: {:?}", e.detail)
@@ -19,11 +19,9 @@ use errors::{DeliveryError, Kind};
 use std::path::{Path, PathBuf};
 use std::fs::File;
 use std::io::prelude::*;
-use utils::{self, read_file};
-use utils::say::{say, sayln};
+use utils::read_file;
 use utils::path_ext::is_file;
 use git;
-use project;
 use regex::Regex;
 use regex::Captures;
 
@@ -52,21 +50,26 @@ impl MetadataVersion {
     }
 }
 
+/// What `bump_version` found out about the cookbook version, with the
+/// versions as strings (`x.y.z`).
+#[derive(Debug, PartialEq)]
+pub enum VersionBump {
+    NotACookbook,
+    // The version differs from the one on the pipeline
+    AlreadyUpdated { pipeline: String, current: String },
+    // The version matched the pipeline one and was bumped and committed
+    Bumped { pipeline: String, new: String },
+}
+
 // Bump the metadata version, only if:
 // * The project is a cookbook
 // * The version hasn't been updated
 //
 // @param p_root [&PathBuf] The project root path
 // @param pipeline [&str] Pipeline the change is targeting to
-// @return () if success
-pub fn bump_version(p_root: &PathBuf, pipeline: &str) -> Result<(), DeliveryError> {
+// @return VersionBump describing what was done
+pub fn bump_version(p_root: &PathBuf, pipeline: &str) -> Result<VersionBump, DeliveryError> {
     if is_cookbook(&p_root) {
-        let project = try!(project::project_from_cwd());
-        say("white", "Project ");
-        say("yellow", &project);
-        sayln("white", " is a cookbook");
-        sayln("white", "Validating version in metadata");
-
         let meta_f_p = PathBuf::from(metadata_file(&p_root));
         let meta_f_c = try!(read_file(&meta_f_p));
         let current_meta_v = try!(metadata_version_from(&meta_f_c));
@@ -78,23 +81,16 @@ pub fn bump_version(p_root: &PathBuf, pipeline: &str) -> Result<(), DeliveryErro
         let pipeline_v = pipeline_meta_v.to_string();
 
         if current_v == pipeline_v {
-            say("yellow", "The version hasn't been updated (");
-            say("red", &pipeline_v);
-            sayln("yellow", ")");
             let new_meta_version = try!(bump_patchset(pipeline_meta_v.clone()));
             let new_version = new_meta_version.to_string();
-            say("white", "Bumping version to: ");
-            sayln("green", &new_version);
-            try!(save_version(&meta_f_p, new_version));
+            try!(save_version(&p_root, &meta_f_p, new_version.clone()));
+            Ok(VersionBump::Bumped{ pipeline: pipeline_v, new: new_version })
         } else {
-            say("white", "Version already updated (");
-            say("magenta", &pipeline_v);
-            say("white", "/");
-            say("green", &current_v);
-            sayln("white", ")");
+            Ok(VersionBump::AlreadyUpdated{ pipeline: pipeline_v, current: current_v })
         }
+    } else {
+        Ok(VersionBump::NotACookbook)
     }
-    Ok(())
 }
 
 // @Private
@@ -158,7 +154,8 @@ fn bump_patchset(mut version: MetadataVersion) -> Result<MetadataVersion, Delive
 }
 
 // Saves the new version to the metadata and commit the changes
-fn save_version(metadata: &PathBuf, version: String) -> Result<(), DeliveryError> {
+fn save_version(p_root: &PathBuf, metadata: &PathBuf,
+                version: String) -> Result<(), DeliveryError> {
     let current_meta = try!(read_file(metadata));
     let current_meta_version = try!(metadata_version_from(&current_meta));
     let current_version = current_meta_version.to_string();
@@ -171,14 +168,15 @@ fn save_version(metadata: &PathBuf, version: String) -> Result<(), DeliveryError
     // Commit the changes made to the metadata
     let mut commit_msg = String::from("Bump version to ");
     commit_msg.push_str(&version);
-    try!(git::git_command(&["add", metadata.to_str().unwrap()], &utils::cwd()));
-    try!(git::git_command(&["commit", "-m", &commit_msg], &utils::cwd()));
+    try!(git::git_command(&["add", metadata.to_str().unwrap()], p_root));
+    try!(git::git_command(&["commit", "-m", &commit_msg], p_root));
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
+    use tempdir::TempDir;
     use cookbook::*;
 
     #[test]
@@ -199,6 +197,13 @@ mod tests {
         assert_eq!(pa, 4);
     }
 
+    #[test]
+    fn bump_version_leaves_other_projects_alone() {
+        let project = TempDir::new("not-a-cookbook").unwrap();
+        let bump = super::bump_version(&project.path().to_path_buf(), "master").unwrap();
+        assert_eq!(super::VersionBump::NotACookbook, bump);
+    }
+
     #[test]
     fn return_the_metadata_file_path() {
         let project_path = PathBuf::from("/cookbook");
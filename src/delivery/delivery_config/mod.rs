@@ -97,7 +97,7 @@ impl DeliveryConfig {
 
         // Commit the changes made in .delivery but detect if nothing has changed,
         // if that is the case, we are Ok() to continue
-        match git::git_commit("Adds custom Delivery config") {
+        match git::git_commit(proj_path, "Adds custom Delivery config") {
           Ok(_) => Ok(true),
           Err(DeliveryError{ kind: Kind::EmptyGitCommit, .. }) => Ok(false),
           Err(e) => Err(e)
@@ -15,7 +15,8 @@
 // limitations under the License.
 //
 
-use rustc_serialize::json;
+use rustc_serialize::json::{self, Json, ToJson};
+use std::collections::BTreeMap;
 use std::error::{self, Error};
 use std::num;
 use std::io;
@@ -23,6 +24,7 @@ use std::fmt;
 use hyper;
 use toml;
 use hyper::error::Error as HttpError;
+use openssl::ssl::error::SslError;
 
 #[derive(Debug)]
 pub enum Kind {
@@ -49,6 +51,7 @@ pub enum Kind {
     GitFailed,
     EmptyGitCommit,
     GitSetupFailed,
+    MissingPipelineBranch,
     ConfigParse,
     MissingConfig,
     MissingConfigFile,
@@ -82,8 +85,16 @@ pub enum Kind {
     OpenFailed,
     NoToken,
     TokenExpired,
+    TokenHelperFailed,
+    TokenStoreLocked,
+    TokenStoreDecryptFailed,
     NoEditor,
-    MissingProjectConfig
+    MissingProjectConfig,
+    Forbidden,
+    ChangeNotReady,
+    EmptyComment,
+    TlsSetup,
+    InvalidProxy
 }
 
 #[derive(Debug)]
@@ -96,6 +107,28 @@ impl DeliveryError {
     pub fn detail(&self) -> Option<String> {
         self.detail.clone()
     }
+
+    /// The name of the `Kind` of the error, without the data some
+    /// kinds carry (e.g. `ApiError`)
+    pub fn kind_name(&self) -> String {
+        let kind = format!("{:?}", self.kind);
+        match kind.find('(') {
+            Some(i) => kind[..i].to_string(),
+            None => kind
+        }
+    }
+
+    /// The error as reported by the json output format
+    pub fn to_json(&self) -> Json {
+        let mut obj = BTreeMap::new();
+        obj.insert("kind".to_string(), self.kind_name().to_json());
+        obj.insert("message".to_string(), self.description().to_json());
+        obj.insert("detail".to_string(), self.detail.to_json());
+        if let Kind::ApiError(ref status, _) = self.kind {
+            obj.insert("http_status".to_string(), status.to_u16().to_json());
+        }
+        Json::Object(obj)
+    }
 }
 
 impl error::Error for DeliveryError {
@@ -112,6 +145,7 @@ impl error::Error for DeliveryError {
             Kind::GitFailed => "Git command failed!",
             Kind::EmptyGitCommit => "Nothing to commit, working directory clean",
             Kind::GitSetupFailed => "Setup failed; you have already set up delivery.",
+            Kind::MissingPipelineBranch => "The pipeline branch does not exist locally or has no commits",
             Kind::BadGitOutputMatch => "A line of git porcelain did not match!",
             Kind::BadMetadataVersionMatch => "Metadata version mismatch!",
             Kind::MissingMetadataVersion => "Missing a version entry into the metadata.rb",
@@ -157,8 +191,16 @@ impl error::Error for DeliveryError {
             Kind::InternalServerError => "There was an internal error on the server. Check the logson the Delivery server.",
             Kind::NoToken => "Missing API token. Try `delivery token` to create one",
             Kind::TokenExpired => "The API token has expired. Try `delivery token` to generate a new one",
+            Kind::TokenHelperFailed => "The token_helper command failed",
+            Kind::TokenStoreLocked => "The API token store is encrypted and no passphrase was given",
+            Kind::TokenStoreDecryptFailed => "Unable to decrypt the API token store",
             Kind::NoEditor => "Environment variable EDITOR not set",
-            Kind::MissingProjectConfig => "Unable to find .delivery/config.json in this directory or its parents"
+            Kind::MissingProjectConfig => "Unable to find .delivery/config.json in this directory or its parents",
+            Kind::Forbidden => "You do not have permission to perform this action",
+            Kind::ChangeNotReady => "The change is not ready for this action",
+            Kind::EmptyComment => "Aborting comment due to an empty message",
+            Kind::TlsSetup => "Unable to set up TLS for the Delivery API",
+            Kind::InvalidProxy => "Invalid proxy setting; expected a url such as http://proxy:3128"
         }
     }
 
@@ -228,6 +270,15 @@ impl From<HttpError> for DeliveryError {
     }
 }
 
+impl From<SslError> for DeliveryError {
+    fn from(err: SslError) -> DeliveryError {
+        DeliveryError{
+            kind: Kind::TlsSetup,
+            detail: Some(format!("{}", err))
+        }
+    }
+}
+
 impl From<num::ParseIntError> for DeliveryError {
     fn from(err: num::ParseIntError) -> DeliveryError {
         let detail = Some(err.description().to_string());
@@ -246,3 +297,21 @@ impl From<toml::DecodeError> for DeliveryError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::status::StatusCode;
+
+    #[test]
+    fn to_json_test() {
+        let e = DeliveryError{ kind: Kind::NoToken, detail: None };
+        assert_eq!("{\"detail\":null,\"kind\":\"NoToken\",\
+                    \"message\":\"Missing API token. Try `delivery token` to create one\"}",
+                   e.to_json().to_string());
+        let e = DeliveryError{ kind: Kind::ApiError(StatusCode::NotFound, Ok(String::new())),
+                               detail: Some("API request returned 404 Not Found".to_string()) };
+        assert_eq!("ApiError", e.kind_name());
+        assert_eq!(Some(404), e.to_json().find("http_status").and_then(|s| s.as_u64()));
+    }
+}
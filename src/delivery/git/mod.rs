@@ -18,23 +18,23 @@
 pub use errors;
 
 use std::process::Command;
-use utils::say::{say, sayln, Spinner};
+use utils::say::Spinner;
 use utils::path_ext::{is_dir};
 use utils::{find_command};
 use errors::{DeliveryError, Kind};
-use std::env;
 use std::path::{Path, PathBuf};
 use std::convert::AsRef;
 use std::error;
 use regex::Regex;
-use project::project_path;
 
-fn cwd() -> PathBuf {
-    env::current_dir().unwrap()
-}
+// Every function that runs git in a repository takes the directory
+// of that repository as its first argument; nothing in here looks at
+// the current directory or prints to the terminal, so the `command`
+// layer decides what to tell the user.
 
-pub fn get_head() -> Result<String, DeliveryError> {
-    let gitr = try!(git_command(&["branch"], &cwd()));
+// Return the name of the branch checked out in `dir`
+pub fn get_head(dir: &Path) -> Result<String, DeliveryError> {
+    let gitr = try!(git_command(&["branch"], dir));
     let result = try!(parse_get_head(&gitr.stdout));
     Ok(result)
 }
@@ -113,14 +113,14 @@ pub fn git_command<P: ?Sized>(args: &[&str], c: &P) -> Result<GitResult, Deliver
     Ok(GitResult{ stdout: stdout, stderr: stderr })
 }
 
-pub fn git_push_review(branch: &str,
+pub fn git_push_review(dir: &Path, branch: &str,
                        target: &str) -> Result<ReviewResult, DeliveryError> {
     let gitr = try!(git_command(&["push",
                                   "--porcelain", "--progress",
                                   "--verbose", "delivery",
                                   &format!("{}:_for/{}/{}",
                                            branch, target, branch)],
-                                &cwd()));
+                                dir));
     parse_git_push_output(&gitr.stdout, &gitr.stderr)
 }
 
@@ -250,34 +250,24 @@ fn parse_line_from_remote(line: &str, review_result: &mut ReviewResult) -> () {
 }
 
 pub fn check_repo_init(path: &PathBuf) -> Result<(), DeliveryError> {
-    say("white", "Is ");
-    say("magenta", &format!("{} ", path.display()));
-    say("white", "a git repo?  ");
-
     let git_dir = path.join(".git");
 
     if is_dir(git_dir.as_path()) {
-        sayln("white", "yes");
         return Ok(())
     } else {
-        sayln("red", "no. Run 'git init' here and then 'delivery init' again.");
-        return Err(DeliveryError{ kind: Kind::GitSetupFailed, detail: None })
+        return Err(DeliveryError{
+            kind: Kind::GitSetupFailed,
+            detail: Some(format!("{} is not a git repo. Run 'git init' here \
+                                  and then 'delivery init' again.", path.display()))
+        })
     }
 }
 
 // This function is not currently used, but will be when we
 // add a --force option to the init command.
 pub fn create_repo(path: &PathBuf) -> Result<(), DeliveryError> {
-    say("white", "Creating repo in: ");
-    say("magenta", &format!("{} ", path.display()));
-    let result = git_command(&["init"], path);
-    match result {
-        Ok(_) => {
-            sayln("white", "'git init' done.");
-            return Ok(());
-        },
-        Err(e) => return Err(e)
-    }
+    try!(git_command(&["init"], path));
+    Ok(())
 }
 
 pub fn config_repo(url: &str, path: &PathBuf) -> Result<bool, DeliveryError> {
@@ -321,6 +311,54 @@ fn remote_already_exists_error_msg(url: &str) -> String {
     return error + "\n\nPlease either update your cli.toml or your git remote. Run:\n\ngit remote -v show -n delivery\n\nto see your current delivery remote."
 }
 
+/// A local branch of a stack of branches that depend on each other
+#[derive(Debug, PartialEq, Clone)]
+pub struct StackBranch {
+    pub name: String,
+    pub sha: String
+}
+
+// Find the chain of local branches between `target` and `head`, from
+// the one closest to `target` up to `head` itself
+pub fn branch_stack(dir: &Path, target: &str,
+                    head: &str) -> Result<Vec<StackBranch>, DeliveryError> {
+    let commits = try!(git_command(&["rev-list", "--first-parent", "--reverse",
+                                     &format!("{}..{}", target, head)], dir));
+    let branches = try!(git_command(&["for-each-ref",
+                                      "--format=%(objectname) %(refname:short)",
+                                      "refs/heads"], dir));
+    Ok(find_stack(&commits.stdout, &branches.stdout, target, head))
+}
+
+// Walk the commits from `target` to `head` (oldest first) and keep
+// every local branch whose tip is one of them. When several branches
+// point to the same commit, `head` wins over the others.
+pub fn find_stack(rev_list: &str, branches: &str,
+                  target: &str, head: &str) -> Vec<StackBranch> {
+    let tips: Vec<(&str, &str)> = branches.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            match (fields.next(), fields.next()) {
+                (Some(sha), Some(name)) if name != target => Some((sha, name)),
+                _ => None
+            }
+        })
+        .collect();
+    let mut stack = Vec::new();
+    for commit in rev_list.lines().map(|c| c.trim()) {
+        let at_commit: Vec<&(&str, &str)> = tips.iter()
+            .filter(|&&(sha, _)| sha == commit)
+            .collect();
+        let branch = at_commit.iter()
+            .find(|&&&(_, name)| name == head)
+            .or(at_commit.first());
+        if let Some(&&(sha, name)) = branch {
+            stack.push(StackBranch{ name: name.to_string(), sha: sha.to_string() });
+        }
+    }
+    stack
+}
+
 pub fn checkout_branch_name(change: &str, patchset: &str) -> String {
     if patchset == "latest" {
         return String::from(change);
@@ -329,41 +367,112 @@ pub fn checkout_branch_name(change: &str, patchset: &str) -> String {
     }
 }
 
-pub fn diff(change: &str, patchset: &str, pipeline: &str, local: &bool) -> Result<(), DeliveryError> {
-    try!(git_command(&["fetch", "delivery"], &cwd()));
+// Return the diff of a patchset against the pipeline, or against
+// HEAD when `local` is set
+pub fn diff(dir: &Path, change: &str, patchset: &str, pipeline: &str,
+            local: &bool) -> Result<String, DeliveryError> {
+    try!(git_command(&["fetch", "delivery"], dir));
     let mut first_branch = format!("delivery/{}", pipeline);
     if *local {
         first_branch = String::from("HEAD");
     }
-    let diff = try!(git_command(&["diff", "--color=always", &first_branch, &format!("delivery/_reviews/{}/{}/{}", pipeline, change, patchset)], &cwd()));
-    say("white", "\n");
-    sayln("white", &diff.stdout);
-    Ok(())
+    let diff = try!(git_command(&["diff", "--color=always", &first_branch, &format!("delivery/_reviews/{}/{}/{}", pipeline, change, patchset)], dir));
+    Ok(diff.stdout)
+}
+
+// Return how a change evolved between two of its patchsets
+pub fn diff_patchsets(dir: &Path, change: &str, from: &str, to: &str,
+                      pipeline: &str) -> Result<String, DeliveryError> {
+    try!(git_command(&["fetch", "delivery"], dir));
+    let from_branch = format!("delivery/_reviews/{}/{}/{}", pipeline, change, from);
+    let to_branch = format!("delivery/_reviews/{}/{}/{}", pipeline, change, to);
+    let diff = try!(git_command(&["diff", "--color=always", &from_branch, &to_branch], dir));
+    Ok(diff.stdout)
+}
+
+/// A patchset of a change as published on the delivery remote
+#[derive(Debug, PartialEq)]
+pub struct PatchsetRef {
+    pub number: u64,
+    pub sha: String
+}
+
+// List the patchsets of a change found on the delivery remote, in
+// the order they were uploaded
+pub fn review_patchsets(dir: &Path, change: &str,
+                        pipeline: &str) -> Result<Vec<PatchsetRef>, DeliveryError> {
+    let prefix = format!("refs/heads/_reviews/{}/{}/", pipeline, change);
+    let refs = try!(git_command(&["ls-remote", "delivery", &format!("{}*", prefix)], dir));
+    Ok(parse_review_refs(&refs.stdout, &prefix))
+}
+
+// Parse the `ls-remote` output for the review refs under `prefix`;
+// the `latest` ref is skipped since it repeats the last patchset.
+pub fn parse_review_refs(ls_remote_output: &str, prefix: &str) -> Vec<PatchsetRef> {
+    let mut patchsets: Vec<PatchsetRef> = ls_remote_output.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            match (fields.next(), fields.next()) {
+                (Some(sha), Some(r)) if r.starts_with(prefix) => {
+                    r[prefix.len()..].parse::<u64>().ok()
+                        .map(|n| PatchsetRef{ number: n, sha: sha.to_string() })
+                },
+                _ => None
+            }
+        })
+        .collect();
+    patchsets.sort_by_key(|p| p.number);
+    patchsets
+}
+
+// Return the commit date of `sha`; the commit needs to have been fetched
+pub fn commit_date(dir: &Path, sha: &str) -> Result<String, DeliveryError> {
+    let show = try!(git_command(&["show", "-s", "--format=%ci", sha], dir));
+    Ok(show.stdout.trim().to_string())
+}
+
+// Return the content of `file` as of a patchset of a change, read
+// from the review refs fetched from the delivery remote
+pub fn show_review_file(dir: &Path, change: &str, patchset: &str, pipeline: &str,
+                        file: &str) -> Result<String, DeliveryError> {
+    let object = format!("delivery/_reviews/{}/{}/{}:{}",
+                         pipeline, change, patchset, file);
+    let show = try!(git_command(&["show", &object], dir));
+    Ok(show.stdout)
 }
 
-pub fn clone(project: &str, git_url: &str) -> Result<(), DeliveryError> {
-    try!(git_command(&["clone", git_url, project], &cwd()));
+// Clone `git_url` into the `project` directory, relative to `dir`
+pub fn clone(dir: &Path, project: &str, git_url: &str) -> Result<(), DeliveryError> {
+    try!(git_command(&["clone", git_url, project], dir));
     Ok(())
 }
 
-pub fn checkout_review(change: &str, patchset: &str, pipeline: &str) -> Result<(), DeliveryError> {
-    try!(git_command(&["fetch", "delivery"], &cwd()));
+/// Returned by `checkout_review`. When the branch of the patchset was
+/// already there it is checked out as is, and `ExistingBranch` holds
+/// the output of `git status` for it.
+#[derive(Debug, PartialEq)]
+pub enum CheckoutResult {
+    NewBranch,
+    ExistingBranch(String)
+}
+
+pub fn checkout_review(dir: &Path, change: &str, patchset: &str,
+                       pipeline: &str) -> Result<CheckoutResult, DeliveryError> {
+    try!(git_command(&["fetch", "delivery"], dir));
     let branchname = checkout_branch_name(change, patchset);
-    let result = git_command(&["branch", "--track", &branchname, &format!("delivery/_reviews/{}/{}/{}", pipeline, change, patchset)], &cwd());
+    let result = git_command(&["branch", "--track", &branchname, &format!("delivery/_reviews/{}/{}/{}", pipeline, change, patchset)], dir);
     match result {
         Ok(_) => {
-            try!(git_command(&["checkout", &branchname], &cwd()));
-            return Ok(())
+            try!(git_command(&["checkout", &branchname], dir));
+            return Ok(CheckoutResult::NewBranch)
         },
         Err(e) => {
             match e.detail {
                 Some(msg) => {
                     if msg.contains("already exists.") {
-                        try!(git_command(&["checkout", &branchname], &cwd()));
-                        sayln("white", "Branch already exists, checking it out.");
-                        let r = try!(git_command(&["status"], &cwd()));
-                        sayln("white", &r.stdout);
-                        return Ok(())
+                        try!(git_command(&["checkout", &branchname], dir));
+                        let r = try!(git_command(&["status"], dir));
+                        return Ok(CheckoutResult::ExistingBranch(r.stdout))
                     } else {
                         return Err(DeliveryError{kind: Kind::GitFailed, detail: Some(msg)});
                     }
@@ -376,10 +485,42 @@ pub fn checkout_review(change: &str, patchset: &str, pipeline: &str) -> Result<(
     }
 }
 
+// List the local branches created by `checkout_review` for a change
+pub fn review_branches(dir: &Path, change: &str,
+                       pipeline: &str) -> Result<Vec<String>, DeliveryError> {
+    let branches = try!(git_command(&["for-each-ref",
+                                      "--format=%(refname:short) %(upstream:short)",
+                                      "refs/heads"], dir));
+    Ok(parse_review_branches(&branches.stdout, change, pipeline))
+}
+
+// Keep the branches tracking a review ref of the change, which leaves
+// out the feature branch the change was submitted from.
+pub fn parse_review_branches(for_each_ref_output: &str,
+                             change: &str, pipeline: &str) -> Vec<String> {
+    let prefix = format!("delivery/_reviews/{}/{}/", pipeline, change);
+    for_each_ref_output.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            match (fields.next(), fields.next()) {
+                (Some(branch), Some(upstream)) if upstream.starts_with(&prefix[..]) => {
+                    Some(branch.to_string())
+                },
+                _ => None
+            }
+        })
+        .collect()
+}
+
+pub fn delete_branch(dir: &Path, branch: &str) -> Result<(), DeliveryError> {
+    try!(git_command(&["branch", "-D", branch], dir));
+    Ok(())
+}
+
 // Verify the content of the repo:pipeline on the server
-pub fn server_content(pipeline: &str) -> Result<bool, DeliveryError> {
+pub fn server_content(dir: &Path, pipeline: &str) -> Result<bool, DeliveryError> {
     let p_ref = &format!("refs/heads/{}", pipeline);
-    match git_command(&["ls-remote", "delivery", p_ref], &cwd()) {
+    match git_command(&["ls-remote", "delivery", p_ref], dir) {
         Ok(msg) => {
             if msg.stdout.contains(p_ref) {
                 return Ok(true)
@@ -392,29 +533,19 @@ pub fn server_content(pipeline: &str) -> Result<bool, DeliveryError> {
 }
 
 // Push pipeline content to the Server
-pub fn git_push(pipeline: &str) -> Result<(), DeliveryError> {
+pub fn git_push(dir: &Path, pipeline: &str) -> Result<(), DeliveryError> {
     // Check if the pipeline branch exists and has commits.
     // If the pipeline branch exists and does not have commits,
     // then `git branch` will not return it, so just checking
     // `git branch` output will handle both cases (pipeline does
     // not exist and pipeline exists but without commits).
-    match git_command(&["branch"], &cwd()) {
+    match git_command(&["branch"], dir) {
         Ok(msg) => {
             if !msg.stdout.contains(pipeline) {
-                sayln("red", &format!("A {} branch does not exist locally.", pipeline));
-                sayln("red", &format!("A {} branch with commits is needed to create the {} \
-                                      pipeline.\n", pipeline, pipeline));
-                sayln("red", &format!("If your project already has git history, you should \
-                                      pull it into {} locally.", pipeline));
-                sayln("red", &format!("For example, if your remote is named origin, and your \
-                                      git history is in {} run:\n", pipeline));
-                sayln("red", &format!("git pull origin {}\n", pipeline));
-                sayln("red", "However, if this is a brand new project, make an initial commit by running:\n");
-                sayln("red", &format!("git checkout -b {}", pipeline));
-                sayln("red", "git commit --allow-empty -m 'Initial commit.'\n");
-                sayln("red", &format!("Once you have commits on the {} branch, run `delivery \
-                                      init` again.", pipeline));
-                return Err(DeliveryError{ kind: Kind::GitFailed, detail: None });
+                return Err(DeliveryError{
+                    kind: Kind::MissingPipelineBranch,
+                    detail: Some(missing_pipeline_branch_msg(pipeline))
+                });
             }
             true
         },
@@ -425,20 +556,32 @@ pub fn git_push(pipeline: &str) -> Result<(), DeliveryError> {
     match git_command(&["push", "--set-upstream",
                         "--porcelain", "--progress",
                         "--verbose", "delivery", pipeline],
-                      &cwd()) {
+                      dir) {
         Ok(_) => return Ok(()),
         // Not expecting any errors at this point.
         Err(e) => return Err(e)
     }
 }
 
+fn missing_pipeline_branch_msg(pipeline: &str) -> String {
+    format!("A {p} branch does not exist locally.\n\
+             A {p} branch with commits is needed to create the {p} pipeline.\n\n\
+             If your project already has git history, you should pull it into {p} locally.\n\
+             For example, if your remote is named origin, and your git history is in {p} run:\n\n\
+             git pull origin {p}\n\n\
+             However, if this is a brand new project, make an initial commit by running:\n\n\
+             git checkout -b {p}\n\
+             git commit --allow-empty -m 'Initial commit.'\n\n\
+             Once you have commits on the {p} branch, run `delivery init` again.", p = pipeline)
+}
+
 // Commit content to local repo
 //
 // This fun will commit the changes you have loaded in the current repo,
 // it will also detect if the commit failed and transform the error to a
 // more specific one. (Ex. If we try to commit when nothing has changed)
-pub fn git_commit(message: &str) -> Result<(), DeliveryError> {
-    match git_command(&["commit", "-m", message], &project_path()) {
+pub fn git_commit(dir: &Path, message: &str) -> Result<(), DeliveryError> {
+    match git_command(&["commit", "-m", message], dir) {
         Err(DeliveryError{ kind, detail: Some(output) }) => {
             if output.contains("nothing to commit") {
               return Err(DeliveryError{ kind: Kind::EmptyGitCommit, detail: None });
@@ -453,9 +596,13 @@ pub fn git_commit(message: &str) -> Result<(), DeliveryError> {
 
 #[cfg(test)]
 mod tests {
-    use super::{ReviewResult, PushResult, PushResultFlag, parse_git_push_output, parse_line_from_remote, check_repo_init};
+    use super::{ReviewResult, PushResult, PushResultFlag, parse_git_push_output, parse_line_from_remote, check_repo_init,
+                parse_review_refs, PatchsetRef, find_stack, StackBranch,
+                parse_review_branches, git_command, get_head, git_push};
+    use errors::Kind;
     use std::path::PathBuf;
     use std::fs::DirBuilder;
+    use tempdir::TempDir;
 
     #[test]
     fn test_check_repo_init_with_invalid_path() {
@@ -482,6 +629,73 @@ mod tests {
         assert!(check_repo_init(&path).is_ok());
     }
 
+    #[test]
+    fn get_head_reads_the_branch_of_the_given_dir() {
+        let repo = TempDir::new("get-head").unwrap();
+        git_command(&["init"], repo.path()).unwrap();
+        git_command(&["-c", "user.name=test", "-c", "user.email=test@example.com",
+                      "commit", "--allow-empty", "-m", "Initial commit."],
+                    repo.path()).unwrap();
+        git_command(&["checkout", "-b", "feature"], repo.path()).unwrap();
+        assert_eq!("feature", get_head(repo.path()).unwrap());
+    }
+
+    #[test]
+    fn git_push_without_a_pipeline_branch_explains_how_to_create_it() {
+        let repo = TempDir::new("git-push").unwrap();
+        git_command(&["init"], repo.path()).unwrap();
+        let e = git_push(repo.path(), "master").unwrap_err();
+        match e.kind {
+            Kind::MissingPipelineBranch => {},
+            _ => panic!("Unexpected error: {:?}", e)
+        }
+        let detail = e.detail.unwrap();
+        assert!(detail.starts_with("A master branch does not exist locally."));
+        assert!(detail.contains("git checkout -b master\n"));
+    }
+
+    #[test]
+    fn test_parse_review_refs() {
+        let output = "9b5b3dd1a4b2d1f0c6c7e6f1c0a3dd62b6c2f0e1\trefs/heads/_reviews/master/add-lint/10\n\
+                      3c6e0b8a9c15224a8228b9a98ca1531d2d10f6a5\trefs/heads/_reviews/master/add-lint/2\n\
+                      9b5b3dd1a4b2d1f0c6c7e6f1c0a3dd62b6c2f0e1\trefs/heads/_reviews/master/add-lint/latest\n\
+                      5f0e1b1a8c7d6e5f4a3b2c1d0e9f8a7b6c5d4e3f\trefs/heads/_reviews/master/add-lint-2/1\n";
+        let refs = parse_review_refs(output, "refs/heads/_reviews/master/add-lint/");
+        assert_eq!(vec![PatchsetRef{ number: 2,
+                                     sha: "3c6e0b8a9c15224a8228b9a98ca1531d2d10f6a5".to_string() },
+                        PatchsetRef{ number: 10,
+                                     sha: "9b5b3dd1a4b2d1f0c6c7e6f1c0a3dd62b6c2f0e1".to_string() }],
+                   refs);
+        assert!(parse_review_refs("", "refs/heads/_reviews/master/add-lint/").is_empty());
+    }
+
+    #[test]
+    fn test_find_stack() {
+        let rev_list = "1111111\n2222222\n3333333\n4444444\n";
+        let branches = "2222222 add-models\n\
+                        9999999 master\n\
+                        4444444 add-cli\n\
+                        4444444 add-cli-backup\n\
+                        8888888 unrelated\n";
+        let stack = find_stack(rev_list, branches, "master", "add-cli");
+        assert_eq!(vec![StackBranch{ name: "add-models".to_string(), sha: "2222222".to_string() },
+                        StackBranch{ name: "add-cli".to_string(), sha: "4444444".to_string() }],
+                   stack);
+        assert!(find_stack("", branches, "master", "master").is_empty());
+    }
+
+    #[test]
+    fn test_parse_review_branches() {
+        let output = "add-lint\n\
+                      add-lint/2 delivery/_reviews/master/add-lint/2\n\
+                      master delivery/master\n\
+                      add-lint-2 delivery/_reviews/master/add-lint-2/latest\n\
+                      review delivery/_reviews/master/add-lint/latest\n";
+        assert_eq!(vec!["add-lint/2".to_string(), "review".to_string()],
+                   parse_review_branches(output, "add-lint", "master"));
+        assert!(parse_review_branches(output, "add-lint", "release").is_empty());
+    }
+
     #[test]
     fn test_parse_line_from_remote() {
         test_parse_line_from_remote_with_eol("");
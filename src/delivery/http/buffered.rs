@@ -0,0 +1,171 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Responses held in memory
+//!
+//! Reading the body of a hyper `Response` consumes it. When the
+//! client has to look at the body of a response before deciding what
+//! to do with it, it reads the response into a `BufferedResponse` and
+//! rebuilds an equivalent `Response` from it afterwards, so callers
+//! can read the body again.
+
+use hyper;
+use hyper::Url;
+use hyper::client::response::Response as HyperResponse;
+use hyper::header::{ContentLength, Headers, TransferEncoding};
+use hyper::http::RawStatus;
+use hyper::net::NetworkStream;
+use hyper::status::StatusCode;
+use std::io;
+use std::io::prelude::*;
+use std::io::Cursor;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct BufferedResponse {
+    pub url: Url,
+    pub status: RawStatus,
+    pub headers: Headers,
+    pub body: Vec<u8>
+}
+
+impl BufferedResponse {
+    /// Read the whole body of `response` into memory.
+    pub fn read(mut response: HyperResponse) -> io::Result<BufferedResponse> {
+        let mut body = Vec::new();
+        try!(response.read_to_end(&mut body));
+        Ok(BufferedResponse {
+            url: response.url.clone(),
+            status: response.status_raw().clone(),
+            headers: response.headers.clone(),
+            body: body
+        })
+    }
+
+    pub fn body_str(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// Turn the buffered data back into a hyper `Response`.
+    pub fn into_response(self) -> hyper::Result<HyperResponse> {
+        let stream = MemoryStream::new(self.raw());
+        HyperResponse::new(self.url, Box::new(stream))
+    }
+
+    // The response as it came over the wire. The body is sent in one
+    // piece, whatever the original transfer encoding was.
+    fn raw(&self) -> Vec<u8> {
+        let mut headers = self.headers.clone();
+        headers.remove::<TransferEncoding>();
+        headers.set(ContentLength(self.body.len() as u64));
+        let RawStatus(code, ref reason) = self.status;
+        let reason = if reason.is_empty() {
+            StatusCode::from_u16(code).canonical_reason().unwrap_or("Unknown")
+        } else {
+            &reason[..]
+        };
+        let mut raw = format!("HTTP/1.1 {} {}\r\n{}\r\n", code, reason, headers).into_bytes();
+        raw.extend_from_slice(&self.body);
+        raw
+    }
+}
+
+// A `NetworkStream` that replies with canned bytes and ignores
+// whatever is written to it.
+struct MemoryStream {
+    reply: Cursor<Vec<u8>>
+}
+
+impl MemoryStream {
+    fn new(reply: Vec<u8>) -> MemoryStream {
+        MemoryStream { reply: Cursor::new(reply) }
+    }
+}
+
+impl Read for MemoryStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reply.read(buf)
+    }
+}
+
+impl Write for MemoryStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl NetworkStream for MemoryStream {
+    fn peer_addr(&mut self) -> io::Result<SocketAddr> {
+        Err(io::Error::new(io::ErrorKind::NotConnected, "in-memory response"))
+    }
+
+    fn set_read_timeout(&self, _dur: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_write_timeout(&self, _dur: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::Url;
+    use hyper::header::{ContentLength, Headers};
+    use hyper::http::RawStatus;
+    use hyper::status::StatusCode;
+    use std::io::prelude::*;
+
+    fn buffered(body: &str) -> BufferedResponse {
+        let mut headers = Headers::new();
+        headers.set_raw("X-Request-Id", vec![b"abc".to_vec()]);
+        BufferedResponse {
+            url: Url::parse("https://earth/api/v0/e/ncc-1701/orgs").unwrap(),
+            status: RawStatus(401, "Unauthorized".into()),
+            headers: headers,
+            body: body.as_bytes().to_vec()
+        }
+    }
+
+    #[test]
+    fn into_response_keeps_status_headers_and_body() {
+        let body = "{\"error\":\"token_expired\"}";
+        let mut response = buffered(body).into_response().unwrap();
+        assert_eq!(StatusCode::Unauthorized, response.status);
+        assert_eq!(Some(&ContentLength(body.len() as u64)),
+                   response.headers.get::<ContentLength>());
+        assert_eq!("abc", response.headers.get_raw("X-Request-Id")
+                   .map(|v| String::from_utf8_lossy(&v[0]).into_owned()).unwrap());
+        let mut read = String::new();
+        response.read_to_string(&mut read).unwrap();
+        assert_eq!(body, read);
+    }
+
+    #[test]
+    fn read_round_trips_a_response() {
+        let response = buffered("denied").into_response().unwrap();
+        let again = BufferedResponse::read(response).unwrap();
+        assert_eq!("denied", again.body_str());
+        assert_eq!(401, again.status.0);
+    }
+}
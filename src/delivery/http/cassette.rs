@@ -0,0 +1,398 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Cassettes: request and response pairs saved as JSON, to test
+//! commands against the Delivery API without a server.
+//!
+//! A `Recorder` sends the requests over another transport and adds
+//! every exchange to the cassette file, so that one cassette can hold
+//! the requests of several commands; a `Player` serves the
+//! responses of a cassette to the requests with the same method and
+//! path. Passwords and tokens found in JSON bodies are not saved.
+
+use errors::DeliveryError;
+use http::buffered::BufferedResponse;
+use http::transport::{Request, Transport};
+use hyper::Url;
+use hyper::client::response::Response as HyperResponse;
+use hyper::error::Error as HttpError;
+use hyper::header::Headers;
+use hyper::http::RawStatus;
+use rustc_serialize::json::{self, Json};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use types::DeliveryResult;
+use utils::read_file;
+
+// JSON fields whose values never make it to a cassette
+const SECRET_FIELDS: [&'static str; 2] = ["password", "token"];
+
+#[derive(RustcEncodable, RustcDecodable, Debug, Clone, PartialEq)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// Path and query of the URL
+    pub path: String,
+    pub body: String
+}
+
+#[derive(RustcEncodable, RustcDecodable, Debug, Clone, PartialEq)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub reason: String,
+    pub headers: BTreeMap<String, String>,
+    pub body: String
+}
+
+#[derive(RustcEncodable, RustcDecodable, Debug, Clone, PartialEq)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse
+}
+
+impl Interaction {
+    /// An interaction answering `method path` with a JSON `body`.
+    pub fn new(method: &str, path: &str, status: u16, body: &str) -> Interaction {
+        let mut headers = BTreeMap::new();
+        headers.insert("Content-Type".to_string(), "application/json".to_string());
+        Interaction {
+            request: RecordedRequest {
+                method: method.to_string(),
+                path: path.to_string(),
+                body: String::new()
+            },
+            response: RecordedResponse {
+                status: status,
+                reason: String::new(),
+                headers: headers,
+                body: body.to_string()
+            }
+        }
+    }
+
+    fn record(request: &Request, response: &BufferedResponse) -> Interaction {
+        let headers = response.headers.iter()
+            .map(|h| (h.name().to_string(), h.value_string()))
+            .collect();
+        let RawStatus(status, ref reason) = response.status;
+        Interaction {
+            request: RecordedRequest {
+                method: request.method.to_string(),
+                path: url_path(&request.url),
+                body: redact(&request.body)
+            },
+            response: RecordedResponse {
+                status: status,
+                reason: reason.to_string(),
+                headers: headers,
+                body: redact(&response.body_str())
+            }
+        }
+    }
+
+    fn matches(&self, request: &Request) -> bool {
+        self.request.method == request.method.to_string()
+            && self.request.path == url_path(&request.url)
+    }
+
+    fn response(&self, url: &str) -> Result<HyperResponse, HttpError> {
+        let mut headers = Headers::new();
+        for (name, value) in self.response.headers.iter() {
+            headers.set_raw(name.clone(), vec![value.clone().into_bytes()]);
+        }
+        let buffered = BufferedResponse {
+            url: try!(Url::parse(url)),
+            status: RawStatus(self.response.status, self.response.reason.clone().into()),
+            headers: headers,
+            body: self.response.body.clone().into_bytes()
+        };
+        buffered.into_response()
+    }
+}
+
+#[derive(RustcEncodable, RustcDecodable, Debug, Clone, Default, PartialEq)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> DeliveryResult<Cassette> {
+        let content = try!(read_file(&path.to_path_buf()));
+        Ok(try!(json::decode(&content)))
+    }
+
+    pub fn save(&self, path: &Path) -> DeliveryResult<()> {
+        let mut file = try!(File::create(path));
+        let content = format!("{}\n", json::as_pretty_json(self));
+        try!(file.write_all(content.as_bytes()));
+        Ok(())
+    }
+}
+
+/// Send the requests over `inner` and add the exchanges to a cassette
+#[derive(Debug)]
+pub struct Recorder {
+    inner: Arc<Transport>,
+    path: PathBuf,
+    cassette: Mutex<Cassette>
+}
+
+impl Recorder {
+    /// Record to the cassette at `path`, after the interactions it
+    /// already holds if it exists.
+    pub fn new(path: &Path, inner: Arc<Transport>) -> DeliveryResult<Recorder> {
+        let cassette = if path.exists() {
+            try!(Cassette::load(path))
+        } else {
+            Cassette::default()
+        };
+        Ok(Recorder {
+            inner: inner,
+            path: path.to_path_buf(),
+            cassette: Mutex::new(cassette)
+        })
+    }
+
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().unwrap().clone()
+    }
+}
+
+impl Transport for Recorder {
+    fn send(&self, request: &Request) -> Result<HyperResponse, HttpError> {
+        let response = try!(self.inner.send(request));
+        let buffered = try!(BufferedResponse::read(response));
+        let mut cassette = self.cassette.lock().unwrap();
+        cassette.interactions.push(Interaction::record(request, &buffered));
+        // Saved after every request, as the command may exit anytime
+        if let Err(e) = cassette.save(&self.path) {
+            let msg = format!("Unable to save the cassette {}: {:?}",
+                              self.path.display(), e);
+            return Err(HttpError::Io(io::Error::new(io::ErrorKind::Other, msg)))
+        }
+        buffered.into_response()
+    }
+}
+
+/// Answer the requests with the responses of a cassette. Each
+/// interaction is served once, in order; once all the interactions
+/// for a request are used up, the last one is served again.
+#[derive(Debug)]
+pub struct Player {
+    // The interactions, and whether they were served already
+    interactions: Mutex<Vec<(Interaction, bool)>>
+}
+
+impl Player {
+    pub fn new(cassette: Cassette) -> Player {
+        let interactions = cassette.interactions.into_iter().map(|i| (i, false)).collect();
+        Player { interactions: Mutex::new(interactions) }
+    }
+
+    pub fn load(path: &Path) -> DeliveryResult<Player> {
+        match Cassette::load(path) {
+            Ok(cassette) => Ok(Player::new(cassette)),
+            Err(e) => {
+                let cause = e.detail.clone().unwrap_or_else(|| e.kind_name());
+                let msg = format!("Unable to load the cassette {}: {}",
+                                  path.display(), cause);
+                Err(DeliveryError{ kind: e.kind, detail: Some(msg) })
+            }
+        }
+    }
+
+    /// Whether every interaction of the cassette was served
+    pub fn is_done(&self) -> bool {
+        self.interactions.lock().unwrap().iter().all(|&(_, served)| served)
+    }
+}
+
+impl Transport for Player {
+    fn send(&self, request: &Request) -> Result<HyperResponse, HttpError> {
+        let mut interactions = self.interactions.lock().unwrap();
+        let unused = interactions.iter().position(|&(ref i, served)| !served && i.matches(request));
+        let index = unused.or_else(|| interactions.iter().rposition(|&(ref i, _)| i.matches(request)));
+        match index {
+            Some(n) => {
+                interactions[n].1 = true;
+                interactions[n].0.response(&request.url)
+            },
+            None => {
+                let msg = format!("No recorded response for {} {}",
+                                  request.method, url_path(&request.url));
+                Err(HttpError::Io(io::Error::new(io::ErrorKind::NotFound, msg)))
+            }
+        }
+    }
+}
+
+// The path and query of `url`, which identify a request in a cassette
+// whatever the server
+fn url_path(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(u) => match u.query() {
+            Some(q) => format!("{}?{}", u.path(), q),
+            None => u.path().to_string()
+        },
+        Err(_) => url.to_string()
+    }
+}
+
+// Hide the secrets of a JSON body
+fn redact(body: &str) -> String {
+    match Json::from_str(body) {
+        Ok(Json::Object(mut obj)) => {
+            let mut changed = false;
+            for field in SECRET_FIELDS.iter() {
+                if let Some(value) = obj.get_mut(*field) {
+                    *value = Json::String("REDACTED".to_string());
+                    changed = true;
+                }
+            }
+            if changed { Json::Object(obj).to_string() } else { body.to_string() }
+        },
+        _ => body.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::transport::{Request, Transport};
+    use hyper::client::response::Response as HyperResponse;
+    use hyper::error::Error as HttpError;
+    use hyper::header::Headers;
+    use hyper::method::Method;
+    use hyper::status::StatusCode;
+    use std::io::prelude::*;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use tempdir::TempDir;
+
+    fn request(method: Method, path: &str, body: &str) -> Request {
+        Request {
+            method: method,
+            url: format!("https://earth/api/v0/e/acme/{}", path),
+            headers: Headers::new(),
+            body: body.to_string()
+        }
+    }
+
+    fn body(mut response: HyperResponse) -> String {
+        let mut body = String::new();
+        response.read_to_string(&mut body).unwrap();
+        body
+    }
+
+    // Stands in for the network
+    #[derive(Debug)]
+    struct Fake {
+        sent: Mutex<Vec<String>>
+    }
+
+    impl Transport for Fake {
+        fn send(&self, request: &Request) -> Result<HyperResponse, HttpError> {
+            self.sent.lock().unwrap().push(request.url.clone());
+            Interaction::new("POST", "/", 200, "{\"token\":\"s3cr3t\",\"user\":\"kirk\"}")
+                .response(&request.url)
+        }
+    }
+
+    #[test]
+    fn record_then_replay() {
+        let tempdir = TempDir::new("cassette").unwrap();
+        let path = tempdir.path().join("cassette.json");
+        let fake = Arc::new(Fake { sent: Mutex::new(Vec::new()) });
+        let recorder = Recorder::new(&path, fake.clone()).unwrap();
+
+        let req = request(Method::Post, "users/kirk/get-token",
+                          "{\"username\":\"kirk\",\"password\":\"sesame\"}");
+        let response = recorder.send(&req).unwrap();
+        assert_eq!(StatusCode::Ok, response.status);
+        assert_eq!("{\"token\":\"s3cr3t\",\"user\":\"kirk\"}", body(response));
+        assert_eq!(1, fake.sent.lock().unwrap().len());
+
+        let saved = Cassette::load(&path).unwrap();
+        assert_eq!(recorder.cassette(), saved);
+        let interaction = &saved.interactions[0];
+        assert_eq!("POST", interaction.request.method);
+        assert_eq!("/api/v0/e/acme/users/kirk/get-token", interaction.request.path);
+        assert_eq!("{\"password\":\"REDACTED\",\"username\":\"kirk\"}", interaction.request.body);
+        assert_eq!("{\"token\":\"REDACTED\",\"user\":\"kirk\"}", interaction.response.body);
+
+        let player = Player::load(&path).unwrap();
+        let replayed = player.send(&req).unwrap();
+        assert_eq!(StatusCode::Ok, replayed.status);
+        assert_eq!("{\"token\":\"REDACTED\",\"user\":\"kirk\"}", body(replayed));
+        assert!(player.is_done());
+    }
+
+    #[test]
+    fn recorder_appends_to_the_cassette() {
+        let tempdir = TempDir::new("cassette").unwrap();
+        let path = tempdir.path().join("cassette.json");
+        let fake = Arc::new(Fake { sent: Mutex::new(Vec::new()) });
+
+        // One recorder per command, as with DELIVERY_CASSETTE_RECORD
+        let first = Recorder::new(&path, fake.clone()).unwrap();
+        first.send(&request(Method::Get, "orgs", "")).unwrap();
+        let second = Recorder::new(&path, fake.clone()).unwrap();
+        second.send(&request(Method::Get, "orgs/o/projects", "")).unwrap();
+
+        let saved = Cassette::load(&path).unwrap();
+        let paths: Vec<&str> = saved.interactions.iter()
+            .map(|i| &i.request.path[..]).collect();
+        assert_eq!(vec!["/api/v0/e/acme/orgs", "/api/v0/e/acme/orgs/o/projects"], paths);
+    }
+
+    #[test]
+    fn player_serves_interactions_in_order() {
+        let player = Player::new(Cassette {
+            interactions: vec![
+                Interaction::new("GET", "/api/v0/e/acme/orgs", 503, "{}"),
+                Interaction::new("GET", "/api/v0/e/acme/orgs", 200, "{\"orgs\":[]}")
+            ]
+        });
+        let req = request(Method::Get, "orgs", "");
+        assert_eq!(StatusCode::ServiceUnavailable, player.send(&req).unwrap().status);
+        assert!(!player.is_done());
+        assert_eq!("{\"orgs\":[]}", body(player.send(&req).unwrap()));
+        assert!(player.is_done());
+        // The last one is served again
+        assert_eq!(StatusCode::Ok, player.send(&req).unwrap().status);
+    }
+
+    #[test]
+    fn player_without_a_match() {
+        let player = Player::new(Cassette::default());
+        match player.send(&request(Method::Delete, "orgs/o", "")) {
+            Err(HttpError::Io(e)) => {
+                assert_eq!("No recorded response for DELETE /api/v0/e/acme/orgs/o",
+                           e.to_string())
+            },
+            other => panic!("unexpected {:?}", other.map(|r| r.status))
+        }
+    }
+
+    #[test]
+    fn load_missing_cassette() {
+        assert!(Player::load(&PathBuf::from("/nonexistent/cassette.json")).is_err());
+    }
+}
@@ -18,11 +18,18 @@
 use errors::{DeliveryError, Kind};
 use http::*;
 use hyper::status::StatusCode;
+use hyper::client::response::Response as HyperResponse;
 use rustc_serialize::json;
 use std::io::prelude::*;
 
 use config::Config;
 
+pub use http::models::{STAGES, PhaseRun, Stage, Progress, Change, ChangeSummary};
+
+// Starts the stack section of a change description; as an html
+// comment it doesn't show up in the rendered markdown.
+const STACK_MARKER: &'static str = "<!-- delivery review --stack -->";
+
 #[derive(RustcEncodable, RustcDecodable, Debug, Clone, PartialEq, PartialOrd)]
 pub struct Description {
     pub title: String,
@@ -46,6 +53,27 @@ impl Description {
         Ok(description)
     }
 
+    /// Replace the section of the description that links the change
+    /// to the changes it depends on (`parent`) and that depend on it
+    /// (`child`) in a stack of reviews. The section is dropped when
+    /// there is neither.
+    pub fn set_stack(&mut self, parent: Option<&str>, child: Option<&str>) {
+        let mut desc = match self.description.find(STACK_MARKER) {
+            Some(i) => self.description[..i].trim_right().to_string(),
+            None => self.description.trim_right().to_string()
+        };
+        if parent.is_some() || child.is_some() {
+            desc.push_str(&format!("\n\n{}\n", STACK_MARKER));
+            if let Some(p) = parent {
+                desc.push_str(&format!("* Depends on: {}\n", p));
+            }
+            if let Some(c) = child {
+                desc.push_str(&format!("* Required by: {}\n", c));
+            }
+        }
+        self.description = desc.trim().to_string();
+    }
+
     pub fn parse_text(text: &str) -> Result<Description, DeliveryError> {
         let mut items: Vec<&str> = text.lines().collect();
         let title = items[0].to_string();
@@ -59,6 +87,233 @@ impl Description {
     }
 }
 
+/// Fetch the pipeline state of a change
+pub fn get_change(config: &Config,
+                  change: &str) -> Result<Change, DeliveryError> {
+    let org = try!(config.organization());
+    let proj = try!(config.project());
+    let client = try!(APIClient::from_config(&config));
+    client.get_change(&org, &proj, change).map_err(|e| {
+        match e.kind {
+            Kind::ApiError(StatusCode::NotFound, _) => {
+                let msg = format!("Change {} was not found in project {} of \
+                                   organization {}", change, proj, org);
+                DeliveryError{ kind: Kind::ChangeNotFound, detail: Some(msg) }
+            },
+            _ => e
+        }
+    })
+}
+
+/// Filters for listing the changes of a project
+#[derive(Debug, Clone)]
+pub struct ChangeFilter {
+    pub state: String,
+    pub pipeline: Option<String>,
+    pub submitter: Option<String>
+}
+
+impl Default for ChangeFilter {
+    fn default() -> ChangeFilter {
+        ChangeFilter {
+            state: String::from("open"),
+            pipeline: None,
+            submitter: None
+        }
+    }
+}
+
+impl ChangeFilter {
+    // The server only knows about open and merged changes; delivered
+    // changes are merged changes that we narrow down on our side.
+    fn api_state(&self) -> &str {
+        match &self.state[..] {
+            "delivered" => "merged",
+            s => s
+        }
+    }
+
+    /// Build the path of one page of the changes listing, starting
+    /// after the change `after` when given.
+    pub fn path(&self, org: &str, proj: &str,
+                limit: usize, after: Option<&str>) -> String {
+        let mut path = format!("orgs/{}/projects/{}/changes?state={}&limit={}",
+                               org, proj, self.api_state(), limit);
+        if let Some(ref pipe) = self.pipeline {
+            path.push_str(&format!("&pipeline={}", pipe));
+        }
+        if let Some(id) = after {
+            path.push_str(&format!("&id={}", id));
+        }
+        path
+    }
+
+    pub fn matches(&self, change: &ChangeSummary) -> bool {
+        if !change.has_state(&self.state) {
+            return false
+        }
+        match self.submitter {
+            Some(ref user) => change.submit_by.as_ref() == Some(user),
+            None => true
+        }
+    }
+}
+
+// Number of changes we request per page of the changes listing
+const CHANGES_PAGE_SIZE: usize = 50;
+
+/// List the changes of the configured project matching `filter`,
+/// following the pagination of the server until the listing is
+/// exhausted. The most recent changes come first.
+pub fn list(config: &Config,
+            filter: &ChangeFilter) -> Result<Vec<ChangeSummary>, DeliveryError> {
+    let org = try!(config.organization());
+    let proj = try!(config.project());
+    let client = try!(APIClient::from_config(&config));
+    let mut changes: Vec<ChangeSummary> = Vec::new();
+    let mut after: Option<String> = None;
+    loop {
+        let path = filter.path(&org, &proj, CHANGES_PAGE_SIZE,
+                               after.as_ref().map(|a| &a[..]));
+        debug!("changes path: {}", path);
+        let mut result = try!(client.get(&path));
+        let page = match result.status {
+            StatusCode::Ok => {
+                let mut body_string = String::new();
+                let _x = try!(result.read_to_string(&mut body_string));
+                try!(ChangeSummary::parse_json_list(&body_string))
+            },
+            StatusCode::Unauthorized => {
+                let msg = "API request returned 401 (unauthorized)".to_string();
+                return Err(DeliveryError{ kind: Kind::AuthenticationFailed,
+                                          detail: Some(msg)})
+            },
+            error_code @ _ => {
+                let msg = format!("API request returned {}",
+                                  error_code);
+                let mut detail = String::new();
+                let e = match result.read_to_string(&mut detail) {
+                    Ok(_) => Ok(detail),
+                    Err(e) => Err(e)
+                };
+                return Err(DeliveryError{ kind: Kind::ApiError(error_code, e),
+                                          detail: Some(msg)})
+            }
+        };
+        let last_page = page.len() < CHANGES_PAGE_SIZE;
+        after = page.last().map(|c| c.id.clone());
+        for change in page.into_iter() {
+            // Guard against servers that include the starting change
+            if !changes.iter().any(|c| c.id == change.id) {
+                changes.push(change);
+            }
+        }
+        if last_page || after.is_none() {
+            break;
+        }
+    }
+    Ok(changes.into_iter().filter(|c| filter.matches(c)).collect())
+}
+
+/// Find the id of the latest open change submitted from the topic
+/// branch `topic` to `pipeline`.
+pub fn latest_id_for_topic(config: &Config,
+                           pipeline: &str,
+                           topic: &str) -> Result<String, DeliveryError> {
+    let filter = ChangeFilter {
+        pipeline: Some(pipeline.to_string()),
+        .. ChangeFilter::default()
+    };
+    let changes = try!(list(config, &filter));
+    match changes.into_iter().find(|c| c.topic == topic) {
+        Some(c) => Ok(c.id),
+        None => {
+            let msg = format!("No open change found for branch {} \
+                               targeted for pipeline {}", topic, pipeline);
+            Err(DeliveryError{ kind: Kind::ChangeNotFound,
+                               detail: Some(msg)})
+        }
+    }
+}
+
+/// Approve a change, merging it into its pipeline
+pub fn approve(config: &Config, change: &str) -> Result<(), DeliveryError> {
+    change_action(config, change, "merge")
+}
+
+/// Deliver a change that has passed acceptance
+pub fn deliver(config: &Config, change: &str) -> Result<(), DeliveryError> {
+    change_action(config, change, "accept")
+}
+
+/// Abandon a change, deleting it from the server
+pub fn abandon(config: &Config, change: &str) -> Result<(), DeliveryError> {
+    let org = try!(config.organization());
+    let proj = try!(config.project());
+    let client = try!(APIClient::from_config(&config));
+    let path = format!("orgs/{}/projects/{}/changes/{}", org, proj, change);
+    let result = try!(client.delete(&path));
+    check_action_result(result, "abandon", change, &proj, &org)
+}
+
+// Trigger a pipeline action, the equivalent of pressing the
+// "Approve" or "Deliver" button in the web UI
+fn change_action(config: &Config,
+                 change: &str,
+                 action: &str) -> Result<(), DeliveryError> {
+    let org = try!(config.organization());
+    let proj = try!(config.project());
+    let client = try!(APIClient::from_config(&config));
+    let path = format!("orgs/{}/projects/{}/changes/{}/{}",
+                       org, proj, change, action);
+    let result = try!(client.post(&path, ""));
+    check_action_result(result, action, change, &proj, &org)
+}
+
+fn check_action_result(mut result: HyperResponse,
+                       action: &str,
+                       change: &str,
+                       proj: &str,
+                       org: &str) -> Result<(), DeliveryError> {
+    match result.status {
+        StatusCode::Ok | StatusCode::Created | StatusCode::NoContent => Ok(()),
+        StatusCode::Unauthorized => {
+            let msg = "API request returned 401 (unauthorized)".to_string();
+            Err(DeliveryError{ kind: Kind::AuthenticationFailed,
+                               detail: Some(msg)})
+        },
+        StatusCode::Forbidden => {
+            let msg = format!("API request returned 403 while trying to {} \
+                               change {}", action, change);
+            Err(DeliveryError{ kind: Kind::Forbidden,
+                               detail: Some(msg)})
+        },
+        StatusCode::NotFound => {
+            let msg = format!("Change {} was not found in project {} of \
+                               organization {}", change, proj, org);
+            Err(DeliveryError{ kind: Kind::ChangeNotFound,
+                               detail: Some(msg)})
+        },
+        StatusCode::PreconditionFailed | StatusCode::Conflict => {
+            let mut detail = String::new();
+            let _x = try!(result.read_to_string(&mut detail));
+            Err(DeliveryError{ kind: Kind::ChangeNotReady,
+                               detail: Some(detail)})
+        },
+        error_code @ _ => {
+            let msg = format!("API request returned {}",
+                              error_code);
+            let mut detail = String::new();
+            let e = match result.read_to_string(&mut detail) {
+                Ok(_) => Ok(detail),
+                Err(e) => Err(e)
+            };
+            Err(DeliveryError{ kind: Kind::ApiError(error_code, e),
+                               detail: Some(msg)})
+        }
+    }
+}
+
 /// Fetch the description for a change
 pub fn get(config: &Config,
            change: &str) -> Result<Description, DeliveryError> {
@@ -193,4 +448,100 @@ mod tests {
         assert_eq!(expect, desc);
     }
 
+    #[test]
+    fn description_set_stack_test() {
+        let mut desc = Description { title: "a title".to_string(),
+                                     description: "so descriptive!\n".to_string() };
+        desc.set_stack(Some("add-models (change abc)"), None);
+        assert_eq!("so descriptive!\n\n<!-- delivery review --stack -->\n\
+                    * Depends on: add-models (change abc)", desc.description);
+        desc.set_stack(Some("add-models (change abc)"), Some("add-cli (change def)"));
+        assert_eq!("so descriptive!\n\n<!-- delivery review --stack -->\n\
+                    * Depends on: add-models (change abc)\n\
+                    * Required by: add-cli (change def)", desc.description);
+        desc.set_stack(None, None);
+        assert_eq!("so descriptive!", desc.description);
+    }
+
+    #[test]
+    fn change_parse_json_test() {
+        let response = "{\"id\":\"4bc3f44f-d81f-48a5-bd38-2c7963cb6d94\",\
+                        \"topic\":\"add-lint\",\"target\":\"master\",\
+                        \"state\":\"open\",\"title\":\"Add lint\",\
+                        \"submit_by\":\"kirk\",\"stages\":[{\"stage\":\"verify\",\
+                        \"status\":\"failed\",\"phases\":[\
+                        {\"name\":\"unit\",\"status\":\"passed\"},\
+                        {\"name\":\"lint\",\"status\":\"failed\"}]}]}";
+        let change = Change::parse_json(response).unwrap();
+        assert_eq!("add-lint", change.topic);
+        assert_eq!(Some("kirk".to_string()), change.submit_by);
+        let verify = change.stage("verify").unwrap();
+        assert_eq!("failed", verify.status);
+        assert_eq!(PhaseRun{ name: "lint".to_string(), status: "failed".to_string() },
+                   verify.phases[1]);
+        assert!(change.stage("build").is_none());
+        assert!(!change.stage_passed("verify"));
+        assert!(!change.stage_passed("build"));
+    }
+
+    #[test]
+    fn change_progress_test() {
+        let response = "{\"id\":\"abc\",\"topic\":\"foo\",\"target\":\"master\",\
+                        \"state\":\"merged\",\"stages\":[\
+                        {\"stage\":\"verify\",\"status\":\"passed\",\"phases\":[\
+                        {\"name\":\"unit\",\"status\":\"passed\"}]},\
+                        {\"stage\":\"build\",\"status\":\"running\",\"phases\":[\
+                        {\"name\":\"unit\",\"status\":\"passed\"},\
+                        {\"name\":\"publish\",\"status\":\"running\"}]}]}";
+        let mut change = Change::parse_json(response).unwrap();
+        assert_eq!(Progress::Reached, change.progress("verify"));
+        assert_eq!(Progress::Waiting, change.progress("build"));
+        assert_eq!(Progress::Waiting, change.progress("delivered"));
+        change.stages[1].phases[1].status = "failed".to_string();
+        assert_eq!(Progress::Failed("build".to_string(), "publish".to_string()),
+                   change.progress("delivered"));
+    }
+
+    #[test]
+    fn change_summary_parse_json_list_test() {
+        let response = "[{\"id\":\"abc\",\"topic\":\"foo\",\"state\":\"open\"},\
+                        {\"id\":\"def\",\"topic\":\"bar\",\"state\":\"open\"}]";
+        let changes = ChangeSummary::parse_json_list(response).unwrap();
+        assert_eq!(2, changes.len());
+        assert_eq!("def", changes[1].id);
+        assert_eq!("bar", changes[1].topic);
+        assert_eq!(None, changes[1].current_stage());
+    }
+
+    #[test]
+    fn change_summary_current_stage_test() {
+        let response = "[{\"id\":\"abc\",\"topic\":\"foo\",\"state\":\"merged\",\
+                        \"delivered_at\":null,\"submit_by\":\"kirk\",\"stages\":[\
+                        {\"stage\":\"verify\",\"status\":\"passed\",\"phases\":[]},\
+                        {\"stage\":\"build\",\"status\":\"running\",\"phases\":[]}]}]";
+        let changes = ChangeSummary::parse_json_list(response).unwrap();
+        assert_eq!(Some("build"), changes[0].current_stage());
+        assert!(changes[0].has_state("merged"));
+        assert!(!changes[0].has_state("delivered"));
+    }
+
+    #[test]
+    fn change_filter_test() {
+        let filter = ChangeFilter {
+            state: "delivered".to_string(),
+            pipeline: Some("master".to_string()),
+            submitter: Some("kirk".to_string())
+        };
+        assert_eq!("orgs/o/projects/p/changes?state=merged&limit=10&pipeline=master&id=abc",
+                   filter.path("o", "p", 10, Some("abc")));
+        let mut change = ChangeSummary::parse_json_list(
+            "[{\"id\":\"abc\",\"topic\":\"foo\",\"state\":\"merged\",\
+             \"submit_by\":\"kirk\",\"delivered_at\":\"2016-10-01 10:00:00\"}]"
+        ).unwrap().remove(0);
+        assert!(filter.matches(&change));
+        change.submit_by = Some("spock".to_string());
+        assert!(!filter.matches(&change));
+        assert_eq!("orgs/o/projects/p/changes?state=open&limit=50",
+                   ChangeFilter::default().path("o", "p", 50, None));
+    }
 }
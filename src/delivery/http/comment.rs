@@ -0,0 +1,126 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use errors::{DeliveryError, Kind};
+use http::APIClient;
+use http::models::Comment;
+use hyper::status::StatusCode;
+use rustc_serialize::json::{Json, ToJson};
+use std::collections::BTreeMap;
+use config::Config;
+
+/// A comment to post on a patchset of a change. The comment is
+/// anchored to the given lines of `file` when both are set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewComment {
+    pub content: String,
+    pub patchset: u64,
+    pub file: Option<String>,
+    pub lines: Option<(u64, u64)>
+}
+
+impl NewComment {
+    pub fn to_json(&self) -> String {
+        let mut obj = BTreeMap::new();
+        obj.insert("content".to_string(), self.content.to_json());
+        obj.insert("patchset".to_string(), self.patchset.to_json());
+        match (&self.file, self.lines) {
+            (&Some(ref file), Some((first, last))) => {
+                obj.insert("type".to_string(), "line".to_json());
+                obj.insert("file".to_string(), file.to_json());
+                obj.insert("line_range".to_string(), vec![first, last].to_json());
+            },
+            _ => {
+                obj.insert("type".to_string(), "patchset".to_json());
+            }
+        }
+        Json::Object(obj).to_string()
+    }
+}
+
+/// Parse a line number (`12`) or an inclusive range of lines (`12-15`)
+pub fn parse_line_range(lines: &str) -> Result<(u64, u64), DeliveryError> {
+    let bounds: Vec<&str> = lines.splitn(2, '-').collect();
+    let first = try!(bounds[0].trim().parse::<u64>());
+    let last = match bounds.get(1) {
+        Some(l) => try!(l.trim().parse::<u64>()),
+        None => first
+    };
+    if first == 0 || last < first {
+        let msg = format!("Invalid line range: {}", lines);
+        return Err(DeliveryError{ kind: Kind::OptionConstraint, detail: Some(msg) })
+    }
+    Ok((first, last))
+}
+
+fn comments_path(org: &str, proj: &str, change: &str) -> String {
+    format!("orgs/{}/projects/{}/changes/{}/comments", org, proj, change)
+}
+
+/// Fetch the comment threads of a change
+pub fn list(config: &Config, change: &str) -> Result<Vec<Comment>, DeliveryError> {
+    let org = try!(config.organization());
+    let proj = try!(config.project());
+    let client = try!(APIClient::from_config(&config));
+    client.get_decoded(&comments_path(&org, &proj, change))
+}
+
+/// Post a comment on a change
+pub fn create(config: &Config,
+              change: &str,
+              comment: &NewComment) -> Result<(), DeliveryError> {
+    let org = try!(config.organization());
+    let proj = try!(config.project());
+    let client = try!(APIClient::from_config(&config));
+    let result = try!(client.post(&comments_path(&org, &proj, change),
+                                  &comment.to_json()));
+    match result.status {
+        StatusCode::Ok | StatusCode::Created | StatusCode::NoContent => Ok(()),
+        _ => Err(client.error_response(result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_comment_to_json_test() {
+        let mut comment = NewComment {
+            content: "Make it so".to_string(),
+            patchset: 2,
+            file: None,
+            lines: None
+        };
+        assert_eq!("{\"content\":\"Make it so\",\"patchset\":2,\"type\":\"patchset\"}",
+                   comment.to_json());
+        comment.file = Some("recipes/default.rb".to_string());
+        comment.lines = Some((12, 14));
+        assert_eq!("{\"content\":\"Make it so\",\"file\":\"recipes/default.rb\",\
+                    \"line_range\":[12,14],\"patchset\":2,\"type\":\"line\"}",
+                   comment.to_json());
+    }
+
+    #[test]
+    fn parse_line_range_test() {
+        assert_eq!((12, 12), parse_line_range("12").unwrap());
+        assert_eq!((12, 15), parse_line_range("12-15").unwrap());
+        assert!(parse_line_range("15-12").is_err());
+        assert!(parse_line_range("0").is_err());
+        assert!(parse_line_range("twelve").is_err());
+    }
+}
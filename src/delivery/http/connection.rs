@@ -0,0 +1,174 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! The hyper clients behind `APIClient`. Clients with the same
+//! connection settings share one hyper client, and with it its pool of
+//! keep-alive connections, for the whole invocation.
+
+use config::Config;
+use http::proxy::{ProxySettings, ProxyConnector};
+use http::timeout::{Timeouts, TimeoutConnector};
+use http::tls::TlsSettings;
+use hyper;
+use hyper::client::pool::Pool;
+use hyper::net::HttpsConnector;
+use std::cell::RefCell;
+use std::sync::Arc;
+use std::time::Duration;
+use types::DeliveryResult;
+
+/// Everything that decides how connections to the server are made
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConnectionSettings {
+    pub tls: TlsSettings,
+    pub proxy: Option<ProxySettings>,
+    pub timeouts: Timeouts
+}
+
+impl ConnectionSettings {
+    pub fn from_config(config: &Config) -> DeliveryResult<ConnectionSettings> {
+        Ok(ConnectionSettings {
+            tls: try!(TlsSettings::from_config(config)),
+            proxy: try!(ProxySettings::from_config(config)),
+            timeouts: Timeouts::from_config(config)
+        })
+    }
+
+    fn build_client(&self) -> DeliveryResult<hyper::Client> {
+        let ssl = try!(self.tls.ssl());
+        let tcp = TimeoutConnector { timeout: self.timeouts.connect };
+        let mut client = match self.proxy {
+            Some(ref proxy) => {
+                let connector = ProxyConnector::new(proxy.clone(), ssl, tcp);
+                hyper::Client::with_connector(Pool::with_connector(Default::default(), connector))
+            },
+            None => {
+                let connector = HttpsConnector::with_connector(ssl, tcp);
+                hyper::Client::with_connector(Pool::with_connector(Default::default(), connector))
+            }
+        };
+        client.set_read_timeout(self.timeouts.read);
+        client.set_write_timeout(self.timeouts.read);
+        Ok(client)
+    }
+}
+
+/// The number and duration of the requests sent during the invocation
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RequestStats {
+    pub count: u32,
+    pub total: Duration,
+    pub slowest: Duration
+}
+
+impl RequestStats {
+    pub fn record(&mut self, elapsed: Duration) {
+        self.count += 1;
+        self.total = self.total + elapsed;
+        if elapsed > self.slowest {
+            self.slowest = elapsed;
+        }
+    }
+
+    pub fn average(&self) -> Duration {
+        if self.count == 0 {
+            Duration::from_secs(0)
+        } else {
+            self.total / self.count
+        }
+    }
+}
+
+thread_local! {
+    static CLIENTS: RefCell<Vec<(ConnectionSettings, Arc<hyper::Client>)>> = RefCell::new(Vec::new());
+    static STATS: RefCell<RequestStats> = RefCell::new(RequestStats::default())
+}
+
+/// Return the hyper client for `settings`, building it on first use.
+pub fn shared_client(settings: &ConnectionSettings) -> DeliveryResult<Arc<hyper::Client>> {
+    let existing = CLIENTS.with(|c| {
+        c.borrow().iter()
+            .find(|&&(ref s, _)| s == settings)
+            .map(|&(_, ref client)| client.clone())
+    });
+    if let Some(client) = existing {
+        return Ok(client)
+    }
+    debug!("Creating an HTTP client for {:?}", settings);
+    let client = Arc::new(try!(settings.build_client()));
+    CLIENTS.with(|c| c.borrow_mut().push((settings.clone(), client.clone())));
+    Ok(client)
+}
+
+pub fn record_request(elapsed: Duration) {
+    STATS.with(|s| s.borrow_mut().record(elapsed));
+}
+
+pub fn request_stats() -> RequestStats {
+    STATS.with(|s| *s.borrow())
+}
+
+/// Print the request count and latency in the debug output
+pub fn log_request_stats() {
+    let stats = request_stats();
+    if stats.count > 0 {
+        debug!("API requests: {} over {} connection pool(s), {}ms total, \
+                {}ms average, {}ms slowest",
+               stats.count, CLIENTS.with(|c| c.borrow().len()),
+               millis(stats.total), millis(stats.average()), millis(stats.slowest));
+    }
+}
+
+fn millis(d: Duration) -> u64 {
+    d.as_secs() * 1000 + (d.subsec_nanos() / 1_000_000) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn same_client(a: &Arc<hyper::Client>, b: &Arc<hyper::Client>) -> bool {
+        &**a as *const hyper::Client == &**b as *const hyper::Client
+    }
+
+    #[test]
+    fn shared_client_test() {
+        let settings = ConnectionSettings::default();
+        let first = shared_client(&settings).unwrap();
+        let second = shared_client(&settings).unwrap();
+        assert!(same_client(&first, &second));
+
+        let mut other = ConnectionSettings::default();
+        other.timeouts.read = Some(Duration::from_secs(1));
+        let third = shared_client(&other).unwrap();
+        assert!(!same_client(&first, &third));
+    }
+
+    #[test]
+    fn request_stats_test() {
+        let mut stats = RequestStats::default();
+        assert_eq!(Duration::from_secs(0), stats.average());
+        stats.record(Duration::from_millis(100));
+        stats.record(Duration::from_millis(300));
+        assert_eq!(2, stats.count);
+        assert_eq!(Duration::from_millis(200), stats.average());
+        assert_eq!(Duration::from_millis(300), stats.slowest);
+    }
+}
@@ -17,26 +17,50 @@
 
 use std::fmt;
 use std::env;
-use std::path::PathBuf;
 use hyper;
 use hyper::status::StatusCode;
 use hyper::client::response::Response as HyperResponse;
 use hyper::error::Error as HttpError;
-use http;
+use hyper::header::Headers;
 use http::token::TokenResponse;
 use mime;
 use rustc_serialize::json;
+use rustc_serialize::Decodable;
+use http::models::{Change, Project, Pipeline, PipelineList,
+                   Organization, OrganizationList, ScmProvider};
 use std::io::prelude::*;
 use errors::{DeliveryError, Kind};
 use token::TokenStore;
+use token::helper as token_helper;
 use utils::say::sayln;
 use config::Config;
 use types::DeliveryResult;
+use http::buffered::BufferedResponse;
+use http::connection::ConnectionSettings;
+use http::tls::TlsSettings;
+use http::retry::{self, RetryPolicy};
+use http::transport::{Request, Transport};
+use hyper::method::Method;
+use std::cell::{Cell, RefCell};
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
 
 mod headers;
 pub mod token;
 pub mod change;
 pub mod saml;
+pub mod models;
+pub mod comment;
+pub mod tls;
+pub mod proxy;
+pub mod timeout;
+pub mod retry;
+pub mod connection;
+pub mod buffered;
+pub mod paging;
+pub mod transport;
+pub mod cassette;
 
 #[derive(Debug)]
 enum HProto {
@@ -74,7 +98,19 @@ enum HTTPMethod {
     GET,
     PUT,
     POST,
-    DELETE
+    DELETE,
+    PATCH,
+    HEAD
+}
+
+impl HTTPMethod {
+    // Whether sending the request twice has the same effect as once
+    fn is_idempotent(&self) -> bool {
+        match *self {
+            HTTPMethod::POST | HTTPMethod::PATCH => false,
+            _ => true
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -82,7 +118,17 @@ pub struct APIClient {
     proto: HProto,
     host: String,
     enterprise: String,
-    auth: Option<APIAuth>
+    auth: RefCell<Option<APIAuth>>,
+    // Used to request a new token when the current one expires
+    config: Option<Config>,
+    // Sent with every request, after the default ones
+    extra_headers: Headers,
+    // Sends the requests; over the network, it shares its hyper
+    // client with the other clients of the invocation
+    transport: Arc<Transport>,
+    retry: RetryPolicy,
+    // How many times the last request was sent
+    attempts: Cell<u32>
 }
 
 impl APIClient {
@@ -91,13 +137,17 @@ impl APIClient {
     /// required configuration values are missing. Expects to find
     /// `server`, `api_port`, `enterprise`, and `user` in the config
     /// along with a token mapped for those values.
+    ///
+    /// The token is not verified up front. If the server answers a
+    /// request with a "token expired" 401, the client requests a new
+    /// token once and replays the request.
     pub fn from_config(config: &Config) -> DeliveryResult<APIClient> {
         APIClient::from_config_no_auth(config).and_then(|mut c| {
             let auth = try!(APIAuth::from_config(&config));
             c.set_auth(auth);
+            c.config = Some(config.clone());
             Ok(c)
         })
-        // Ok(client)
     }
 
     /// Create a new `APIClient` from the specified `Config`
@@ -111,27 +161,47 @@ impl APIClient {
         let ent = try!(config.enterprise());
         let proto_str = try!(config.api_protocol());
         let proto = try!(HProto::from_str(&proto_str));
-        Ok(APIClient::new(proto, &host, &ent))
+        let settings = try!(ConnectionSettings::from_config(config));
+        let http = try!(connection::shared_client(&settings));
+        let transport = try!(transport::for_client(http));
+        let mut client = APIClient::new(proto, &host, &ent, transport);
+        client.retry = RetryPolicy::from_config(config);
+        Ok(client)
     }
 
     /// Create a new `APIClient` using HTTP attached to the enterprise
     /// given by `ent`.
-    pub fn new_http(host: &str, ent: &str) -> APIClient {
-        APIClient::new(HProto::HTTP, host, ent)
+    pub fn new_http(host: &str, ent: &str, tls: &TlsSettings) -> DeliveryResult<APIClient> {
+        APIClient::with_tls(HProto::HTTP, host, ent, tls)
     }
 
     /// Create a new `APIClient` using HTTPS attached to the
-    /// enterprise given by `ent`.
-    pub fn new_https(host: &str, ent: &str) -> APIClient {
-        APIClient::new(HProto::HTTPS, host, ent)
+    /// enterprise given by `ent`, verifying the server with `tls`
+    /// like the clients created with `from_config` do.
+    pub fn new_https(host: &str, ent: &str, tls: &TlsSettings) -> DeliveryResult<APIClient> {
+        APIClient::with_tls(HProto::HTTPS, host, ent, tls)
     }
 
-    fn new(proto: HProto, host: &str, ent: &str) -> APIClient {
+    // A client with the default connection settings but for `tls`
+    fn with_tls(proto: HProto, host: &str, ent: &str,
+                tls: &TlsSettings) -> DeliveryResult<APIClient> {
+        let settings = ConnectionSettings { tls: tls.clone(), .. ConnectionSettings::default() };
+        let http = try!(connection::shared_client(&settings));
+        let transport = try!(transport::for_client(http));
+        Ok(APIClient::new(proto, host, ent, transport))
+    }
+
+    fn new(proto: HProto, host: &str, ent: &str, transport: Arc<Transport>) -> APIClient {
         APIClient {
             proto: proto,
             host: String::from(host),
             enterprise: String::from(ent),
-            auth: None
+            auth: RefCell::new(None),
+            config: None,
+            extra_headers: Headers::new(),
+            transport: transport,
+            retry: RetryPolicy::default(),
+            attempts: Cell::new(0)
         }
     }
 
@@ -163,8 +233,8 @@ impl APIClient {
                 }
             },
             error_code @ _ => {
-                let msg = format!("API request returned {}",
-                                  error_code);
+                let msg = format!("API request returned {}{}",
+                                  error_code, self.attempts_note());
                 let mut detail = String::new();
                 let e = match response.read_to_string(&mut detail) {
                     Ok(_) => Ok(detail),
@@ -187,7 +257,14 @@ impl APIClient {
     }
 
     pub fn set_auth(&mut self, auth: APIAuth) {
-        self.auth = Some(auth);
+        self.auth = RefCell::new(Some(auth));
+    }
+
+    /// Add a header to every request of this client. It replaces
+    /// any header of the same name the client would send, such as
+    /// `Content-Type`.
+    pub fn add_header(&mut self, name: &str, value: &str) {
+        self.extra_headers.set_raw(name.to_string(), vec![value.as_bytes().to_vec()]);
     }
 
     pub fn api_url(&self, path: &str) -> String {
@@ -203,6 +280,15 @@ impl APIClient {
         self.req_with_body(HTTPMethod::DELETE, path, "")
     }
 
+    pub fn head(&self, path: &str) -> Result<HyperResponse, HttpError> {
+        self.req_with_body(HTTPMethod::HEAD, path, "")
+    }
+
+    pub fn patch(&self, path: &str,
+                 payload: &str) -> Result<HyperResponse, HttpError> {
+        self.req_with_body(HTTPMethod::PATCH, path, payload)
+    }
+
     pub fn put(&self, path: &str,
                payload: &str) -> Result<HyperResponse, HttpError> {
         self.req_with_body(HTTPMethod::PUT, path, payload)
@@ -213,90 +299,171 @@ impl APIClient {
         self.req_with_body(HTTPMethod::POST, path, payload)
     }
 
-    /// Send a request using the specified HTTP verb. If `payload` is
-    /// an empty string, no request body will be sent. This could be an
-    /// `Options<String>` but (I think) keeping the simple `&str`
-    /// avoids an allocation.
+    /// Send a POST request that is safe to retry, such as the creation
+    /// of a resource where a 409 Conflict counts as success.
+    pub fn post_idempotent(&self, path: &str,
+                           payload: &str) -> Result<HyperResponse, HttpError> {
+        self.req_with_refresh(HTTPMethod::POST, path, payload, true)
+    }
+
     fn req_with_body(&self,
                      http_method: HTTPMethod,
                      path: &str,
                      payload: &str) -> Result<HyperResponse, HttpError> {
-        let url = self.api_url(path);
-        let client = hyper::Client::new();
-        let req = match http_method {
-            HTTPMethod::GET    => client.get(&url),
-            HTTPMethod::PUT    => client.put(&url),
-            HTTPMethod::POST   => client.post(&url),
-            HTTPMethod::DELETE => client.delete(&url)
-        };
-        let req = req.header(self.json_content());
-        let req = match self.auth {
-            Some(ref auth) => {
-                let (deliv_user, deliv_token) = auth.auth_headers();
-                req.header(deliv_user).header(deliv_token)
-            },
-            None => req
-        };
-        debug!("Request: {:?} Path: {:?} Payload: {:?}",
-                http_method, path, payload);
-        if payload.is_empty() {
-            req.send()
-        } else {
-            req.body(payload).send()
-        }
+        let retry = http_method.is_idempotent();
+        self.req_with_refresh(http_method, path, payload, retry)
     }
 
-    pub fn pipeline_exists(&self,
-                          org: &str, proj: &str, pipe: &str) -> bool {
-        let path = format!("orgs/{}/projects/{}/pipelines/{}", org, proj, pipe);
-        match self.get(&path) {
-            Ok(res) => {
-                match res.status {
-                    StatusCode::Ok => {
-                        return true;
-                    },
-                    _ => {
-                        return false;
-                    }
-                }
+    /// Send the request and, when the server says the token expired,
+    /// request a new token and send the request once more. If no new
+    /// token can be had, as with `--non-interactive`, the 401 response
+    /// is handed back to the caller.
+    fn req_with_refresh(&self,
+                        http_method: HTTPMethod,
+                        path: &str,
+                        payload: &str,
+                        retry: bool) -> Result<HyperResponse, HttpError> {
+        let response = try!(self.req_with_retries(&http_method, path, payload, retry));
+        if response.status != StatusCode::Unauthorized || !self.can_refresh_token() {
+            return Ok(response)
+        }
+        let buffered = try!(BufferedResponse::read(response));
+        if !TokenResponse::parse_token_expired(&buffered.body_str()) {
+            return buffered.into_response()
+        }
+        match self.refresh_token() {
+            Ok(()) => {
+                debug!("Replaying {:?} {} with the new token", http_method, path);
+                self.req_with_retries(&http_method, path, payload, retry)
             },
             Err(e) => {
-                sayln("red", &format!("pipeline_exists: HttpError: {:?}", e));
-                return false;
+                sayln("red", &format!("Unable to request a new token: {}", e));
+                buffered.into_response()
             }
         }
     }
 
-    pub fn project_exists(&self,
-                          org: &str,
-                          proj: &str) -> bool {
-
-        let path = format!("orgs/{}/projects/{}", org, proj);
-        match self.get(&path) {
-            Ok(res) => {
-                match res.status {
-                    StatusCode::Ok => {
-                        return true;
-                    },
-                    _ => {
-                        return false;
-                    }
+    // Only clients created with `from_config` can request a new token,
+    // and never with `--non-interactive`
+    fn can_refresh_token(&self) -> bool {
+        match self.config {
+            Some(ref c) => !c.non_interactive.unwrap_or(false),
+            None => false
+        }
+    }
+
+    // Replace the expired token with a new one, requested the same way
+    // `delivery token` does.
+    fn refresh_token(&self) -> DeliveryResult<()> {
+        sayln("red", "Token expired");
+        let config = self.config.as_ref().expect("client without config");
+        let auth = try!(APIAuth::from_token_request(config));
+        *self.auth.borrow_mut() = Some(auth);
+        Ok(())
+    }
+
+    /// Send the request, and when `retry` is set, send it again on
+    /// network errors and 502/503/504 responses until the retry policy
+    /// runs out of attempts.
+    fn req_with_retries(&self,
+                        http_method: &HTTPMethod,
+                        path: &str,
+                        payload: &str,
+                        retry: bool) -> Result<HyperResponse, HttpError> {
+        let mut backoff = self.retry.backoff();
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            self.attempts.set(attempt);
+            let result = self.send(http_method, path, payload);
+            let again = retry && attempt < self.retry.attempts && match result {
+                Ok(ref response) => retry::retryable_status(response.status),
+                Err(ref e) => retry::retryable_error(e)
+            };
+            if !again {
+                if let Err(ref e) = result {
+                    debug!("Giving up on {:?} {}{}: {}",
+                           http_method, path, self.attempts_note(), e);
                 }
-            },
-            Err(e) => {
-                sayln("red", &format!("project_exists: HttpError: {:?}", e));
-                return false;
+                return result
             }
+            let delay = retry::with_jitter(backoff.next_delay());
+            debug!("Attempt {} of {:?} {} failed: {:?}; retrying in {:?}",
+                   attempt, http_method, path,
+                   result.as_ref().map(|r| r.status), delay);
+            thread::sleep(delay);
         }
     }
 
+    fn attempts_note(&self) -> String {
+        match self.attempts.get() {
+            0 | 1 => String::new(),
+            n => format!(" (after {} attempts)", n)
+        }
+    }
+
+    /// Send a request using the specified HTTP verb. If `payload` is
+    /// an empty string, no request body will be sent. The request goes
+    /// through the transport of the client.
+    fn send(&self,
+            http_method: &HTTPMethod,
+            path: &str,
+            payload: &str) -> Result<HyperResponse, HttpError> {
+        let method = match *http_method {
+            HTTPMethod::GET    => Method::Get,
+            HTTPMethod::PUT    => Method::Put,
+            HTTPMethod::POST   => Method::Post,
+            HTTPMethod::DELETE => Method::Delete,
+            HTTPMethod::PATCH  => Method::Patch,
+            HTTPMethod::HEAD   => Method::Head
+        };
+        let mut headers = Headers::new();
+        headers.set(self.json_content());
+        if let Some(ref auth) = *self.auth.borrow() {
+            let (deliv_user, deliv_token) = auth.auth_headers();
+            headers.set(deliv_user);
+            headers.set(deliv_token);
+        }
+        for header in self.extra_headers.iter() {
+            headers.set_raw(header.name().to_string(),
+                            vec![header.value_string().into_bytes()]);
+        }
+        let request = Request {
+            method: method,
+            url: self.api_url(path),
+            headers: headers,
+            body: payload.to_string()
+        };
+        debug!("Request: {:?} Path: {:?} Payload: {:?}",
+                http_method, path, payload);
+        let started = Instant::now();
+        let result = self.transport.send(&request);
+        connection::record_request(started.elapsed());
+        result
+    }
+
+    /// Return true if the pipeline exists, false if the server
+    /// doesn't know it, and the error of any other response.
+    pub fn pipeline_exists(&self,
+                          org: &str, proj: &str, pipe: &str) -> DeliveryResult<bool> {
+        found(self.get_pipeline(org, proj, pipe))
+    }
+
+    /// Return true if the project exists, false if the server
+    /// doesn't know it, and the error of any other response.
+    pub fn project_exists(&self,
+                          org: &str,
+                          proj: &str) -> DeliveryResult<bool> {
+        found(self.get_project(org, proj))
+    }
+
     pub fn create_delivery_project(&self, org: &str,
                                    proj: &str) -> DeliveryResult<StatusCode> {
         let path = format!("orgs/{}/projects", org);
         // FIXME: we'd like to use the native struct->json stuff, but
         // seeing link issues.
         let payload = format!("{{\"name\":\"{}\"}}", proj);
-        self.parse_response(try!(self.post(&path, &payload)))
+        self.parse_response(try!(self.post_idempotent(&path, &payload)))
     }
 
     pub fn create_github_project(&self, org: &str, proj: &str,
@@ -313,29 +480,22 @@ impl APIClient {
                                     \"verify_ssl\": {}\
                                 }}\
                               }}", proj, repo_name, git_org, pipe, ssl);
-        self.parse_response(try!(self.post(&path, &payload)))
+        self.parse_response(try!(self.post_idempotent(&path, &payload)))
     }
 
     fn get_scm_server_config(&self, scm: &str) -> DeliveryResult<Vec<json::Json>> {
-        let json = try!(APIClient::parse_json(self.get("scm-providers")));
-        debug!("Endpoint[scm-providers]: {:?}", json);
-        if let Some(data) = json.as_array() {
-            for obj in data.iter() {
-                if let Some(scp) = obj.as_object() {
-                    let name = scp.get("name").unwrap().as_string().unwrap();
-                    if name == scm {
-                        let scp_config = scp.get("scmSetupConfigs").unwrap().as_array().unwrap();
-                        debug!("{:?} Config: {:?}", scm, scp_config);
-                        return Ok(scp_config.clone())
-                    }
-                }
-            }
+        let providers = try!(self.get_scm_providers());
+        debug!("Endpoint[scm-providers]: {:?}", providers);
+        match providers.into_iter().find(|p| p.name == scm) {
+            Some(provider) => {
+                debug!("{:?} Config: {:?}", scm, provider.setup_configs);
+                Ok(provider.setup_configs)
+            },
+            None => Err(DeliveryError {
+                kind: Kind::ExpectedJsonString,
+                detail: Some(format!("Unable to find {:?} SCM Config in Delivery Server.", scm))
+            })
         }
-        Err(DeliveryError {
-            kind: Kind::ExpectedJsonString,
-            detail: Some(format!("Unable to find {:?} SCM Config in Delivery Server.\n\
-                                 JSON Output: {:?}", scm, json))
-        })
     }
 
     pub fn get_github_server_config(&self) -> DeliveryResult<Vec<json::Json>> {
@@ -362,7 +522,7 @@ impl APIClient {
         // Sadly the endpoint returns a Status 204 NoContent instead of 201 Created
         // therefore we need to hardcode the output of the sayln(" created)"
         //
-        self.parse_response(try!(self.post(&path, &payload)))
+        self.parse_response(try!(self.post_idempotent(&path, &payload)))
         // TODO: Fix the endpoint, delete this code and uncomment this line:
         //self.parse_response(try!(self.post(&path, &payload)))
     }
@@ -376,7 +536,87 @@ impl APIClient {
         let base_branch = base.unwrap_or("master");
         let payload = format!("{{\"name\":\"{}\",\"base\":\"{}\"}}", pipe, base_branch);
 
-        self.parse_response(try!(self.post(&path, &payload)))
+        self.parse_response(try!(self.post_idempotent(&path, &payload)))
+    }
+
+    /// Fetch the pipeline state of a change
+    pub fn get_change(&self, org: &str, proj: &str,
+                      change: &str) -> DeliveryResult<Change> {
+        self.get_decoded(&format!("orgs/{}/projects/{}/changes/{}",
+                                  org, proj, change))
+    }
+
+    pub fn get_project(&self, org: &str, proj: &str) -> DeliveryResult<Project> {
+        self.get_decoded(&format!("orgs/{}/projects/{}", org, proj))
+    }
+
+    pub fn get_pipeline(&self, org: &str, proj: &str,
+                        pipe: &str) -> DeliveryResult<Pipeline> {
+        self.get_decoded(&format!("orgs/{}/projects/{}/pipelines/{}",
+                                  org, proj, pipe))
+    }
+
+    /// List the names of the pipelines of a project
+    pub fn list_pipelines(&self, org: &str, proj: &str) -> DeliveryResult<Vec<String>> {
+        let list: PipelineList = try!(
+            self.get_decoded(&format!("orgs/{}/projects/{}/pipelines", org, proj)));
+        Ok(list.pipelines)
+    }
+
+    pub fn get_organization(&self, org: &str) -> DeliveryResult<Organization> {
+        self.get_decoded(&format!("orgs/{}", org))
+    }
+
+    /// List the names of the organizations of the enterprise
+    pub fn list_organizations(&self) -> DeliveryResult<Vec<String>> {
+        let list: OrganizationList = try!(self.get_decoded("orgs"));
+        Ok(list.orgs)
+    }
+
+    /// List the source code providers known to the server
+    pub fn get_scm_providers(&self) -> DeliveryResult<Vec<ScmProvider>> {
+        let mut response = try!(self.get("scm-providers"));
+        match response.status {
+            StatusCode::Ok => {
+                let mut body = String::new();
+                try!(response.read_to_string(&mut body));
+                ScmProvider::parse_json_list(&body)
+            },
+            _ => Err(self.error_response(response))
+        }
+    }
+
+    /// Send a GET request to `path` and decode the JSON body of the
+    /// response. Any status other than 200 is returned as an error.
+    pub fn get_decoded<T: Decodable>(&self, path: &str) -> DeliveryResult<T> {
+        let response = try!(self.get(path));
+        self.decode_response(response)
+    }
+
+    fn decode_response<T: Decodable>(&self,
+                                     mut response: HyperResponse) -> DeliveryResult<T> {
+        match response.status {
+            StatusCode::Ok => {
+                let mut body = String::new();
+                try!(response.read_to_string(&mut body));
+                debug!("Status: {:?} Body: {:?}", response.status, body);
+                Ok(try!(json::decode(&body)))
+            },
+            _ => Err(self.error_response(response))
+        }
+    }
+
+    // Turn an unexpected response into an error, using the same
+    // messages as `parse_response`.
+    fn error_response(&self, response: HyperResponse) -> DeliveryError {
+        match self.parse_response(response) {
+            Err(e) => e,
+            Ok(status) => {
+                let msg = format!("API request returned {}{}", status, self.attempts_note());
+                DeliveryError{ kind: Kind::ApiError(status, Ok(String::new())),
+                               detail: Some(msg) }
+            }
+        }
     }
 
     pub fn parse_json(result: Result<HyperResponse, HttpError>) -> DeliveryResult<json::Json> {
@@ -409,6 +649,16 @@ impl APIClient {
 
 }
 
+// Turn the result of fetching a resource into whether it exists,
+// keeping the errors other than a 404.
+fn found<T>(result: DeliveryResult<T>) -> DeliveryResult<bool> {
+    match result {
+        Ok(_) => Ok(true),
+        Err(DeliveryError{ kind: Kind::ApiError(StatusCode::NotFound, _), .. }) => Ok(false),
+        Err(e) => Err(e)
+    }
+}
+
 #[derive(Debug)]
 pub struct APIAuth {
     user: String,
@@ -425,23 +675,32 @@ impl APIAuth {
     /// Create an `APIAuth` struct from the specified `Config`
     /// instance. Expects to find valid values for `server`,
     /// `enterprise`, and `user`.
-    /// Reads API tokens from `$HOME/.delivery/api-tokens`.
+    /// Reads API tokens from the `TokenStore` of the config.
     /// Lookup for the stored token, if it does not exist request it.
+    /// With a `token_helper` set the token comes from the helper and
+    /// the token file is left alone.
+    /// The token is not verified; an expired token is replaced by the
+    /// `APIClient` when the server rejects it. A warning is printed
+    /// when a token of the store is about to expire; there is none
+    /// for the tokens of a `token_helper`, whose TTL is unknown.
     pub fn from_config(config: &Config) -> DeliveryResult<APIAuth> {
-        if !try!(http::token::verify(&config)) {
-            sayln("red", "Token expired");
-            return APIAuth::from_token_request(config)
+        if let Some(ref cmd) = config.token_helper {
+            let api_server = try!(config.api_host_and_port());
+            let ent = try!(config.enterprise());
+            let user = try!(config.user());
+            return match try!(token_helper::get(cmd, &api_server, &ent, &user)) {
+                Some(token) => Ok(APIAuth{ user: user, token: token }),
+                None => {
+                    debug!("No token from the token_helper, requesting one");
+                    APIAuth::from_token_request(&config)
+                }
+            }
         }
-        let tstore = match config.token_file {
-            Some(ref f) => {
-                let file = PathBuf::from(f);
-                try!(TokenStore::from_file(&file))
-            },
-            None => try!(TokenStore::from_home())
-        };
+        let tstore = try!(TokenStore::from_config(config));
         let api_server = try!(config.api_host_and_port());
         let ent = try!(config.enterprise());
         let user = try!(config.user());
+        tstore.warn_if_expiring(config, &api_server, &ent, &user);
         APIAuth::from_token_store(tstore, &api_server, &ent, &user).or_else(|e| {
             debug!("Ignoring {:?}\nRequesting token from config", e);
             APIAuth::from_token_request(&config)
@@ -501,6 +760,9 @@ mod tests {
     use super::*;
     use token::TokenStore;
     use std::env;
+    use std::io::prelude::*;
+    use std::net::TcpListener;
+    use std::thread;
     use tempdir::TempDir;
     use utils::path_join_many::PathJoinMany;
     use config::Config;
@@ -515,6 +777,21 @@ mod tests {
         assert!(auth.token.len() > 4);
     }
 
+    #[test]
+    fn found_test() {
+        assert_eq!(true, found(Ok(())).unwrap());
+        let missing: DeliveryResult<()> = Err(DeliveryError{
+            kind: Kind::ApiError(StatusCode::NotFound, Ok(String::new())),
+            detail: None
+        });
+        assert_eq!(false, found(missing).unwrap());
+        let forbidden: DeliveryResult<()> = Err(DeliveryError{
+            kind: Kind::ApiError(StatusCode::Forbidden, Ok(String::new())),
+            detail: None
+        });
+        assert!(found(forbidden).is_err());
+    }
+
     #[test]
     fn from_config_no_auth_test() {
         let config = Config::default()
@@ -561,22 +838,81 @@ mod tests {
                                               "cafecafe");
         assert_eq!(true, write_result.is_ok());
 
-        // Turning this test into a verification instead since `from_config`
-        // now validatates that the token extracted from the tstore is valid.
-        // That means that it hits an endpoint and we can't mock it in this test.
-        let client = APIClient::from_config(&config);
-        assert!(client.is_err());
+        // The token is read from the tstore without a round trip to
+        // the server
+        let client = APIClient::from_config(&config).unwrap();
+        let url = client.api_url("foo");
+        assert_eq!("https://earth/api/v0/e/ncc-1701/foo", url);
+        assert_eq!(Some("cafecafe".to_string()),
+                   client.auth.borrow().as_ref().map(|a| a.token()));
+    }
 
-        // Instead we use `from_config_no_auth` that doesn't validate the token
-        let client_from_store = APIClient::from_config_no_auth(&config).unwrap();
-        let url = client_from_store.api_url("foo");
-        assert_eq!("https://earth/api/v0/e/ncc-1701/foo", url)
+    #[test]
+    fn from_config_with_token_helper_test() {
+        let tempdir = TempDir::new("t1").ok().expect("TempDir failed");
+        let token_file = tempdir.path().join_many(&["api-tokens"]);
+        let mut config = Config::default()
+            .set_enterprise("ncc-1701")
+            .set_server("earth")
+            .set_user("kirk")
+            .set_token_file(token_file.to_str().unwrap())
+            .set_token_helper("cat >/dev/null; echo beefbeef; true");
+        config.non_interactive = Some(true);
+
+        let client = APIClient::from_config(&config).unwrap();
+        assert_eq!(Some("beefbeef".to_string()),
+                   client.auth.borrow().as_ref().map(|a| a.token()));
+        // The token file is not needed, so it is not created either
+        assert!(!token_file.exists());
+    }
+
+    #[test]
+    fn expired_token_non_interactive_test() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request);
+            let body = "{\"error\":\"token_expired\"}";
+            let reply = format!("HTTP/1.1 401 Unauthorized\r\n\
+                                 Content-Length: {}\r\n\
+                                 Connection: close\r\n\r\n{}",
+                                body.len(), body);
+            stream.write_all(reply.as_bytes()).unwrap();
+        });
+
+        let tempdir = TempDir::new("t1").ok().expect("TempDir failed");
+        let token_file = tempdir.path().join_many(&["api-tokens"]);
+        let mut config = Config::default()
+            .set_enterprise("ncc-1701")
+            .set_server("127.0.0.1")
+            .set_api_port(&port.to_string())
+            .set_api_protocol("http")
+            .set_user("kirk")
+            .set_token_file(token_file.to_str().unwrap());
+        config.non_interactive = Some(true);
+        config.no_proxy = Some("127.0.0.1".to_string());
+        let mut tstore = TokenStore::from_file(&token_file).ok().expect("tstore sad");
+        tstore.write_token(&config.api_host_and_port().unwrap(), "ncc-1701",
+                           "kirk", "cafecafe").unwrap();
+
+        // No new token can be requested, so the 401 comes back as is
+        // and turns into a `TokenExpired` error
+        let client = APIClient::from_config(&config).unwrap();
+        let response = client.get("orgs").unwrap();
+        server.join().unwrap();
+        assert_eq!(StatusCode::Unauthorized, response.status);
+        match client.parse_response(response) {
+            Err(DeliveryError{ kind: Kind::TokenExpired, .. }) => (),
+            other => panic!("expected TokenExpired, got {:?}", other)
+        }
     }
 
     #[test]
     fn http_api_url_test() {
-        let mut client = APIClient::new_http("localhost:4343",
-                                         "Chef");
+        let mut client = APIClient::new_http("localhost:4343", "Chef",
+                                             &TlsSettings::default()).unwrap();
         fake_test_env();
         let auth = APIAuth::from_env();
         client.set_auth(auth);
@@ -586,8 +922,8 @@ mod tests {
 
     #[test]
     fn https_api_url_test() {
-        let mut client = APIClient::new_https("localhost:4343",
-                                          "Chef");
+        let mut client = APIClient::new_https("localhost:4343", "Chef",
+                                              &TlsSettings::default()).unwrap();
         fake_test_env();
         let auth = APIAuth::from_env();
         client.set_auth(auth);
@@ -653,14 +989,10 @@ mod tests {
                                               "cafecafe");
         assert_eq!(true, write_result.is_ok());
 
-        let auth = APIAuth::from_config(&config);
-
-        // Turning this test into a verification instead since `from_config`
-        // now validatates that the token extracted from the tstore is valid.
-        // That means that it hits an endpoint and we can't mock it in this test.
-        assert!(auth.is_err());
+        let auth = APIAuth::from_config(&config).unwrap();
+        assert_eq!("kirk", auth.user());
+        assert_eq!("cafecafe", auth.token());
 
-        // Instead we use `from_token_store` that doesn't validate the token
         let auth_from_tstore = APIAuth::from_token_store(tstore, "earth", "ncc-1701", "kirk");
         assert_eq!(true,
                    auth_from_tstore.and_then(|a| {
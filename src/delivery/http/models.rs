@@ -0,0 +1,404 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Decoded representations of the resources served by the Delivery
+//! API. The structs only hold the fields the cli uses; anything else
+//! the server sends back is ignored when decoding.
+
+use errors::{DeliveryError, Kind};
+use rustc_serialize::json::{self, Json};
+
+/// The stages a change goes through, in pipeline order.
+pub const STAGES: [&'static str; 6] = ["verify", "build", "acceptance",
+                                        "union", "rehearsal", "delivered"];
+
+/// A phase run within a stage (e.g. `lint` in `verify`)
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone, PartialEq)]
+pub struct PhaseRun {
+    pub name: String,
+    pub status: String
+}
+
+/// A stage run of a change and the phases it ran
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone, PartialEq)]
+pub struct Stage {
+    pub stage: String,
+    pub status: String,
+    pub phases: Vec<PhaseRun>
+}
+
+/// How far a change got towards a given stage
+#[derive(Debug, PartialEq)]
+pub enum Progress {
+    Waiting,
+    Reached,
+    /// The stage and phase that failed
+    Failed(String, String)
+}
+
+/// The pipeline state of a change as returned by the
+/// `orgs/:org/projects/:proj/changes/:change` endpoint
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone, PartialEq)]
+pub struct Change {
+    pub id: String,
+    pub topic: String,
+    pub target: String,
+    pub state: String,
+    pub title: Option<String>,
+    pub submit_by: Option<String>,
+    pub stages: Vec<Stage>,
+    pub patchsets: Option<Vec<Patchset>>
+}
+
+impl Change {
+    pub fn parse_json(response: &str) -> Result<Change, DeliveryError> {
+        let change: Change = try!(json::decode(response));
+        Ok(change)
+    }
+
+    /// Return the run of the stage named `name`, if the change
+    /// has reached it.
+    pub fn stage(&self, name: &str) -> Option<&Stage> {
+        self.stages.iter().find(|s| s.stage == name)
+    }
+
+    /// Return the patchset numbered `number`, or the latest one
+    /// when `number` is `latest`.
+    pub fn patchset(&self, number: &str) -> Option<&Patchset> {
+        match self.patchsets {
+            Some(ref patchsets) => {
+                if number == "latest" {
+                    patchsets.iter().max_by_key(|p| p.sequence_number)
+                } else {
+                    patchsets.iter()
+                        .find(|p| p.sequence_number.to_string() == number)
+                }
+            },
+            None => None
+        }
+    }
+
+    /// Return true if the stage named `name` ran and passed.
+    pub fn stage_passed(&self, name: &str) -> bool {
+        match self.stage(name) {
+            Some(s) => s.status == "passed",
+            None => false
+        }
+    }
+
+    /// Return whether the change passed the stage named `until`,
+    /// failed a phase on the way there, or is still running.
+    pub fn progress(&self, until: &str) -> Progress {
+        if self.stage_passed(until) {
+            return Progress::Reached
+        }
+        for stage in self.stages.iter() {
+            if let Some(phase) = stage.phases.iter().find(|p| p.status == "failed") {
+                return Progress::Failed(stage.stage.clone(), phase.name.clone())
+            }
+            if stage.status == "failed" {
+                return Progress::Failed(stage.stage.clone(), String::new())
+            }
+        }
+        Progress::Waiting
+    }
+}
+
+/// A change as listed by the `orgs/:org/projects/:proj/changes`
+/// endpoint
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone, PartialEq)]
+pub struct ChangeSummary {
+    pub id: String,
+    pub topic: String,
+    pub target: Option<String>,
+    pub state: Option<String>,
+    pub title: Option<String>,
+    pub submit_by: Option<String>,
+    pub delivered_at: Option<String>,
+    pub stages: Option<Vec<Stage>>
+}
+
+impl ChangeSummary {
+    pub fn parse_json_list(response: &str) -> Result<Vec<ChangeSummary>, DeliveryError> {
+        let changes: Vec<ChangeSummary> = try!(json::decode(response));
+        Ok(changes)
+    }
+
+    /// Return the name of the last stage the change reached, if any.
+    pub fn current_stage(&self) -> Option<&str> {
+        match self.stages {
+            Some(ref stages) => {
+                STAGES.iter().rev()
+                    .find(|name| stages.iter().any(|s| s.stage == **name))
+                    .map(|name| *name)
+            },
+            None => None
+        }
+    }
+
+    /// Return true if the change matches the `state` filter; where
+    /// `delivered` is a merged change that has also been delivered.
+    pub fn has_state(&self, state: &str) -> bool {
+        match state {
+            "delivered" => self.delivered_at.is_some(),
+            s => match self.state {
+                Some(ref st) => st == s,
+                None => false
+            }
+        }
+    }
+}
+
+/// A patchset of a change; every push to the same topic branch
+/// creates a new one.
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone, PartialEq)]
+pub struct Patchset {
+    pub sequence_number: u64,
+    pub sha: String,
+    pub submitted_at: Option<String>,
+    pub submitted_by: Option<String>
+}
+
+/// A comment on a patchset, either general or anchored to a range of
+/// lines of a file, along with its replies.
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub id: u64,
+    pub author: Option<String>,
+    pub content: String,
+    pub datetime: Option<String>,
+    pub patchset: u64,
+    pub file: Option<String>,
+    pub line_range: Option<Vec<u64>>,
+    pub children: Option<Vec<Comment>>
+}
+
+impl Comment {
+    pub fn parse_json_list(response: &str) -> Result<Vec<Comment>, DeliveryError> {
+        let comments: Vec<Comment> = try!(json::decode(response));
+        Ok(comments)
+    }
+
+    /// Return the first and last line the comment is anchored to, if any.
+    pub fn lines(&self) -> Option<(u64, u64)> {
+        match self.line_range {
+            Some(ref range) if !range.is_empty() => {
+                Some((range[0], *range.last().unwrap()))
+            },
+            _ => None
+        }
+    }
+}
+
+/// A project as returned by `orgs/:org/projects/:proj`
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone, PartialEq)]
+pub struct Project {
+    pub name: String,
+    pub ent_name: Option<String>,
+    pub org_name: Option<String>
+}
+
+/// A pipeline as returned by `orgs/:org/projects/:proj/pipelines/:pipe`
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone, PartialEq)]
+pub struct Pipeline {
+    pub name: String,
+    pub base: Option<String>
+}
+
+/// The names of the pipelines of a project, as returned by
+/// `orgs/:org/projects/:proj/pipelines`
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone, PartialEq)]
+pub struct PipelineList {
+    pub pipelines: Vec<String>
+}
+
+/// An organization as returned by `orgs/:org`
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone, PartialEq)]
+pub struct Organization {
+    pub name: String,
+    pub project_count: Option<u64>
+}
+
+/// The names of the organizations of an enterprise, as returned
+/// by `orgs`
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone, PartialEq)]
+pub struct OrganizationList {
+    pub orgs: Vec<String>
+}
+
+/// A source code provider as listed by `scm-providers`, along with
+/// the server configurations set up for it. The setup configs vary
+/// by provider, so they are kept as raw JSON; a provider listed
+/// without them has not been set up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScmProvider {
+    pub name: String,
+    pub kind: Option<String>,
+    pub project_create_uri: Option<String>,
+    pub setup_configs: Vec<Json>
+}
+
+impl ScmProvider {
+    // The keys of this endpoint are camelCased and include `type`,
+    // which the derived decoder can't map onto a field.
+    pub fn from_json(json: &Json) -> Result<ScmProvider, DeliveryError> {
+        let string_at = |key: &str| {
+            json.find(key).and_then(|v| v.as_string()).map(|v| v.to_string())
+        };
+        match string_at("name") {
+            Some(name) => {
+                let configs = json.find("scmSetupConfigs")
+                    .and_then(|c| c.as_array())
+                    .map(|c| c.clone())
+                    .unwrap_or(Vec::new());
+                Ok(ScmProvider {
+                    name: name,
+                    kind: string_at("type"),
+                    project_create_uri: string_at("projectCreateUri"),
+                    setup_configs: configs
+                })
+            },
+            None => Err(DeliveryError {
+                kind: Kind::ExpectedJsonString,
+                detail: Some(format!("Invalid SCM provider: {}", json))
+            })
+        }
+    }
+
+    /// Decode the providers of the list, skipping the entries that
+    /// don't name a provider rather than failing on them.
+    pub fn parse_json_list(response: &str) -> Result<Vec<ScmProvider>, DeliveryError> {
+        let json = try!(Json::from_str(response));
+        match json.as_array() {
+            Some(providers) => {
+                Ok(providers.iter().filter_map(|p| {
+                    match ScmProvider::from_json(p) {
+                        Ok(provider) => Some(provider),
+                        Err(e) => {
+                            debug!("Skipping SCM provider: {:?}", e.detail);
+                            None
+                        }
+                    }
+                }).collect())
+            },
+            None => Err(DeliveryError {
+                kind: Kind::ExpectedJsonString,
+                detail: Some(format!("Expected a list of SCM providers: {}", json))
+            })
+        }
+    }
+
+    /// Return true if the provider has been set up on the server.
+    pub fn is_configured(&self) -> bool {
+        !self.setup_configs.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_serialize::json;
+
+    #[test]
+    fn change_fixture_test() {
+        let change = Change::parse_json(
+            include_str!("../../../tests/fixtures/api/change.json")).unwrap();
+        assert_eq!("a1ea6a8b-8b3e-4b6d-9d2c-1d2a6b1dc0f4", change.id);
+        assert_eq!("add-lint", change.topic);
+        assert_eq!("master", change.target);
+        assert_eq!(Some("Add lint checks".to_string()), change.title);
+        assert_eq!(2, change.stages.len());
+        assert_eq!(PhaseRun{ name: "syntax".to_string(), status: "running".to_string() },
+                   change.stage("build").unwrap().phases[1]);
+        assert_eq!(Progress::Reached, change.progress("verify"));
+        assert_eq!(2, change.patchset("latest").unwrap().sequence_number);
+        assert_eq!("3c6e0b8a9c15224a8228b9a98ca1531d2d10f6a5",
+                   change.patchset("1").unwrap().sha);
+        assert!(change.patchset("3").is_none());
+    }
+
+    #[test]
+    fn comments_fixture_test() {
+        let comments = Comment::parse_json_list(
+            include_str!("../../../tests/fixtures/api/comments.json")).unwrap();
+        assert_eq!(2, comments.len());
+        assert_eq!(None, comments[0].lines());
+        assert_eq!(Some("recipes/default.rb".to_string()), comments[1].file);
+        assert_eq!(Some((12, 14)), comments[1].lines());
+        let replies = comments[1].children.as_ref().unwrap();
+        assert_eq!(Some("kirk".to_string()), replies[0].author);
+        assert_eq!(None, replies[0].lines());
+    }
+
+    #[test]
+    fn project_and_pipeline_fixture_test() {
+        let project: Project = json::decode(
+            include_str!("../../../tests/fixtures/api/project.json")).unwrap();
+        assert_eq!("delivery-cli", project.name);
+        assert_eq!(Some("chef".to_string()), project.org_name);
+        let pipeline: Pipeline = json::decode(
+            include_str!("../../../tests/fixtures/api/pipeline.json")).unwrap();
+        assert_eq!("master", pipeline.name);
+        assert_eq!(None, pipeline.base);
+        let pipelines: PipelineList = json::decode(
+            include_str!("../../../tests/fixtures/api/pipelines.json")).unwrap();
+        assert_eq!(vec!["master".to_string(), "release".to_string()],
+                   pipelines.pipelines);
+    }
+
+    #[test]
+    fn organization_fixture_test() {
+        let org: Organization = json::decode(
+            include_str!("../../../tests/fixtures/api/organization.json")).unwrap();
+        assert_eq!("chef", org.name);
+        assert_eq!(Some(12), org.project_count);
+        let orgs: OrganizationList = json::decode(
+            include_str!("../../../tests/fixtures/api/organizations.json")).unwrap();
+        assert_eq!(vec!["chef".to_string(), "sandbox".to_string()], orgs.orgs);
+    }
+
+    #[test]
+    fn scm_providers_fixture_test() {
+        let providers = ScmProvider::parse_json_list(
+            include_str!("../../../tests/fixtures/api/scm_providers.json")).unwrap();
+        assert_eq!(2, providers.len());
+        assert_eq!("GitHub", providers[0].name);
+        assert_eq!(Some("github".to_string()), providers[0].kind);
+        assert!(providers[0].is_configured());
+        assert_eq!("Bitbucket", providers[1].name);
+        assert_eq!(Some("/bitbucket-projects".to_string()),
+                   providers[1].project_create_uri);
+        assert!(!providers[1].is_configured());
+        assert!(ScmProvider::parse_json_list("{\"name\":\"GitHub\"}").is_err());
+    }
+
+    #[test]
+    fn scm_providers_mixed_list_test() {
+        let providers = ScmProvider::parse_json_list(r#"[
+            {"name": "GitHub", "type": "github", "scmSetupConfigs": [{"root": "x"}]},
+            {"name": "Gerrit"},
+            {"type": "gitlab", "scmSetupConfigs": []},
+            "bogus"
+        ]"#).unwrap();
+        assert_eq!(2, providers.len());
+        assert!(providers[0].is_configured());
+        assert_eq!("Gerrit", providers[1].name);
+        assert_eq!(None, providers[1].kind);
+        assert_eq!(None, providers[1].project_create_uri);
+        assert!(!providers[1].is_configured());
+    }
+}
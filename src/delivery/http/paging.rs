@@ -0,0 +1,282 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Paged listings of the API. A `Listing` collects the items of the
+//! pages of a listing and tells which page to request next: the one
+//! of the `_links.next` link of the last page when there is one,
+//! otherwise the next `offset` when the path sets a `limit` and the
+//! last page was full.
+
+use errors::{DeliveryError, Kind};
+use hyper::Url;
+use rustc_serialize::json::Json;
+use types::DeliveryResult;
+
+#[derive(Debug)]
+pub struct Listing {
+    // Path of the API root, such as `/api/v0/e/acme/`
+    base_path: String,
+    // Path of the last page, relative to the API root
+    path: String,
+    first: Json,
+    // Field of the pages holding the items; `None` when the pages
+    // are arrays themselves
+    key: Option<String>,
+    items: Vec<Json>,
+    last_page: Vec<Json>,
+    next_link: Option<String>,
+    done: bool
+}
+
+impl Listing {
+    /// Start a listing with its first page, fetched from `path`.
+    /// `base_url` is the URL of the API root, as given by
+    /// `APIClient::api_url("")`.
+    pub fn start(base_url: &str, path: &str, body: &str) -> DeliveryResult<Listing> {
+        let base_path = match Url::parse(base_url) {
+            Ok(url) => url.path().to_string(),
+            Err(_) => base_url.to_string()
+        };
+        let first = try!(Json::from_str(body));
+        let key = try!(items_key(&first));
+        let mut listing = Listing {
+            base_path: base_path,
+            path: path.to_string(),
+            first: first.clone(),
+            key: key,
+            items: Vec::new(),
+            last_page: Vec::new(),
+            next_link: None,
+            done: false
+        };
+        listing.add_page(path, first);
+        Ok(listing)
+    }
+
+    /// Add the page fetched from `path`.
+    pub fn add(&mut self, path: &str, body: &str) -> DeliveryResult<()> {
+        let page = try!(Json::from_str(body));
+        self.add_page(path, page);
+        Ok(())
+    }
+
+    fn add_page(&mut self, path: &str, page: Json) {
+        let items = match self.key {
+            Some(ref key) => page.find(key).and_then(|i| i.as_array()).cloned(),
+            None => page.as_array().cloned()
+        }.unwrap_or(Vec::new());
+        // A server ignoring our offset would send the same page forever
+        if !self.last_page.is_empty() && items == self.last_page {
+            debug!("Page {} repeats the previous one; stopping", path);
+            self.done = true;
+            return
+        }
+        self.next_link = page.find_path(&["_links", "next", "href"])
+            .and_then(|h| h.as_string())
+            .map(|h| h.to_string());
+        self.path = path.to_string();
+        self.items.extend(items.iter().cloned());
+        self.last_page = items;
+    }
+
+    /// The path of the next page, relative to the API root, or `None`
+    /// when the listing is exhausted.
+    pub fn next_path(&self) -> Option<String> {
+        if self.done || self.last_page.is_empty() {
+            return None
+        }
+        let next = match self.next_link {
+            Some(ref href) => self.relative_path(href),
+            None => self.next_offset_path()
+        };
+        match next {
+            Some(ref p) if *p == self.path => None,
+            next => next
+        }
+    }
+
+    fn relative_path(&self, href: &str) -> Option<String> {
+        let path = match Url::parse(href) {
+            Ok(url) => match url.query() {
+                Some(q) => format!("{}?{}", url.path(), q),
+                None => url.path().to_string()
+            },
+            Err(_) => href.to_string()
+        };
+        if path.starts_with(&self.base_path) {
+            Some(path[self.base_path.len()..].to_string())
+        } else if !path.starts_with("/") {
+            Some(path)
+        } else {
+            debug!("Ignoring next link outside of the API: {}", href);
+            None
+        }
+    }
+
+    fn next_offset_path(&self) -> Option<String> {
+        let limit = match query_param(&self.path, "limit").and_then(|l| l.parse::<usize>().ok()) {
+            Some(l) if l > 0 => l,
+            _ => return None
+        };
+        if self.last_page.len() < limit {
+            return None
+        }
+        let offset = query_param(&self.path, "offset")
+            .and_then(|o| o.parse::<usize>().ok())
+            .unwrap_or(0);
+        Some(set_query_param(&self.path, "offset", &(offset + limit).to_string()))
+    }
+
+    /// The first page with the items of all the pages.
+    pub fn merged(&self) -> Json {
+        let items = Json::Array(self.items.clone());
+        match (self.key.as_ref(), self.first.clone()) {
+            (Some(key), Json::Object(mut obj)) => {
+                obj.remove("_links");
+                obj.insert(key.clone(), items);
+                Json::Object(obj)
+            },
+            _ => items
+        }
+    }
+}
+
+// The field holding the items of a page: none when the page is an
+// array, or the only array field of an object.
+fn items_key(page: &Json) -> DeliveryResult<Option<String>> {
+    match *page {
+        Json::Array(_) => return Ok(None),
+        Json::Object(ref obj) => {
+            let keys: Vec<&String> = obj.iter()
+                .filter(|&(k, v)| k != "_links" && v.is_array())
+                .map(|(k, _)| k)
+                .collect();
+            if keys.len() == 1 {
+                return Ok(Some(keys[0].clone()))
+            }
+        },
+        _ => {}
+    }
+    let msg = "Expected a listing: a JSON array, or an object with \
+               a single array field".to_string();
+    Err(DeliveryError{ kind: Kind::OptionConstraint, detail: Some(msg) })
+}
+
+/// The value of the query parameter `name` of `path`.
+pub fn query_param<'a>(path: &'a str, name: &str) -> Option<&'a str> {
+    let query = match path.find('?') {
+        Some(i) => &path[i + 1..],
+        None => return None
+    };
+    query.split('&')
+        .filter_map(|pair| {
+            let mut kv = pair.splitn(2, '=');
+            match (kv.next(), kv.next()) {
+                (Some(k), Some(v)) if k == name => Some(v),
+                _ => None
+            }
+        })
+        .next()
+}
+
+/// `path` with the query parameter `name` set to `value`.
+pub fn set_query_param(path: &str, name: &str, value: &str) -> String {
+    let (base, query) = match path.find('?') {
+        Some(i) => (&path[..i], &path[i + 1..]),
+        None => (path, "")
+    };
+    let mut pairs: Vec<String> = query.split('&')
+        .filter(|pair| !pair.is_empty() && pair.splitn(2, '=').next() != Some(name))
+        .map(|pair| pair.to_string())
+        .collect();
+    pairs.push(format!("{}={}", name, value));
+    format!("{}?{}", base, pairs.join("&"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_serialize::json::Json;
+
+    const BASE: &'static str = "https://earth/api/v0/e/acme/";
+
+    #[test]
+    fn query_params() {
+        let path = "orgs/o/projects/p/changes?state=open&limit=2";
+        assert_eq!(Some("2"), query_param(path, "limit"));
+        assert_eq!(None, query_param(path, "offset"));
+        assert_eq!(None, query_param("orgs", "limit"));
+        assert_eq!("orgs/o/projects/p/changes?state=open&limit=2&offset=4",
+                   set_query_param(path, "offset", "4"));
+        assert_eq!("orgs?limit=2&offset=6",
+                   set_query_param("orgs?offset=4&limit=2", "offset", "6"));
+        assert_eq!("orgs?offset=2", set_query_param("orgs", "offset", "2"));
+    }
+
+    #[test]
+    fn arrays_follow_the_offset_until_a_short_page() {
+        let mut listing = Listing::start(BASE, "changes?limit=2", "[1,2]").unwrap();
+        assert_eq!(Some("changes?limit=2&offset=2".to_string()), listing.next_path());
+        listing.add("changes?limit=2&offset=2", "[3,4]").unwrap();
+        assert_eq!(Some("changes?limit=2&offset=4".to_string()), listing.next_path());
+        listing.add("changes?limit=2&offset=4", "[5]").unwrap();
+        assert_eq!(None, listing.next_path());
+        assert_eq!(Json::from_str("[1,2,3,4,5]").unwrap(), listing.merged());
+    }
+
+    #[test]
+    fn no_limit_means_a_single_page() {
+        let listing = Listing::start(BASE, "orgs", "{\"orgs\":[\"a\",\"b\"]}").unwrap();
+        assert_eq!(None, listing.next_path());
+    }
+
+    #[test]
+    fn objects_follow_next_links() {
+        let first = "{\"changes\":[{\"id\":\"a\"}],\"total\":2,\
+                     \"_links\":{\"next\":{\"href\":\"/api/v0/e/acme/changes?page=2\"}}}";
+        let mut listing = Listing::start(BASE, "changes", first).unwrap();
+        assert_eq!(Some("changes?page=2".to_string()), listing.next_path());
+        let second = "{\"changes\":[{\"id\":\"b\"}],\"total\":2,\"_links\":{}}";
+        listing.add("changes?page=2", second).unwrap();
+        assert_eq!(None, listing.next_path());
+        let expect = "{\"changes\":[{\"id\":\"a\"},{\"id\":\"b\"}],\"total\":2}";
+        assert_eq!(Json::from_str(expect).unwrap(), listing.merged());
+    }
+
+    #[test]
+    fn absolute_next_links_are_made_relative() {
+        let first = "{\"orgs\":[1],\
+                     \"_links\":{\"next\":{\"href\":\"https://earth/api/v0/e/acme/orgs?page=2\"}}}";
+        let listing = Listing::start(BASE, "orgs", first).unwrap();
+        assert_eq!(Some("orgs?page=2".to_string()), listing.next_path());
+    }
+
+    #[test]
+    fn a_repeated_page_ends_the_listing() {
+        let mut listing = Listing::start(BASE, "orgs?limit=1", "[1]").unwrap();
+        listing.add("orgs?limit=1&offset=1", "[1]").unwrap();
+        assert_eq!(None, listing.next_path());
+        assert_eq!(Json::from_str("[1]").unwrap(), listing.merged());
+    }
+
+    #[test]
+    fn start_needs_a_listing() {
+        assert!(Listing::start(BASE, "orgs/o", "{\"name\":\"o\"}").is_err());
+        assert!(Listing::start(BASE, "orgs/o", "{\"a\":[],\"b\":[]}").is_err());
+        assert!(Listing::start(BASE, "orgs/o", "not json").is_err());
+    }
+}
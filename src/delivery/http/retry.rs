@@ -0,0 +1,126 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! The retry policy of the API client. Requests failing with a network
+//! error or a 502, 503 or 504 are sent again after an exponential
+//! backoff with jitter, as long as they are safe to repeat.
+
+use config::Config;
+use hyper::error::Error as HttpError;
+use hyper::status::StatusCode;
+use std::time::Duration;
+use time;
+use utils::backoff::Backoff;
+
+pub const DEFAULT_ATTEMPTS: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// How many times a request is sent at most
+    pub attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            attempts: DEFAULT_ATTEMPTS,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(8)
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Read the `retries` key, the number of retries after the first
+    /// attempt of a request.
+    pub fn from_config(config: &Config) -> RetryPolicy {
+        let mut policy = RetryPolicy::default();
+        if let Some(retries) = config.retries {
+            policy.attempts = retries as u32 + 1;
+        }
+        policy
+    }
+
+    pub fn backoff(&self) -> Backoff {
+        Backoff::new(self.initial_delay, self.max_delay, 2.0)
+    }
+}
+
+/// Whether a response with `status` is worth sending the request again
+pub fn retryable_status(status: StatusCode) -> bool {
+    match status {
+        StatusCode::BadGateway |
+        StatusCode::ServiceUnavailable |
+        StatusCode::GatewayTimeout => true,
+        _ => false
+    }
+}
+
+/// Whether a request that failed with `error` is worth sending again.
+/// TLS and protocol errors won't go away by themselves.
+pub fn retryable_error(error: &HttpError) -> bool {
+    match *error {
+        HttpError::Io(_) => true,
+        _ => false
+    }
+}
+
+/// Spread `delay` between half and all of its value, so clients that
+/// failed together don't all retry at the same time.
+pub fn with_jitter(delay: Duration) -> Duration {
+    let ms = delay.as_secs() * 1000 + (delay.subsec_nanos() / 1_000_000) as u64;
+    if ms < 2 {
+        return delay
+    }
+    let half = ms / 2;
+    Duration::from_millis(half + time::precise_time_ns() % (half + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::Config;
+    use hyper::status::StatusCode;
+    use std::time::Duration;
+
+    #[test]
+    fn from_config_test() {
+        assert_eq!(DEFAULT_ATTEMPTS, RetryPolicy::from_config(&Config::default()).attempts);
+        let mut config = Config::default();
+        config.retries = Some(0);
+        assert_eq!(1, RetryPolicy::from_config(&config).attempts);
+    }
+
+    #[test]
+    fn retryable_status_test() {
+        assert!(retryable_status(StatusCode::BadGateway));
+        assert!(retryable_status(StatusCode::GatewayTimeout));
+        assert!(!retryable_status(StatusCode::InternalServerError));
+        assert!(!retryable_status(StatusCode::Conflict));
+    }
+
+    #[test]
+    fn with_jitter_test() {
+        for _ in 0..20 {
+            let delay = with_jitter(Duration::from_secs(4));
+            assert!(delay >= Duration::from_secs(2));
+            assert!(delay <= Duration::from_secs(4));
+        }
+    }
+}
@@ -18,9 +18,21 @@
 use errors::{DeliveryError, Kind};
 use http::*;
 use hyper::status::StatusCode;
+use rand::{OsRng, Rng};
+use rustc_serialize::hex::ToHex;
 use rustc_serialize::json;
 use std::io::prelude::*;
+use std::io::{self, BufReader};
+use std::net::{TcpListener, TcpStream};
+use std::str;
+use std::thread;
+use std::time::{Duration, Instant};
 use config::Config;
+use types::DeliveryResult;
+
+/// How long to wait for the browser to hand back the token, unless
+/// `saml_callback_timeout` says otherwise.
+pub const DEFAULT_CALLBACK_TIMEOUT: u64 = 120;
 
 #[derive(RustcEncodable, RustcDecodable, Debug, Clone)]
 pub struct LookupResponse {
@@ -64,9 +76,180 @@ pub fn is_enabled(config: &Config) -> Result<bool, DeliveryError> {
     }
 }
 
+/// A short-lived HTTP listener on 127.0.0.1. Once the SAML login is
+/// done, the Delivery web UI redirects the browser to its
+/// `redirect_url` with the new token: `GET /<state>?token=<token>`.
+pub struct TokenCallback {
+    listener: TcpListener,
+    // Random path of the redirect URL, so that other local processes
+    // can't hand us a token of their choosing
+    state: String
+}
+
+impl TokenCallback {
+    /// Listen on a port picked by the system
+    pub fn bind() -> DeliveryResult<TokenCallback> {
+        let listener = try!(TcpListener::bind("127.0.0.1:0"));
+        let mut rng = try!(OsRng::new());
+        let mut state = [0u8; 16];
+        rng.fill_bytes(&mut state);
+        Ok(TokenCallback{ listener: listener, state: state.to_hex() })
+    }
+
+    /// The URL the web UI should redirect the browser to
+    pub fn redirect_url(&self) -> DeliveryResult<String> {
+        let addr = try!(self.listener.local_addr());
+        Ok(format!("http://{}/{}", addr, self.state))
+    }
+
+    /// Wait up to `timeout` for the browser to bring the token.
+    /// Requests for anything else are answered with a 400 and ignored.
+    pub fn wait_for_token(&self, timeout: Duration) -> DeliveryResult<Option<String>> {
+        try!(self.listener.set_nonblocking(true));
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    match self.answer(stream, deadline) {
+                        Ok(Some(token)) => return Ok(Some(token)),
+                        Ok(None) => debug!("Ignoring a request that is not a token callback"),
+                        Err(e) => debug!("Ignoring a failed token callback: {:?}", e)
+                    }
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(Duration::from_millis(100))
+                },
+                Err(e) => return Err(DeliveryError::from(e))
+            }
+        }
+        Ok(None)
+    }
+
+    // Answer the request of `stream`, giving up on reading it at
+    // `deadline` so that a slow client can't hold the CLI past it
+    fn answer(&self, mut stream: TcpStream, deadline: Instant) -> DeliveryResult<Option<String>> {
+        try!(stream.set_nonblocking(false));
+        let mut request_line = String::new();
+        {
+            let mut reader = BufReader::new(DeadlineReader{ stream: &stream, deadline: deadline });
+            try!(reader.read_line(&mut request_line));
+            // Read the headers so that closing the connection doesn't
+            // reset it before the browser gets the answer
+            let mut header = String::new();
+            while try!(reader.read_line(&mut header)) > 0 && header.trim() != "" {
+                header.clear();
+            }
+        }
+        let token = parse_callback(&request_line, &self.state);
+        let (status, body) = match token {
+            Some(_) => ("200 OK", "The Delivery CLI got your API token; you can close this window."),
+            None => ("400 Bad Request", "This is not a Delivery CLI token callback.")
+        };
+        let reply = format!("HTTP/1.1 {}\r\n\
+                             Content-Type: text/plain\r\n\
+                             Content-Length: {}\r\n\
+                             Connection: close\r\n\r\n{}",
+                            status, body.len(), body);
+        try!(stream.write_all(reply.as_bytes()));
+        Ok(token)
+    }
+}
+
+// Reads a stream until a deadline, waiting at most 5 seconds for
+// each read
+struct DeadlineReader<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant
+}
+
+impl<'a> Read for DeadlineReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let now = Instant::now();
+        if now >= self.deadline {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "token callback deadline passed"))
+        }
+        let left = self.deadline.duration_since(now);
+        let wait = if left < Duration::from_secs(5) { left } else { Duration::from_secs(5) };
+        try!(self.stream.set_read_timeout(Some(wait)));
+        self.stream.read(buf)
+    }
+}
+
+/// The token of a `GET /<state>?token=<token> HTTP/1.1` request line
+pub fn parse_callback(request_line: &str, state: &str) -> Option<String> {
+    let mut parts = request_line.split_whitespace();
+    if parts.next() != Some("GET") {
+        return None
+    }
+    let target = match parts.next() {
+        Some(t) => t,
+        None => return None
+    };
+    let (path, query) = match target.find('?') {
+        Some(i) => (&target[..i], &target[i + 1..]),
+        None => return None
+    };
+    if path != format!("/{}", state) {
+        return None
+    }
+    query.split('&')
+        .filter_map(|pair| {
+            let mut kv = pair.splitn(2, '=');
+            match (kv.next(), kv.next()) {
+                (Some("token"), Some(v)) if !v.is_empty() => percent_decode(v),
+                _ => None
+            }
+        })
+        .next()
+}
+
+/// Escape `s` to be used as a URL query value
+pub fn percent_encode(s: &str) -> String {
+    let mut encoded = String::new();
+    for b in s.bytes() {
+        match b {
+            b'A' ... b'Z' | b'a' ... b'z' | b'0' ... b'9' |
+            b'-' | b'.' | b'_' | b'~' => encoded.push(b as char),
+            _ => encoded.push_str(&format!("%{:02X}", b))
+        }
+    }
+    encoded
+}
+
+// A `+` is kept as is: tokens are base64 and never hold spaces
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if i + 3 > bytes.len() {
+                return None
+            }
+            let hex = match str::from_utf8(&bytes[i + 1..i + 3]) {
+                Ok(h) => h,
+                Err(_) => return None
+            };
+            match u8::from_str_radix(hex, 16) {
+                Ok(b) => decoded.push(b),
+                Err(_) => return None
+            }
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::prelude::*;
+    use std::net::TcpStream;
+    use std::thread;
+    use std::time::Duration;
 
     #[test]
     fn lookup_response_parse_test() {
@@ -78,4 +261,86 @@ mod tests {
         let saml = LookupResponse::parse_saml_enabled(response).unwrap();
         assert_eq!(false, saml);
     }
+
+    #[test]
+    fn parse_callback_test() {
+        let token = parse_callback("GET /cafe?token=xOsq%2B0U%2FII%3D HTTP/1.1\r\n", "cafe");
+        assert_eq!(Some("xOsq+0U/II=".to_string()), token);
+        let token = parse_callback("GET /cafe?other=1&token=abc+1 HTTP/1.1", "cafe");
+        assert_eq!(Some("abc+1".to_string()), token);
+
+        assert_eq!(None, parse_callback("GET /beef?token=abc HTTP/1.1", "cafe"));
+        assert_eq!(None, parse_callback("GET /cafe HTTP/1.1", "cafe"));
+        assert_eq!(None, parse_callback("GET /cafe?token= HTTP/1.1", "cafe"));
+        assert_eq!(None, parse_callback("GET /cafe?token=%2 HTTP/1.1", "cafe"));
+        assert_eq!(None, parse_callback("POST /cafe?token=abc HTTP/1.1", "cafe"));
+    }
+
+    #[test]
+    fn percent_encode_test() {
+        assert_eq!("http%3A%2F%2F127.0.0.1%3A4242%2Fcafe",
+                   percent_encode("http://127.0.0.1:4242/cafe"));
+    }
+
+    // Plays the browser: GET `url` and return the answer
+    fn browse(url: &str) -> String {
+        let rest = url.trim_left_matches("http://");
+        let slash = rest.find('/').unwrap();
+        let mut stream = TcpStream::connect(&rest[..slash]).unwrap();
+        let request = format!("GET {} HTTP/1.1\r\nHost: {}\r\n\r\n",
+                              &rest[slash..], &rest[..slash]);
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut answer = String::new();
+        stream.read_to_string(&mut answer).unwrap();
+        answer
+    }
+
+    #[test]
+    fn token_callback_test() {
+        let callback = TokenCallback::bind().unwrap();
+        let redirect = callback.redirect_url().unwrap();
+        assert!(redirect.starts_with("http://127.0.0.1:"));
+        let browser = thread::spawn(move || {
+            let base = &redirect[..redirect.rfind('/').unwrap()];
+            let rejected = browse(&format!("{}/beef?token=beefbeef", base));
+            let accepted = browse(&format!("{}?token=cafe%2Bcafe", redirect));
+            (rejected, accepted)
+        });
+        let token = callback.wait_for_token(Duration::from_secs(10)).unwrap();
+        assert_eq!(Some("cafe+cafe".to_string()), token);
+        let (rejected, accepted) = browser.join().unwrap();
+        assert!(rejected.starts_with("HTTP/1.1 400"));
+        assert!(accepted.starts_with("HTTP/1.1 200"));
+    }
+
+    #[test]
+    fn token_callback_times_out_test() {
+        let callback = TokenCallback::bind().unwrap();
+        let token = callback.wait_for_token(Duration::from_millis(200)).unwrap();
+        assert_eq!(None, token);
+    }
+
+    #[test]
+    fn token_callback_gives_up_on_slow_clients_test() {
+        use std::time::Instant;
+        let callback = TokenCallback::bind().unwrap();
+        let redirect = callback.redirect_url().unwrap();
+        let rest = redirect.trim_left_matches("http://").to_string();
+        // Sends a header byte every 100ms, for far longer than the timeout
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(&rest[..rest.find('/').unwrap()]).unwrap();
+            stream.write_all(b"GET / HTTP/1.1\r\nX-Slow: ").unwrap();
+            for _ in 0..50 {
+                thread::sleep(Duration::from_millis(100));
+                if stream.write_all(b"z").is_err() {
+                    break
+                }
+            }
+        });
+        let started = Instant::now();
+        let token = callback.wait_for_token(Duration::from_millis(500)).unwrap();
+        assert_eq!(None, token);
+        assert!(started.elapsed() < Duration::from_secs(3), "waited {:?}", started.elapsed());
+        client.join().unwrap();
+    }
 }
@@ -0,0 +1,155 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Connect and read timeouts of the API client.
+
+use config::Config;
+use hyper;
+use hyper::net::{NetworkConnector, HttpStream};
+use std::io;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+pub const DEFAULT_READ_TIMEOUT_SECS: u64 = 60;
+
+/// The timeouts of the connections to the server; `None` waits for
+/// as long as it takes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timeouts {
+    pub connect: Option<Duration>,
+    pub read: Option<Duration>
+}
+
+impl Default for Timeouts {
+    fn default() -> Timeouts {
+        Timeouts {
+            connect: Some(Duration::from_secs(DEFAULT_CONNECT_TIMEOUT_SECS)),
+            read: Some(Duration::from_secs(DEFAULT_READ_TIMEOUT_SECS))
+        }
+    }
+}
+
+impl Timeouts {
+    /// Read the `connect_timeout` and `read_timeout` keys, in seconds.
+    /// A timeout of 0 disables it.
+    pub fn from_config(config: &Config) -> Timeouts {
+        Timeouts {
+            connect: seconds(config.connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS)),
+            read: seconds(config.read_timeout.unwrap_or(DEFAULT_READ_TIMEOUT_SECS))
+        }
+    }
+}
+
+fn seconds(secs: u64) -> Option<Duration> {
+    if secs == 0 {
+        None
+    } else {
+        Some(Duration::from_secs(secs))
+    }
+}
+
+/// A plain TCP connector giving up on servers that don't accept the
+/// connection within `timeout`.
+#[derive(Debug, Clone, Copy)]
+pub struct TimeoutConnector {
+    pub timeout: Option<Duration>
+}
+
+impl NetworkConnector for TimeoutConnector {
+    type Stream = HttpStream;
+
+    fn connect(&self, host: &str, port: u16, _scheme: &str) -> hyper::Result<HttpStream> {
+        let mut last_error = None;
+        for addr in try!((host, port).to_socket_addrs()) {
+            match connect_within(addr, self.timeout) {
+                Ok(stream) => return Ok(HttpStream(stream)),
+                Err(e) => {
+                    debug!("Unable to connect to {} ({}): {}", host, addr, e);
+                    last_error = Some(e)
+                }
+            }
+        }
+        let e = last_error.unwrap_or(io::Error::new(io::ErrorKind::NotFound,
+                                                    format!("no address found for {}", host)));
+        Err(hyper::Error::Io(e))
+    }
+}
+
+// Connect to `addr` on a thread of its own, so that the connection can
+// be given up on after `timeout`. The thread is left to finish the
+// attempt on its own then.
+fn connect_within(addr: SocketAddr, timeout: Option<Duration>) -> io::Result<TcpStream> {
+    let timeout = match timeout {
+        Some(t) => t,
+        None => return TcpStream::connect(addr)
+    };
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(TcpStream::connect(addr));
+    });
+    match rx.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => {
+            Err(io::Error::new(io::ErrorKind::TimedOut,
+                               format!("no connection within {}s", timeout.as_secs())))
+        },
+        Err(RecvTimeoutError::Disconnected) => {
+            Err(io::Error::new(io::ErrorKind::Other, "the connection attempt was lost"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::connect_within;
+    use config::Config;
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    #[test]
+    fn from_config_test() {
+        assert_eq!(Timeouts::default(), Timeouts::from_config(&Config::default()));
+        let mut config = Config::default();
+        config.connect_timeout = Some(3);
+        config.read_timeout = Some(300);
+        let timeouts = Timeouts::from_config(&config);
+        assert_eq!(Some(Duration::from_secs(3)), timeouts.connect);
+        assert_eq!(Some(Duration::from_secs(300)), timeouts.read);
+    }
+
+    #[test]
+    fn zero_disables_the_timeout_test() {
+        let mut config = Config::default();
+        config.connect_timeout = Some(0);
+        config.read_timeout = Some(0);
+        let timeouts = Timeouts::from_config(&config);
+        assert_eq!(None, timeouts.connect);
+        assert_eq!(None, timeouts.read);
+    }
+
+    #[test]
+    fn connect_within_test() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert!(connect_within(addr, Some(Duration::from_secs(5))).is_ok());
+        assert!(connect_within(addr, None).is_ok());
+    }
+}
@@ -0,0 +1,279 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! TLS settings of the API client: the CA bundle trusted on top of the
+//! system store, the client certificate for mutual TLS, and the
+//! insecure mode that skips certificate verification altogether.
+
+use config::Config;
+use errors::{DeliveryError, Kind};
+use hyper;
+use hyper::net::{HttpStream, SslClient};
+use openssl::nid::Nid;
+use openssl::ssl::{self, SslContext, SslMethod, SslStream, SSL_VERIFY_PEER, SSL_VERIFY_NONE};
+use openssl::x509::{X509, X509FileType, X509StoreContext};
+use std::cell::Cell;
+use std::net::IpAddr;
+use std::sync::Arc;
+use types::DeliveryResult;
+use utils::path_ext::is_file;
+use utils::say::sayln;
+
+thread_local! {
+    // The insecure warning is printed once, not for every client
+    static INSECURE_WARNED: Cell<bool> = Cell::new(false)
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TlsSettings {
+    pub ca_bundle: Option<String>,
+    pub client_cert: Option<String>,
+    pub client_key: Option<String>,
+    pub insecure: bool
+}
+
+impl TlsSettings {
+    /// Read the TLS settings from `config`, making sure that the
+    /// files exist and that a client certificate comes with its key.
+    pub fn from_config(config: &Config) -> DeliveryResult<TlsSettings> {
+        let settings = TlsSettings {
+            ca_bundle: config.ca_bundle.clone(),
+            client_cert: config.client_cert.clone(),
+            client_key: config.client_key.clone(),
+            insecure: config.insecure.unwrap_or(false)
+        };
+        try!(settings.validate());
+        Ok(settings)
+    }
+
+    fn validate(&self) -> DeliveryResult<()> {
+        if self.client_cert.is_some() != self.client_key.is_some() {
+            let msg = "client_cert and client_key must be set together".to_string();
+            return Err(DeliveryError{ kind: Kind::TlsSetup, detail: Some(msg) })
+        }
+        for file in [&self.ca_bundle, &self.client_cert, &self.client_key].iter() {
+            if let Some(ref f) = **file {
+                if !is_file(f) {
+                    return Err(DeliveryError{ kind: Kind::TlsSetup,
+                                              detail: Some(format!("{} is not a file", f)) })
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the OpenSSL client of the https connections. Peers are
+    /// verified against the system store and the CA bundle, and must
+    /// hold a certificate issued for the host, unless running in
+    /// insecure mode.
+    pub fn ssl(&self) -> DeliveryResult<VerifyingSsl> {
+        let mut ctx = try!(SslContext::new(SslMethod::Sslv23));
+        try!(ctx.set_cipher_list("DEFAULT"));
+        if self.insecure {
+            warn_insecure();
+            ctx.set_verify(SSL_VERIFY_NONE, None);
+        } else {
+            try!(ctx.set_default_verify_paths());
+            if let Some(ref ca_bundle) = self.ca_bundle {
+                try!(ctx.set_CA_file(ca_bundle));
+            }
+            ctx.set_verify(SSL_VERIFY_PEER, None);
+        }
+        if let (&Some(ref cert), &Some(ref key)) = (&self.client_cert, &self.client_key) {
+            try!(ctx.set_certificate_file(cert, X509FileType::PEM));
+            try!(ctx.set_private_key_file(key, X509FileType::PEM));
+            try!(ctx.check_private_key());
+        }
+        Ok(VerifyingSsl { context: Arc::new(ctx), verify_host: !self.insecure })
+    }
+}
+
+/// Wraps the connections in OpenSSL like hyper's `Openssl`, and also
+/// checks that the certificate of the server names the host connected
+/// to, which hyper leaves to the caller.
+#[derive(Debug, Clone)]
+pub struct VerifyingSsl {
+    context: Arc<SslContext>,
+    verify_host: bool
+}
+
+impl SslClient for VerifyingSsl {
+    type Stream = SslStream<HttpStream>;
+
+    fn wrap_client(&self, stream: HttpStream, host: &str) -> hyper::Result<Self::Stream> {
+        let mut ssl = try!(ssl::Ssl::new(&self.context));
+        try!(ssl.set_hostname(host));
+        if self.verify_host {
+            let host = host.to_string();
+            ssl.set_verify_callback(SSL_VERIFY_PEER, move |preverified, store| {
+                verify_host(preverified, store, &host)
+            });
+        }
+        SslStream::connect(ssl, stream).map_err(From::from)
+    }
+}
+
+// Only the certificate of the server itself, at the bottom of the
+// chain, has to name the host; the chain was verified by OpenSSL.
+fn verify_host(preverified: bool, store: &X509StoreContext, host: &str) -> bool {
+    if !preverified || store.error_depth() != 0 {
+        return preverified
+    }
+    match store.get_current_cert() {
+        Some(cert) => {
+            let matches = host_matches(&cert, host);
+            if !matches {
+                debug!("The certificate of the server was not issued for {}", host);
+            }
+            matches
+        },
+        None => false
+    }
+}
+
+/// Return true if the certificate was issued for `host`: one of its
+/// subject alternative names matches it or, when it has none, its
+/// common name does. A wildcard only stands for the leftmost label.
+pub fn host_matches(cert: &X509, host: &str) -> bool {
+    let host = host.trim_right_matches('.').to_lowercase();
+    let ip: Option<IpAddr> = host.parse().ok();
+    if let Some(names) = cert.subject_alt_names() {
+        return names.iter().any(|name| {
+            match (ip, name.dnsname(), name.ipaddress()) {
+                (None, Some(dns), _) => name_matches(dns, &host),
+                (Some(IpAddr::V4(ip)), _, Some(addr)) => &ip.octets()[..] == addr,
+                (Some(IpAddr::V6(ip)), _, Some(addr)) => &ip.octets()[..] == addr,
+                _ => false
+            }
+        })
+    }
+    match cert.subject_name().text_by_nid(Nid::CN) {
+        Some(cn) => ip.is_none() && name_matches(&cn, &host),
+        None => false
+    }
+}
+
+fn name_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim_right_matches('.').to_lowercase();
+    if pattern.starts_with("*.") {
+        match host.find('.') {
+            Some(dot) => dot > 0 && host[dot..] == pattern[1..],
+            None => false
+        }
+    } else {
+        pattern == host
+    }
+}
+
+fn warn_insecure() {
+    INSECURE_WARNED.with(|warned| {
+        if !warned.get() {
+            sayln("red", "WARNING: TLS certificate verification is disabled (insecure \
+                          mode); the identity of the Delivery server is NOT checked!");
+            warned.set(true);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TlsSettings, host_matches};
+    use config::Config;
+    use errors::Kind;
+    use openssl::crypto::pkey::PKey;
+    use openssl::x509::{X509, X509Generator};
+    use openssl::x509::extension::Extension::SubjectAltName;
+    use openssl::x509::extension::AltNameOption;
+    use std::fs::File;
+    use tempdir::TempDir;
+
+    fn certificate(cn: &str, alt_names: &[(AltNameOption, &str)]) -> (X509<'static>, PKey) {
+        let mut gen = X509Generator::new()
+            .set_bitlength(1024)
+            .set_valid_period(1)
+            .add_name("CN".to_string(), cn.to_string());
+        if !alt_names.is_empty() {
+            let names = alt_names.iter().map(|&(o, n)| (o, n.to_string())).collect();
+            gen = gen.add_extension(SubjectAltName(names));
+        }
+        gen.generate().unwrap()
+    }
+
+    #[test]
+    fn from_config_defaults_to_verifying_test() {
+        let settings = TlsSettings::from_config(&Config::default()).unwrap();
+        assert_eq!(TlsSettings::default(), settings);
+        assert!(settings.ssl().is_ok());
+    }
+
+    #[test]
+    fn from_config_needs_cert_and_key_test() {
+        let tempdir = TempDir::new("tls").unwrap();
+        let cert_path = tempdir.path().join("client.pem");
+        let key_path = tempdir.path().join("client.key");
+        let (cert, key) = certificate("builder", &[]);
+        cert.write_pem(&mut File::create(&cert_path).unwrap()).unwrap();
+        key.write_pem(&mut File::create(&key_path).unwrap()).unwrap();
+
+        let mut config = Config::default();
+        config.client_cert = Some(cert_path.to_str().unwrap().to_string());
+        match TlsSettings::from_config(&config) {
+            Err(e) => match e.kind {
+                Kind::TlsSetup => {},
+                _ => panic!("expected a TlsSetup error, got {:?}", e)
+            },
+            Ok(_) => panic!("a client certificate without a key is invalid")
+        }
+
+        config.client_key = Some(key_path.to_str().unwrap().to_string());
+        let settings = TlsSettings::from_config(&config).unwrap();
+        assert!(settings.ssl().is_ok());
+    }
+
+    #[test]
+    fn host_matches_test() {
+        let (cert, _) = certificate("delivery.example.com",
+                                    &[(AltNameOption::DNS, "delivery.example.com"),
+                                      (AltNameOption::DNS, "*.delivery.example.com"),
+                                      (AltNameOption::IPAddress, "10.0.0.5")]);
+        assert!(host_matches(&cert, "delivery.example.com"));
+        assert!(host_matches(&cert, "Delivery.Example.com."));
+        assert!(host_matches(&cert, "eu.delivery.example.com"));
+        assert!(host_matches(&cert, "10.0.0.5"));
+        assert!(!host_matches(&cert, "a.eu.delivery.example.com"));
+        assert!(!host_matches(&cert, "10.0.0.6"));
+
+        let (cn_only, _) = certificate("delivery.example.com", &[]);
+        assert!(host_matches(&cn_only, "delivery.example.com"));
+    }
+
+    #[test]
+    fn host_matches_rejects_another_name_test() {
+        let (cert, _) = certificate("delivery.example.com",
+                                    &[(AltNameOption::DNS, "other.example.com")]);
+        // The alternative names take precedence over the common name
+        assert!(!host_matches(&cert, "delivery.example.com"));
+        assert!(!host_matches(&cert, "example.com"));
+        assert!(host_matches(&cert, "other.example.com"));
+    }
+
+    #[test]
+    fn from_config_missing_ca_bundle_test() {
+        let config = Config::default().set_ca_bundle("/no/such/ca.pem");
+        assert!(TlsSettings::from_config(&config).is_err());
+    }
+}
@@ -39,14 +39,25 @@ impl TokenRequest {
 }
 
 #[derive(RustcEncodable, RustcDecodable, Debug, Clone)]
-pub struct TokenResponse {
+pub struct RevokeRequest {
     token: String
 }
 
+#[derive(RustcEncodable, RustcDecodable, Debug, Clone)]
+pub struct TokenResponse {
+    pub token: String,
+    // How many seconds the token is valid for, if the server says
+    pub ttl: Option<u64>
+}
+
 impl TokenResponse {
-    pub fn parse_token(response: &str) -> Result<String, DeliveryError> {
+    pub fn parse(response: &str) -> Result<TokenResponse, DeliveryError> {
         let tresponse: TokenResponse = try!(json::decode(response));
-        Ok(tresponse.token)
+        Ok(tresponse)
+    }
+
+    pub fn parse_token(response: &str) -> Result<String, DeliveryError> {
+        TokenResponse::parse(response).map(|r| r.token)
     }
 
     pub fn parse_token_expired(content: &str) -> bool {
@@ -66,7 +77,7 @@ pub fn verify(config: &Config) -> Result<bool, DeliveryError> {
     let api_server = try!(config.api_host_and_port());
     let ent = try!(config.enterprise());
     let user = try!(config.user());
-    let tstore = try!(TokenStore::from_home());
+    let tstore = try!(TokenStore::from_config(config));
     let auth = try!(APIAuth::from_token_store(tstore, &api_server, &ent, &user).or_else(|e| {
         debug!("Ignoring {:?}\nRequesting token from config", e);
         APIAuth::from_token_request(&config)
@@ -92,7 +103,7 @@ pub fn verify(config: &Config) -> Result<bool, DeliveryError> {
 }
 
 /// Request an API token for a user from a Delivery server.
-pub fn request(config: &Config, pass: &str) -> Result<String, DeliveryError> {
+pub fn request(config: &Config, pass: &str) -> Result<TokenResponse, DeliveryError> {
     let client = try!(APIClient::from_config_no_auth(config));
     let user = try!(config.user());
     let payload = try!(TokenRequest::payload(&user, pass));
@@ -102,8 +113,7 @@ pub fn request(config: &Config, pass: &str) -> Result<String, DeliveryError> {
         StatusCode::Ok => {
             let mut body_string = String::new();
             try!(result.read_to_string(&mut body_string));
-            let token = try!(TokenResponse::parse_token(&body_string));
-            Ok(token)
+            TokenResponse::parse(&body_string)
         },
         StatusCode::Unauthorized => {
             let ent = try!(config.enterprise());
@@ -122,9 +132,37 @@ pub fn request(config: &Config, pass: &str) -> Result<String, DeliveryError> {
     }
 }
 
+/// Revoke the API token of `user` on the Delivery server of the
+/// config. Returns false if the server no longer accepted the token.
+pub fn revoke(config: &Config, user: &str, token: &str) -> Result<bool, DeliveryError> {
+    let mut client = try!(APIClient::from_config_no_auth(config));
+    client.set_auth(APIAuth{ user: String::from(user), token: String::from(token) });
+    let payload = try!(json::encode(&RevokeRequest{ token: String::from(token) }));
+    let path = format!("users/{}/revoke-token", user);
+    let mut result = try!(client.post(&path, &payload));
+    match result.status {
+        StatusCode::Ok | StatusCode::NoContent => Ok(true),
+        StatusCode::Unauthorized => {
+            let content = try!(APIClient::extract_pretty_json(&mut result));
+            debug!("Token revoke rejected: {}", content);
+            Ok(false)
+        },
+        error_code @ _ => {
+            let msg = format!("token revoke returned {}", error_code);
+            Err(DeliveryError{ kind: Kind::AuthenticationFailed,
+                               detail: Some(msg)})
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use config::Config;
+    use errors::{DeliveryError, Kind};
+    use http::cassette::{Cassette, Interaction, Player};
+    use http::transport;
+    use std::sync::Arc;
 
     #[test]
     fn token_request_payload_test() {
@@ -140,6 +178,16 @@ mod tests {
         assert_eq!("abc123", token);
     }
 
+    #[test]
+    fn token_response_parse_ttl_test() {
+        let response = TokenResponse::parse("{\"token\":\"abc123\",\"ttl\":604800}").unwrap();
+        assert_eq!("abc123", response.token);
+        assert_eq!(Some(604800), response.ttl);
+
+        let response = TokenResponse::parse("{\"token\":\"abc123\"}").unwrap();
+        assert_eq!(None, response.ttl);
+    }
+
     #[test]
     fn token_response_parse_token_expired_test() {
         let r_token_expired = "{\"error\":\"token_expired\"}";
@@ -151,4 +199,43 @@ mod tests {
         let r_other = "{\"orgs\":\"[]\"}";
         assert!(!TokenResponse::parse_token_expired(r_other))
     }
+
+    #[test]
+    fn request_replayed_from_a_cassette() {
+        let player = Player::new(Cassette { interactions: vec![
+            Interaction::new("POST", "/api/v0/e/acme/users/kirk/get-token", 200,
+                             "{\"token\":\"cafecafe\"}"),
+            Interaction::new("POST", "/api/v0/e/acme/users/spock/get-token", 401,
+                             "{\"error\":\"token_denied\"}")
+        ]});
+        transport::set_transport(Some(Arc::new(player)));
+        let config = Config::default()
+            .set_server("earth")
+            .set_enterprise("acme")
+            .set_user("kirk");
+        assert_eq!("cafecafe", request(&config, "sesame").unwrap().token);
+
+        let config = config.set_user("spock");
+        match request(&config, "sesame") {
+            Err(DeliveryError{ kind: Kind::AuthenticationFailed, .. }) => (),
+            other => panic!("expected AuthenticationFailed, got {:?}", other)
+        }
+        transport::set_transport(None);
+    }
+
+    #[test]
+    fn revoke_replayed_from_a_cassette() {
+        let player = Player::new(Cassette { interactions: vec![
+            Interaction::new("POST", "/api/v0/e/acme/users/kirk/revoke-token", 204, ""),
+            Interaction::new("POST", "/api/v0/e/acme/users/spock/revoke-token", 401,
+                             "{\"error\":\"token_expired\"}")
+        ]});
+        transport::set_transport(Some(Arc::new(player)));
+        let config = Config::default()
+            .set_server("earth")
+            .set_enterprise("acme");
+        assert_eq!(true, revoke(&config, "kirk", "cafecafe").unwrap());
+        assert_eq!(false, revoke(&config, "spock", "beefbeef").unwrap());
+        transport::set_transport(None);
+    }
 }
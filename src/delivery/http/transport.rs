@@ -0,0 +1,104 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! The transport sends the requests of `APIClient` and returns the
+//! responses. Over the network, that is a hyper client; in tests, and
+//! when `DELIVERY_CASSETTE_REPLAY` is set, a `cassette::Player` answers
+//! with recorded responses instead. With `DELIVERY_CASSETTE_RECORD`
+//! set, the exchanges with the server are added to that file.
+
+use http::cassette::{Player, Recorder};
+use hyper;
+use hyper::client::response::Response as HyperResponse;
+use hyper::error::Error as HttpError;
+use hyper::header::Headers;
+use hyper::method::Method;
+use std::cell::RefCell;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use types::DeliveryResult;
+use utils::env_variable;
+
+/// A request of `APIClient`, ready to be sent
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: Method,
+    pub url: String,
+    pub headers: Headers,
+    pub body: String
+}
+
+pub trait Transport: fmt::Debug + Send + Sync {
+    fn send(&self, request: &Request) -> Result<HyperResponse, HttpError>;
+}
+
+/// Send requests over the network with a hyper client
+#[derive(Debug)]
+pub struct HyperTransport {
+    client: Arc<hyper::Client>
+}
+
+impl HyperTransport {
+    pub fn new(client: Arc<hyper::Client>) -> HyperTransport {
+        HyperTransport { client: client }
+    }
+}
+
+impl Transport for HyperTransport {
+    fn send(&self, request: &Request) -> Result<HyperResponse, HttpError> {
+        let req = self.client.request(request.method.clone(), &request.url[..])
+            .headers(request.headers.clone());
+        if request.body.is_empty() {
+            req.send()
+        } else {
+            req.body(&request.body[..]).send()
+        }
+    }
+}
+
+thread_local! {
+    static OVERRIDE: RefCell<Option<Arc<Transport>>> = RefCell::new(None)
+}
+
+/// Make every `APIClient` created afterwards on this thread use
+/// `transport`, or go back to the network with `None`. Tests use it to
+/// run commands against a `cassette::Player`.
+pub fn set_transport(transport: Option<Arc<Transport>>) {
+    OVERRIDE.with(|o| *o.borrow_mut() = transport);
+}
+
+/// The transport of a new `APIClient` whose requests go through
+/// `client` over the network. The cassette set in the environment,
+/// if any, is loaded once and shared by all the clients.
+pub fn for_client(client: Arc<hyper::Client>) -> DeliveryResult<Arc<Transport>> {
+    if let Some(transport) = OVERRIDE.with(|o| o.borrow().clone()) {
+        return Ok(transport)
+    }
+    let transport: Arc<Transport> = if let Some(path) = env_variable("DELIVERY_CASSETTE_REPLAY") {
+        debug!("Replaying API responses from {}", path);
+        Arc::new(try!(Player::load(&PathBuf::from(path))))
+    } else if let Some(path) = env_variable("DELIVERY_CASSETTE_RECORD") {
+        debug!("Recording API responses to {}", path);
+        let network = Arc::new(HyperTransport::new(client));
+        Arc::new(try!(Recorder::new(&PathBuf::from(path), network)))
+    } else {
+        return Ok(Arc::new(HyperTransport::new(client)))
+    };
+    set_transport(Some(transport.clone()));
+    Ok(transport)
+}
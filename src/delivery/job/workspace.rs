@@ -25,9 +25,11 @@ use job;
 use std::process::{Command, Stdio};
 use std::path::{Path, PathBuf};
 use std::fs::File;
+use std::io;
 use std::io::prelude::*;
 use utils;
 use utils::path_to_string;
+use utils::say;
 use utils::path_join_many::PathJoinMany;
 use utils::path_ext::{is_file, is_dir};
 use std::error;
@@ -414,20 +416,31 @@ impl Workspace {
             .arg(&path_to_string(&self.chef.join("config.rb")))
             .arg("-r")
             .arg(run_list)
-            .stdout(Stdio::inherit())
             .stderr(Stdio::inherit())
             .current_dir(&self.repo);
+        // stdout is reserved for the result object in json mode, so the
+        // chef-client output is relayed to stderr as it arrives instead.
+        let json_output = say::is_json_output();
+        if json_output {
+            command.stdout(Stdio::piped());
+        } else {
+            command.stdout(Stdio::inherit());
+        }
         match phase_arg {
             "default" => command.env("DELIVERY_BUILD_SETUP", "TRUE"),
             _ => command.env("DELIVERY_BUILD_SETUP", "FALSE")
         };
         debug!("Job Command: {:?}", command);
-        let output = match command.output() {
-            Ok(o) => o,
+        let mut child = match command.spawn() {
+            Ok(c) => c,
             Err(e) => { return Err(DeliveryError{ kind: Kind::FailedToExecute, detail: Some(format!("failed to execute chef-client: {}", error::Error::description(&e)))}) },
         };
-        if !output.status.success() {
-            return Err(DeliveryError{ kind: Kind::ChefFailed, detail: Some(format!("STDOUT: {}\nSTDERR: {}\n", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr)))});
+        if let Some(ref mut stdout) = child.stdout {
+            try!(io::copy(stdout, &mut io::stderr()));
+        }
+        let status = try!(child.wait());
+        if !status.success() {
+            return Err(DeliveryError{ kind: Kind::ChefFailed, detail: Some(format!("chef-client exited with {}", status))});
         }
         Ok(())
     }
@@ -27,9 +27,11 @@ extern crate libc;
 extern crate tempdir;
 extern crate uuid;
 #[macro_use] extern crate hyper;
+extern crate openssl;
 extern crate mime;
 extern crate clap;
 extern crate crypto;
+extern crate rand;
 
 #[macro_export]
 macro_rules! validate {
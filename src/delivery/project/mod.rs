@@ -20,7 +20,8 @@ use utils::path_ext::is_dir;
 use errors::{DeliveryError, Kind};
 use types::DeliveryResult;
 use std::path::{Path, PathBuf};
-use http::APIClient;
+use http::{self, APIClient};
+use config::Config;
 use git::{self, ReviewResult};
 use std::process::{Output, Command};
 use std::fs;
@@ -122,7 +123,7 @@ impl SourceCodeProvider {
 // Returns true if created, returns false if already exists.
 pub fn create_delivery_pipeline(client: &APIClient, org: &str,
                                 proj: &str, pipe: &str) -> DeliveryResult<bool> {
-    if client.pipeline_exists(org, proj, pipe) {
+    if try!(client.pipeline_exists(org, proj, pipe)) {
         return Ok(false)
     } else {
         try!(client.create_pipeline(org, proj, pipe, Some(pipe)));
@@ -135,7 +136,7 @@ pub fn create_delivery_pipeline(client: &APIClient, org: &str,
 // If the project already exists, return false
 pub fn create_delivery_project(client: &APIClient, org: &str,
                                proj: &str) -> DeliveryResult<bool> {
-    if client.project_exists(org, proj) {
+    if try!(client.project_exists(org, proj)) {
         return Ok(false)
     } else {
         try!(client.create_delivery_project(org, proj));
@@ -145,19 +146,20 @@ pub fn create_delivery_project(client: &APIClient, org: &str,
 
 // Push local content to the Delivery Server if no upstream commits.
 // Returns true if commits pushed, returns false if upstream commits found.
-pub fn push_project_content_to_delivery(pipeline: &str) -> DeliveryResult<bool> {
-    if try!(git::server_content(pipeline)) {
+pub fn push_project_content_to_delivery(project_path: &Path,
+                                        pipeline: &str) -> DeliveryResult<bool> {
+    if try!(git::server_content(project_path, pipeline)) {
         Ok(false)
     } else {
-        try!(git::git_push(pipeline));
+        try!(git::git_push(project_path, pipeline));
         Ok(true)
     }
 }
 
 // Create delivery remote if it doesn't exist. Returns true if created.
-pub fn create_delivery_remote_if_missing(
+pub fn create_delivery_remote_if_missing(project_path: &PathBuf,
       delivery_git_ssh_url: &str) -> DeliveryResult<bool> {
-    if try!(git::config_repo(delivery_git_ssh_url, &project_path())) {
+    if try!(git::config_repo(delivery_git_ssh_url, project_path)) {
         return Ok(true)
     } else {
         return Ok(false)
@@ -165,8 +167,8 @@ pub fn create_delivery_remote_if_missing(
 }
 
 // Check to see if the origin remote is set up.
-pub fn missing_github_remote() -> DeliveryResult<bool> {
-    let git_remote_result = git::git_command(&["remote"], &project_path());
+pub fn missing_github_remote(project_path: &Path) -> DeliveryResult<bool> {
+    let git_remote_result = git::git_command(&["remote"], project_path);
     match git_remote_result {
         Ok(git_result) => Ok(!git_result.stdout.contains("origin")),
         Err(e) => return Err(e)
@@ -213,22 +215,22 @@ pub fn root_dir(dir: &Path) -> DeliveryResult<PathBuf> {
     }
 }
 
-pub fn project_path() -> PathBuf {
-    root_dir(&utils::cwd()).unwrap()
+// Return the project name from the repository `dir` belongs to
+pub fn project_from_dir(dir: &Path) -> DeliveryResult<String> {
+    let root = try!(self::root_dir(dir));
+    Ok(root.file_name().unwrap().to_str().unwrap().to_string())
 }
 
-// Return the project name from the current path
-pub fn project_from_cwd() -> DeliveryResult<String> {
-    let cwd = try!(self::root_dir(&utils::cwd()));
-    Ok(cwd.file_name().unwrap().to_str().unwrap().to_string())
-}
-
-// Return the project name or try to extract it from the current path
-pub fn project_or_from_cwd(proj: &str) -> DeliveryResult<String> {
-    if proj.is_empty() {
-        project_from_cwd()
+// Return the provided change id or look up the latest open change
+// submitted for review from the branch checked out in `dir`
+pub fn change_id_or_from_head(dir: &Path, config: &Config,
+                              change: &str) -> DeliveryResult<String> {
+    if change.is_empty() {
+        let pipeline = try!(config.pipeline());
+        let head = try!(git::get_head(dir));
+        http::change::latest_id_for_topic(config, &pipeline, &head)
     } else {
-        Ok(proj.to_string())
+        Ok(change.to_string())
     }
 }
 
@@ -262,10 +264,11 @@ pub fn create_feature_branch_if_missing(project_path: &PathBuf, branch_name: &st
 }
 
 // Add and commit the generated build_cookbook
-pub fn add_commit_build_cookbook(custom_config_passed: &bool) -> DeliveryResult<bool> {
+pub fn add_commit_build_cookbook(project_path: &Path,
+                                 custom_config_passed: &bool) -> DeliveryResult<bool> {
     // .delivery is probably not yet under version control, so we have to add
     // the whole folder instead of .delivery/build_cookbook.
-    try!(git::git_command(&["add", ".delivery"], &project_path()));
+    try!(git::git_command(&["add", ".delivery"], project_path));
 
     let mut commit_msg = "Adds Delivery build cookbook".to_string();
     if *custom_config_passed {
@@ -274,19 +277,18 @@ pub fn add_commit_build_cookbook(custom_config_passed: &bool) -> DeliveryResult<
 
     // Commit the changes made in .delivery but detect if nothing has changed,
     // if that is the case, we are Ok() to continue
-    match git::git_commit(&commit_msg) {
+    match git::git_commit(project_path, &commit_msg) {
       Ok(_) => Ok(true),
       Err(DeliveryError{ kind: Kind::EmptyGitCommit, .. }) => Ok(false),
       Err(e) => Err(e)
     }
 }
 
-// Create the delivery readme if it doesn't exist already.
-pub fn create_delivery_readme() -> DeliveryResult<bool> {
-    // NOTE: this isn't guaranteed to be in the project root; however it is only invoked via
-    // `delivery init` which makes some assumptions elsewhere that the CWD is the project root.
-    if !PathBuf::from("DELIVERY.md").exists() {
-        let mut f = try!(File::create("DELIVERY.md"));
+// Create the delivery readme in the project root if it doesn't exist already.
+pub fn create_delivery_readme(project_path: &Path) -> DeliveryResult<bool> {
+    let readme = project_path.join("DELIVERY.md");
+    if !readme.exists() {
+        let mut f = try!(File::create(&readme));
         try!(f.write_all(&DELIVERY_DOT_MD_CONTENT));
         Ok(true)
     } else {
@@ -294,28 +296,28 @@ pub fn create_delivery_readme() -> DeliveryResult<bool> {
     }
 }
 
-pub fn commit_delivery_readme() -> DeliveryResult<()> {
-    try!(git::git_command(&["add", "DELIVERY.md"], &project_path()));
+pub fn commit_delivery_readme(project_path: &Path) -> DeliveryResult<()> {
+    try!(git::git_command(&["add", "DELIVERY.md"], project_path));
     let commit_msg = "New pipeline verification commit".to_string();
-    try!(git::git_command(&["commit", "-m", &commit_msg], &project_path()));
+    try!(git::git_command(&["commit", "-m", &commit_msg], project_path));
     Ok(())
 }
 
-pub fn create_dot_delivery() -> &'static Path {
-    // TODO: should we be doing some relative pathing here?
-    let dot_delivery = Path::new(".delivery");
-    fs::create_dir_all(dot_delivery).unwrap();
-    dot_delivery
+pub fn create_dot_delivery(project_path: &Path) -> DeliveryResult<PathBuf> {
+    let dot_delivery = project_path.join(".delivery");
+    try!(fs::create_dir_all(&dot_delivery));
+    Ok(dot_delivery)
 }
 
-pub fn create_default_build_cookbook(pipeline: &str) -> DeliveryResult<Command> {
+pub fn create_default_build_cookbook(project_path: &Path,
+                                     pipeline: &str) -> DeliveryResult<Command> {
     let mut command = utils::make_command("chef");
     command.arg("generate")
         .arg("build-cookbook")
         .arg(".delivery/build_cookbook")
         .arg("--pipeline")
         .arg(pipeline)
-        .current_dir(&project_path());
+        .current_dir(project_path);
     let output = try!(command.output());
     try!(handle_chef_generate_cookbook_cmd(output));
     Ok(command)
@@ -349,7 +351,7 @@ pub fn download_or_mv_custom_build_cookbook_generator(
         } else {
             let cache_path_str = &cache_generator_path.to_string_lossy();
             let generator_str  = &generator.to_string_lossy();
-            try!(git::clone(&cache_path_str, &generator_str));
+            try!(git::clone(cache_path, &cache_path_str, &generator_str));
             return Ok(CustomCookbookSource::Git)
         }
     }
@@ -393,12 +395,76 @@ fn handle_chef_generate_cookbook_cmd(output: Output) -> DeliveryResult<()> {
     Ok(())
 }
 
-pub fn review(target: &str, head: &str) -> DeliveryResult<ReviewResult> {
+// Submit the branch `head` of the repository in `project_path` for
+// review against the `target` pipeline
+pub fn review(project_path: &Path, target: &str,
+              head: &str) -> DeliveryResult<ReviewResult> {
     if target == head {
         Err(DeliveryError{ kind: Kind::CannotReviewSameBranch, detail: None })
     } else {
-        Ok(try!(git::git_push_review(head, target)))
+        Ok(try!(git::git_push_review(project_path, head, target)))
+    }
+}
+
+/// A branch of a stack along with the change it was submitted as;
+/// `submitted` is false when the latest patchset of the change was
+/// already up to date with the branch. An unchanged branch whose change
+/// is no longer open (e.g. it was merged) has no `change_id`.
+#[derive(Debug, PartialEq)]
+pub struct StackedChange {
+    pub branch: String,
+    pub change_id: Option<String>,
+    pub url: Option<String>,
+    pub submitted: bool
+}
+
+// Submit every branch of the stack ending at `head` as its own change,
+// skipping the branches whose latest patchset is already up to date,
+// then link the changes to each other in their descriptions.
+pub fn review_stack(project_path: &Path, config: &Config, target: &str,
+                    head: &str) -> DeliveryResult<Vec<StackedChange>> {
+    if target == head {
+        return Err(DeliveryError{ kind: Kind::CannotReviewSameBranch, detail: None })
+    }
+    let stack = try!(git::branch_stack(project_path, target, head));
+    let mut changes: Vec<StackedChange> = Vec::new();
+    for branch in stack.iter() {
+        let latest = try!(git::review_patchsets(project_path, &branch.name, target)).pop();
+        let submitted = latest.map(|p| p.sha) != Some(branch.sha.clone());
+        let (change_id, url) = if submitted {
+            let review = try!(git::git_push_review(project_path, &branch.name, target));
+            (review.change_id, review.url)
+        } else {
+            (None, None)
+        };
+        let change_id = match change_id {
+            Some(id) => Some(id),
+            None => match http::change::latest_id_for_topic(config, target, &branch.name) {
+                Ok(id) => Some(id),
+                Err(DeliveryError{ kind: Kind::ChangeNotFound, .. }) => None,
+                Err(e) => return Err(e)
+            }
+        };
+        changes.push(StackedChange{ branch: branch.name.clone(), change_id: change_id,
+                                    url: url, submitted: submitted });
+    }
+    // Only the open changes are linked to each other
+    let open: Vec<(&StackedChange, &str)> = changes.iter()
+        .filter_map(|c| c.change_id.as_ref().map(|id| (c, &id[..])))
+        .collect();
+    for (i, &(_, change_id)) in open.iter().enumerate() {
+        let parent = if i > 0 { Some(stack_link(open[i - 1])) } else { None };
+        let child = open.get(i + 1).map(|&link| stack_link(link));
+        let mut description = try!(http::change::get(config, change_id));
+        description.set_stack(parent.as_ref().map(|p| &p[..]),
+                              child.as_ref().map(|c| &c[..]));
+        try!(http::change::set(config, change_id, &description));
     }
+    Ok(changes)
+}
+
+fn stack_link(link: (&StackedChange, &str)) -> String {
+    format!("{} (change {})", link.0.branch, link.1)
 }
 
 pub fn handle_review_result(review: &ReviewResult,
@@ -417,7 +483,8 @@ pub fn handle_review_result(review: &ReviewResult,
 #[cfg(test)]
 mod tests {
     use std::path::Path;
-    use super::root_dir;
+    use super::{root_dir, create_delivery_readme, create_dot_delivery};
+    use tempdir::TempDir;
 
     #[test]
     fn detect_error_if_root_project_is_not_a_git_repo() {
@@ -429,4 +496,15 @@ mod tests {
             Err(_) => assert!(true)
         }
     }
+
+    #[test]
+    fn delivery_files_are_created_in_the_given_project() {
+        let project = TempDir::new("init-project").unwrap();
+        let dot_delivery = create_dot_delivery(project.path()).unwrap();
+        assert_eq!(project.path().join(".delivery"), dot_delivery);
+        assert!(dot_delivery.is_dir());
+        assert!(create_delivery_readme(project.path()).unwrap());
+        assert!(project.path().join("DELIVERY.md").is_file());
+        assert!(!create_delivery_readme(project.path()).unwrap());
+    }
 }
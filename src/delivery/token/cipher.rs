@@ -0,0 +1,195 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Encrypted token store
+//!
+//! With `encrypt_tokens = true` in `cli.toml` the token store is
+//! saved encrypted with ChaCha20-Poly1305. The key is derived with
+//! PBKDF2-SHA256 from a passphrase and a random salt written to the
+//! file; the passphrase comes from `$DELIVERY_TOKEN_PASSPHRASE`, the
+//! output of `token_passphrase_command` (e.g. a lookup in the OS
+//! keyring) or a prompt, in that order.
+//!
+//! An encrypted store is a header line followed by the rounds, salt,
+//! nonce, tag and ciphertext of the plaintext store, separated by `:`.
+//!
+use std::cell::RefCell;
+use std::fmt;
+use std::process::Stdio;
+use crypto::aead::{AeadDecryptor, AeadEncryptor};
+use crypto::chacha20poly1305::ChaCha20Poly1305;
+use crypto::hmac::Hmac;
+use crypto::pbkdf2::pbkdf2;
+use crypto::sha2::Sha256;
+use rand::{OsRng, Rng};
+use rustc_serialize::base64::{FromBase64, ToBase64, STANDARD};
+use rpassword;
+use config::Config;
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+use utils::{self, env_variable};
+
+const HEADER: &'static str = "#delivery-api-tokens:v1:pbkdf2-sha256:chacha20-poly1305";
+const ROUNDS: u32 = 10000;
+
+thread_local! {
+    // A passphrase typed at the prompt is asked once per invocation
+    static PROMPTED: RefCell<Option<String>> = RefCell::new(None)
+}
+
+/// The passphrase the key of an encrypted token store is derived from
+#[derive(Clone)]
+pub struct StoreKey {
+    passphrase: String
+}
+
+// Keep the passphrase out of debug output
+impl fmt::Debug for StoreKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "StoreKey(..)")
+    }
+}
+
+impl StoreKey {
+    pub fn new(passphrase: &str) -> StoreKey {
+        StoreKey{ passphrase: passphrase.to_string() }
+    }
+
+    // Find the passphrase for the token store of `config`
+    pub fn from_config(config: &Config) -> DeliveryResult<StoreKey> {
+        if let Some(p) = env_variable("DELIVERY_TOKEN_PASSPHRASE") {
+            return Ok(StoreKey::new(&p))
+        }
+        if let Some(ref cmd) = config.token_passphrase_command {
+            return StoreKey::from_command(cmd)
+        }
+        if let Some(p) = PROMPTED.with(|p| p.borrow().clone()) {
+            return Ok(StoreKey::new(&p))
+        }
+        if config.non_interactive.unwrap_or(false) {
+            let msg = "Set DELIVERY_TOKEN_PASSPHRASE or token_passphrase_command \
+                       to use the token store without a prompt".to_string();
+            return Err(DeliveryError{ kind: Kind::TokenStoreLocked, detail: Some(msg) })
+        }
+        let p = try!(rpassword::prompt_password_stdout("API token store passphrase: "));
+        PROMPTED.with(|prompted| *prompted.borrow_mut() = Some(p.clone()));
+        Ok(StoreKey::new(&p))
+    }
+
+    fn from_command(cmd: &str) -> DeliveryResult<StoreKey> {
+        let output = try!(utils::shell_command(cmd).stderr(Stdio::inherit()).output());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let passphrase = stdout.trim_right_matches(|c| c == '\n' || c == '\r');
+        if !output.status.success() || passphrase.is_empty() {
+            let msg = format!("`{}` did not print a passphrase ({})", cmd, output.status);
+            return Err(DeliveryError{ kind: Kind::TokenStoreLocked, detail: Some(msg) })
+        }
+        Ok(StoreKey::new(passphrase))
+    }
+
+    fn derive(&self, salt: &[u8], rounds: u32) -> [u8; 32] {
+        let mut mac = Hmac::new(Sha256::new(), self.passphrase.as_bytes());
+        let mut key = [0u8; 32];
+        pbkdf2(&mut mac, salt, rounds, &mut key);
+        key
+    }
+}
+
+// Whether `content` is an encrypted token store
+pub fn is_encrypted(content: &str) -> bool {
+    content.starts_with(HEADER)
+}
+
+pub fn encrypt(key: &StoreKey, plaintext: &str) -> DeliveryResult<String> {
+    let mut rng = try!(OsRng::new());
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 8];
+    rng.fill_bytes(&mut salt);
+    rng.fill_bytes(&mut nonce);
+    let mut cipher = ChaCha20Poly1305::new(&key.derive(&salt, ROUNDS), &nonce,
+                                           HEADER.as_bytes());
+    let mut ciphertext = vec![0u8; plaintext.len()];
+    let mut tag = [0u8; 16];
+    cipher.encrypt(plaintext.as_bytes(), &mut ciphertext, &mut tag);
+    Ok(format!("{}\n{}:{}:{}:{}:{}\n", HEADER, ROUNDS,
+               salt.to_base64(STANDARD), nonce.to_base64(STANDARD),
+               tag.to_base64(STANDARD), ciphertext.to_base64(STANDARD)))
+}
+
+pub fn decrypt(key: &StoreKey, content: &str) -> DeliveryResult<String> {
+    let fields: Vec<&str> = content.lines().nth(1).unwrap_or("").trim().split(':').collect();
+    if !is_encrypted(content) || fields.len() != 5 {
+        return Err(decrypt_error("the file is not a valid encrypted token store"))
+    }
+    let rounds = try!(fields[0].parse::<u32>()
+                      .map_err(|_| decrypt_error("invalid number of rounds")));
+    let mut parts = Vec::new();
+    for f in fields[1..].iter() {
+        parts.push(try!(f.from_base64().map_err(|e| decrypt_error(&e.to_string()))));
+    }
+    let (salt, nonce, tag, ciphertext) = (&parts[0], &parts[1], &parts[2], &parts[3]);
+    if nonce.len() != 8 || tag.len() != 16 {
+        return Err(decrypt_error("invalid nonce or tag"))
+    }
+    let mut cipher = ChaCha20Poly1305::new(&key.derive(salt, rounds), nonce,
+                                           HEADER.as_bytes());
+    let mut plaintext = vec![0u8; ciphertext.len()];
+    if !cipher.decrypt(ciphertext, &mut plaintext, tag) {
+        return Err(decrypt_error("wrong passphrase or corrupted file"))
+    }
+    String::from_utf8(plaintext).map_err(|e| decrypt_error(&e.to_string()))
+}
+
+fn decrypt_error(msg: &str) -> DeliveryError {
+    DeliveryError{ kind: Kind::TokenStoreDecryptFailed, detail: Some(msg.to_string()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use errors::Kind;
+
+    #[test]
+    fn encrypt_then_decrypt_test() {
+        let key = StoreKey::new("open sesame");
+        let plaintext = "127.0.0.1,acme,bob|beefbeef\n";
+        let content = encrypt(&key, plaintext).unwrap();
+        assert!(is_encrypted(&content));
+        assert!(!content.contains("beefbeef"));
+        assert_eq!(plaintext, decrypt(&key, &content).unwrap());
+        // Every write gets a new salt and nonce
+        assert!(content != encrypt(&key, plaintext).unwrap());
+    }
+
+    #[test]
+    fn decrypt_with_the_wrong_passphrase_test() {
+        let content = encrypt(&StoreKey::new("open sesame"), "a,b,c|d\n").unwrap();
+        let e = decrypt(&StoreKey::new("open barley"), &content).unwrap_err();
+        match e.kind {
+            Kind::TokenStoreDecryptFailed => {},
+            _ => panic!("Unexpected error: {:?}", e)
+        }
+    }
+
+    #[test]
+    fn passphrase_from_a_command_test() {
+        let key = StoreKey::from_command("echo 'open sesame'").unwrap();
+        assert_eq!("open sesame", key.passphrase);
+        assert!(StoreKey::from_command("true").is_err());
+        assert_eq!("StoreKey(..)", format!("{:?}", key));
+    }
+}
@@ -0,0 +1,158 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Token helpers
+//!
+//! Setting `token_helper` in `cli.toml` hands the API tokens over to
+//! an external command instead of `~/.delivery/api-tokens`, in the
+//! same spirit as git credential helpers. The command is run through
+//! the shell with `get` or `store` appended, and receives the
+//! attributes of the token on stdin, one `key=value` per line:
+//!
+//! ```text
+//! server=delivery.example.com
+//! enterprise=acme
+//! user=alice
+//! ```
+//!
+//! For `get`, the helper prints the token on stdout (either bare or as
+//! `token=<token>`), or nothing when it doesn't have one. For `store`,
+//! a `token=<token>` line is added to the input so the helper can save
+//! a token the CLI just requested.
+//!
+use std::io::{self, Write};
+use std::process::Stdio;
+use errors::{DeliveryError, Kind};
+use types::DeliveryResult;
+use utils;
+
+// Return the token the helper has for `server`, `ent` and `user`
+pub fn get(helper: &str, server: &str, ent: &str,
+           user: &str) -> DeliveryResult<Option<String>> {
+    let stdout = try!(run(helper, "get", &attributes(server, ent, user)));
+    Ok(parse_token(&stdout))
+}
+
+// Give a new token to the helper
+pub fn store(helper: &str, server: &str, ent: &str, user: &str,
+             token: &str) -> DeliveryResult<()> {
+    let input = format!("{}token={}\n", attributes(server, ent, user), token);
+    try!(run(helper, "store", &input));
+    Ok(())
+}
+
+fn attributes(server: &str, ent: &str, user: &str) -> String {
+    format!("server={}\nenterprise={}\nuser={}\n", server, ent, user)
+}
+
+// The first non-empty line of the output, without a `token=` prefix
+pub fn parse_token(stdout: &str) -> Option<String> {
+    stdout.lines()
+        .map(|l| l.trim())
+        .find(|l| !l.is_empty())
+        .map(|l| l.trim_left_matches("token=").to_string())
+}
+
+// Run `helper action` with `input` on stdin and return its stdout.
+// The helper shares our stderr so it can report problems or prompt
+// through it.
+fn run(helper: &str, action: &str, input: &str) -> DeliveryResult<String> {
+    let cmdline = format!("{} {}", helper, action);
+    debug!("Running token_helper: {}", cmdline);
+    let mut child = try!(utils::shell_command(&cmdline)
+                         .stdin(Stdio::piped())
+                         .stdout(Stdio::piped())
+                         .stderr(Stdio::inherit())
+                         .spawn()
+                         .map_err(|e| helper_error(&cmdline, &e.to_string())));
+    if let Some(mut stdin) = child.stdin.take() {
+        // Helpers are free to ignore their input and exit early
+        match stdin.write_all(input.as_bytes()) {
+            Err(ref e) if e.kind() == io::ErrorKind::BrokenPipe => {},
+            Err(e) => return Err(helper_error(&cmdline, &e.to_string())),
+            Ok(_) => {}
+        }
+    }
+    let output = try!(child.wait_with_output()
+                      .map_err(|e| helper_error(&cmdline, &e.to_string())));
+    if !output.status.success() {
+        return Err(helper_error(&cmdline, &format!("exited with {}", output.status)))
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn helper_error(cmdline: &str, msg: &str) -> DeliveryError {
+    DeliveryError{ kind: Kind::TokenHelperFailed,
+                   detail: Some(format!("`{}`: {}", cmdline, msg)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use errors::Kind;
+    use std::fs::File;
+    use std::io::prelude::*;
+    use tempdir::TempDir;
+
+    fn script(dir: &TempDir, content: &str) -> String {
+        let path = dir.path().join("helper.sh");
+        let mut f = File::create(&path).unwrap();
+        f.write_all(content.as_bytes()).unwrap();
+        format!("sh {}", path.display())
+    }
+
+    #[test]
+    fn parse_token_test() {
+        assert_eq!(Some("cafecafe".to_string()), parse_token("\ncafecafe\n"));
+        assert_eq!(Some("cafecafe".to_string()), parse_token("token=cafecafe\n"));
+        assert_eq!(None, parse_token(" \n"));
+    }
+
+    #[test]
+    fn get_reads_the_token_printed_by_the_helper() {
+        let dir = TempDir::new("token-helper").unwrap();
+        let helper = script(&dir, "[ \"$1\" = get ] || exit 1\n\
+                                   while read line; do\n\
+                                     case \"$line\" in user=*) user=${line#user=};; esac\n\
+                                   done\n\
+                                   echo \"token=for-$user\"\n");
+        let token = get(&helper, "127.0.0.1", "acme", "alice").unwrap();
+        assert_eq!(Some("for-alice".to_string()), token);
+        assert_eq!(None, get("true", "127.0.0.1", "acme", "alice").unwrap());
+    }
+
+    #[test]
+    fn store_passes_the_token_to_the_helper() {
+        let dir = TempDir::new("token-helper").unwrap();
+        let saved = dir.path().join("saved");
+        let helper = script(&dir, &format!("cat > {}\n", saved.display()));
+        store(&helper, "127.0.0.1", "acme", "alice", "beefbeef").unwrap();
+        let mut content = String::new();
+        File::open(&saved).unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!("server=127.0.0.1\nenterprise=acme\nuser=alice\ntoken=beefbeef\n",
+                   content);
+    }
+
+    #[test]
+    fn a_failing_helper_is_an_error() {
+        let e = get("exit 3;", "127.0.0.1", "acme", "alice").unwrap_err();
+        match e.kind {
+            Kind::TokenHelperFailed => {},
+            _ => panic!("Unexpected error: {:?}", e)
+        }
+    }
+}
@@ -22,44 +22,254 @@
 //! The `TokenStore` manages a map of keys to tokens and a path to the
 //! backing file. Adding or updating a token is done via `write_token`
 //! and will immediately rewrite the backing file. Find an existing
-//! token using `lookup`.
+//! token using `lookup`. When a `token_helper` is configured, the
+//! tokens are read from and saved through it instead (see `helper`).
+//!
+//! Each line of the file holds one token:
+//!
+//! ```text
+//! server,enterprise,user|token
+//! ```
+//!
+//! What else is known about a token is kept in a `.meta` file next
+//! to it, so that older versions of the CLI can still read the store:
+//!
+//! ```text
+//! server,enterprise,user|sha=3f9a0c1e5b7d2468|issued=2016-10-17T12:00:00Z|ttl=604800|verified=2016-10-17T12:00:00Z
+//! ```
+//!
+//! `sha` is the start of the SHA-256 of the token, and the rest only
+//! applies to the token with that digest: metadata left over from a
+//! token that an older CLI replaced is ignored. With the time a token
+//! was issued and the TTL the server gave it, the store warns when
+//! the token is about to expire (see `warn_if_expiring`).
+//!
+//! Both files are only readable by their owner, and can be encrypted
+//! (see `cipher`). Writers hold a lock on a `.lock` file next to
+//! them, and replace the files with a rename so that readers never
+//! see them half written.
 //!
 use std::io;
 use std::io::prelude::*;
-use std::io::BufReader;
 use std::path::PathBuf;
-use std::fs::{File, OpenOptions};
+use std::fs::{self, File};
+use std::ffi::OsString;
+use std::time::Duration;
 use std::collections::BTreeMap;
-use errors::DeliveryError;
+use std::cell::Cell;
+use errors::{DeliveryError, Kind};
 use types::DeliveryResult;
 use utils;
 use utils::path_join_many::PathJoinMany;
 use config::Config;
 use http;
+use http::saml::{self, TokenCallback};
 use utils::say::{sayln,say};
 use utils::{home_dir, env_variable};
 use rpassword;
+use time;
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+
+pub mod helper;
+pub mod cipher;
+
+use self::cipher::StoreKey;
+
+/// Warn about tokens expiring within a day, unless
+/// `token_expiry_warning` says otherwise.
+pub const DEFAULT_EXPIRY_WARNING: u64 = 24 * 60 * 60;
+
+thread_local! {
+    // The expiry warning is only printed once per invocation
+    static EXPIRY_WARNED: Cell<bool> = Cell::new(false)
+}
+
+/// A token of the store, along with what it was issued for
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenEntry {
+    pub server: String,
+    pub ent: String,
+    pub user: String,
+    pub token: String,
+    // When the token was saved after the server issued it (RFC 3339, UTC)
+    pub issued: Option<String>,
+    // How many seconds the server said the token is valid for
+    pub ttl: Option<u64>,
+    // When the server last accepted the token (RFC 3339, UTC)
+    pub verified: Option<String>
+}
+
+impl TokenEntry {
+    /// The token with all but its last four characters hidden
+    pub fn masked_token(&self) -> String {
+        let chars: Vec<char> = self.token.chars().collect();
+        if chars.len() <= 8 {
+            return "********".to_string()
+        }
+        let tail: String = chars[chars.len() - 4..].iter().cloned().collect();
+        format!("********{}", tail)
+    }
+
+    /// Whether the token was issued by `server`. Without a port in
+    /// `server`, any port matches.
+    pub fn is_from(&self, server: &str) -> bool {
+        self.server == server || self.server.starts_with(&format!("{}:", server))
+    }
+
+    /// Seconds until the token expires, negative once it has; None
+    /// when the server didn't give it a TTL
+    pub fn expires_in(&self) -> Option<i64> {
+        let (issued, ttl) = match (self.issued.as_ref(), self.ttl) {
+            (Some(i), Some(t)) => (i, t),
+            _ => return None
+        };
+        match time::strptime(issued, "%Y-%m-%dT%H:%M:%SZ") {
+            Ok(tm) => Some(tm.to_timespec().sec + ttl as i64 - time::get_time().sec),
+            Err(e) => {
+                debug!("Ignoring the issue time {} of a token: {}", issued, e);
+                None
+            }
+        }
+    }
+
+    fn to_line(&self) -> String {
+        format!("{}|{}", TokenStore::key(&self.server, &self.ent, &self.user), self.token)
+    }
+
+    // The line of the `.meta` file for the token, if there is
+    // anything to say about it
+    fn to_meta_line(&self) -> Option<String> {
+        if self.issued.is_none() && self.ttl.is_none() && self.verified.is_none() {
+            return None
+        }
+        let mut line = format!("{}|sha={}", TokenStore::key(&self.server, &self.ent, &self.user),
+                               self.digest());
+        if let Some(ref i) = self.issued {
+            line.push_str(&format!("|issued={}", i));
+        }
+        if let Some(t) = self.ttl {
+            line.push_str(&format!("|ttl={}", t));
+        }
+        if let Some(ref v) = self.verified {
+            line.push_str(&format!("|verified={}", v));
+        }
+        Some(line)
+    }
+
+    // The first 16 hex digits of the SHA-256 of the token
+    fn digest(&self) -> String {
+        let mut sha = Sha256::new();
+        sha.input_str(&self.token);
+        sha.result_str()[..16].to_string()
+    }
+
+    fn from_line(line: &str) -> Option<TokenEntry> {
+        let items = line.trim().split("|").collect::<Vec<&str>>();
+        if items.len() < 2 {
+            return None
+        }
+        let key = items[0].splitn(3, ",").collect::<Vec<&str>>();
+        if key.len() != 3 {
+            return None
+        }
+        let mut entry = TokenEntry{ server: key[0].to_string(),
+                                    ent: key[1].to_string(),
+                                    user: key[2].to_string(),
+                                    token: items[1].to_string(),
+                                    issued: None,
+                                    ttl: None,
+                                    verified: None };
+        // Stores written by some earlier versions kept the metadata on
+        // the token line; it moves to the `.meta` file on the next write.
+        if entry.set_meta(&items[2..]) {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+
+    // Set the metadata of `attrs`, such as `ttl=3600`. Returns false
+    // when one of them is not an attribute.
+    fn set_meta(&mut self, attrs: &[&str]) -> bool {
+        for attr in attrs.iter() {
+            match attr.find("=").map(|i| attr.split_at(i)) {
+                Some(("issued", v)) => self.issued = Some(v[1..].to_string()),
+                Some(("ttl", v)) => self.ttl = v[1..].parse().ok(),
+                Some(("verified", v)) => self.verified = Some(v[1..].to_string()),
+                Some(_) => debug!("Ignoring token attribute {}", attr),
+                None => return false
+            }
+        }
+        true
+    }
+}
 
 #[derive(Debug)]
 pub struct TokenStore {
-    tokens: BTreeMap<String, String>,
-    path: PathBuf
+    tokens: BTreeMap<String, TokenEntry>,
+    path: PathBuf,
+    key: Option<StoreKey>
 }
 
 impl TokenStore {
     pub fn from_home() -> DeliveryResult<TokenStore> {
-        let home_dot_delivery = try!(home_dir(&[".delivery"]));
-        try!(utils::mkdir_recursive(home_dot_delivery.as_path()));
-        let token_path = home_dot_delivery.join_many(&["api-tokens"]);
+        let token_path = try!(TokenStore::home_path());
         TokenStore::from_file(&token_path)
     }
 
     pub fn from_file(path: &PathBuf) -> Result<TokenStore, DeliveryError> {
-        let tokens = try!(TokenStore::read_config(&path));
-        let tstore = TokenStore {path: path.clone(), tokens: tokens};
+        TokenStore::open(path, None)
+    }
+
+    /// Open the token store of `config`: its `token_file`, or
+    /// `$HOME/.delivery/api-tokens`. A passphrase is needed when the
+    /// store is already encrypted or `encrypt_tokens` is set.
+    pub fn from_config(config: &Config) -> DeliveryResult<TokenStore> {
+        let path = match config.token_file {
+            Some(ref f) => PathBuf::from(f),
+            None => try!(TokenStore::home_path())
+        };
+        let encrypted = try!(TokenStore::read_file(&path))
+            .map(|content| cipher::is_encrypted(&content))
+            .unwrap_or(false);
+        let key = if encrypted || config.encrypt_tokens.unwrap_or(false) {
+            Some(try!(StoreKey::from_config(config)))
+        } else {
+            None
+        };
+        TokenStore::open(&path, key)
+    }
+
+    /// Open the store at `path`, encrypted with `key` if one is given.
+    /// A plaintext store opened with a key is encrypted right away.
+    pub fn open(path: &PathBuf, key: Option<StoreKey>) -> DeliveryResult<TokenStore> {
+        let (tokens, migrate) = match try!(TokenStore::read_file(path)) {
+            None => (BTreeMap::new(), false),
+            Some(content) => {
+                if utils::is_shared_file(path) {
+                    sayln("yellow", &format!("WARN: {} can be read by other users; \
+                                              run `chmod 600 {}`",
+                                             path.display(), path.display()));
+                }
+                let migrate = key.is_some() && !cipher::is_encrypted(&content);
+                (try!(TokenStore::decode(path, key.as_ref(), &content)), migrate)
+            }
+        };
+        let mut tstore = TokenStore {path: path.clone(), tokens: tokens, key: key};
+        if migrate {
+            debug!("Encrypting the token store {}", path.display());
+            try!(tstore.update(|_| ()));
+        }
         Ok(tstore)
     }
 
+    fn home_path() -> DeliveryResult<PathBuf> {
+        let home_dot_delivery = try!(home_dir(&[".delivery"]));
+        try!(utils::mkdir_recursive(home_dot_delivery.as_path()));
+        Ok(home_dot_delivery.join_many(&["api-tokens"]))
+    }
+
     pub fn path(&self) -> PathBuf {
         self.path.clone()
     }
@@ -67,7 +277,43 @@ impl TokenStore {
     pub fn lookup(&self,
                   server: &str, ent: &str, user: &str) -> Option<&String> {
         let key = TokenStore::key(server, ent, user);
-        self.tokens.get(&key)
+        self.tokens.get(&key).map(|e| &e.token)
+    }
+
+    /// All the tokens of the store, sorted by server, enterprise
+    /// and user.
+    pub fn entries(&self) -> Vec<&TokenEntry> {
+        self.tokens.values().collect()
+    }
+
+    /// Record that the server accepted the token of `user` just now
+    pub fn mark_verified(&mut self,
+                         server: &str, ent: &str, user: &str) -> DeliveryResult<()> {
+        let key = TokenStore::key(server, ent, user);
+        let now = format!("{}", time::now_utc().rfc3339());
+        self.update(move |tokens| {
+            if let Some(entry) = tokens.get_mut(&key) {
+                entry.verified = Some(now);
+            }
+        })
+    }
+
+    /// Remove the token of `user`, returning it if there was one
+    pub fn remove_token(&mut self,
+                        server: &str, ent: &str, user: &str) -> DeliveryResult<Option<TokenEntry>> {
+        let key = TokenStore::key(server, ent, user);
+        self.update(move |tokens| tokens.remove(&key))
+    }
+
+    /// Remove every token issued by `server` (see `TokenEntry::is_from`)
+    pub fn remove_server(&mut self, server: &str) -> DeliveryResult<Vec<TokenEntry>> {
+        self.update(|tokens| {
+            let keys: Vec<String> = tokens.iter()
+                .filter(|&(_, e)| e.is_from(server))
+                .map(|(k, _)| k.clone())
+                .collect();
+            keys.iter().filter_map(|k| tokens.remove(k)).collect()
+        })
     }
 
     pub fn write_token(&mut self,
@@ -75,11 +321,66 @@ impl TokenStore {
                        ent: &str,
                        user: &str,
                        token: &str) -> Result<Option<String>, DeliveryError> {
+        let key = TokenStore::key(server, ent, user);
+        let entry = TokenEntry{ server: server.to_string(),
+                                ent: ent.to_string(),
+                                user: user.to_string(),
+                                token: token.to_string(),
+                                issued: None,
+                                ttl: None,
+                                verified: None };
+        self.update(move |tokens| tokens.insert(key, entry).map(|e| e.token))
+    }
+
+    /// Save a token the server just issued, valid for `ttl` seconds
+    /// if the server said so.
+    pub fn write_issued_token(&mut self,
+                              server: &str, ent: &str, user: &str,
+                              token: &str, ttl: Option<u64>) -> DeliveryResult<()> {
+        let key = TokenStore::key(server, ent, user);
+        let entry = TokenEntry{ server: server.to_string(),
+                                ent: ent.to_string(),
+                                user: user.to_string(),
+                                token: token.to_string(),
+                                issued: Some(format!("{}", time::now_utc().rfc3339())),
+                                ttl: ttl,
+                                verified: None };
+        self.update(move |tokens| { tokens.insert(key, entry); })
+    }
+
+    /// The seconds left before the token of `user` expires, if that
+    /// is `window` seconds or less.
+    pub fn expires_within(&self, server: &str, ent: &str, user: &str,
+                          window: u64) -> Option<i64> {
+        let key = TokenStore::key(server, ent, user);
+        self.tokens.get(&key)
+            .and_then(|e| e.expires_in())
+            .and_then(|left| if left <= window as i64 { Some(left) } else { None })
+    }
 
-        let result = self.set_token(server, ent, user, token);
-        match self.write_config() {
-            Ok(_) => Ok(result),
-            Err(e) => Err(e)
+    /// Warn, once per invocation, when the token of `user` expires
+    /// within the `token_expiry_warning` window of `config`.
+    pub fn warn_if_expiring(&self, config: &Config, server: &str, ent: &str, user: &str) {
+        let window = config.token_expiry_warning.unwrap_or(DEFAULT_EXPIRY_WARNING);
+        let left = match self.expires_within(server, ent, user, window) {
+            Some(left) => left,
+            None => return
+        };
+        let warned = EXPIRY_WARNED.with(|w| {
+            let warned = w.get();
+            w.set(true);
+            warned
+        });
+        if warned {
+            return
+        }
+        if left <= 0 {
+            sayln("yellow", &format!("WARN: The API token of {} has expired; \
+                                      run `delivery token --renew` to renew it", user));
+        } else {
+            sayln("yellow", &format!("WARN: The API token of {} expires in {}; \
+                                      run `delivery token --renew` to renew it",
+                                     user, format_duration(left)));
         }
     }
 
@@ -87,8 +388,17 @@ impl TokenStore {
       let server = try!(config.api_host_and_port());
       let ent = try!(config.enterprise());
       let user = try!(config.user());
-      let tstore = try!(TokenStore::from_home());
-      match tstore.lookup(&server, &ent, &user) {
+      let mut tstore = None;
+      let stored = match config.token_helper {
+          Some(ref cmd) => try!(helper::get(cmd, &server, &ent, &user)),
+          None => {
+              let store = try!(TokenStore::from_config(config));
+              let token = store.lookup(&server, &ent, &user).cloned();
+              tstore = Some(store);
+              token
+          }
+      };
+      match stored {
         Some(token) => {
             sayln("magenta", &format!("token: {}", &token));
             say("yellow", "Verifying Token: ");
@@ -97,6 +407,10 @@ impl TokenStore {
                 Ok(valid) => {
                     if valid {
                         sayln("green", "valid");
+                        if let Some(ref mut store) = tstore {
+                            try!(store.mark_verified(&server, &ent, &user));
+                            store.warn_if_expiring(config, &server, &ent, &user);
+                        }
                         return Ok(token.clone())
                     } else {
                         sayln("red", "expired");
@@ -116,22 +430,14 @@ impl TokenStore {
         let ent = try!(config.enterprise());
         let user = try!(config.user());
         let api_server = try!(config.api_host_and_port());
-        let mut tstore = try!(TokenStore::from_home());
 
         let saml = match config.saml {
             Some(b) => b,
             None => try!(http::saml::is_enabled(&config)),
         };
-        let token = if saml {
-            let mut enter = String::new();
-            say("red", "Press Enter to open a browser window to retrieve a new token.");
-            try!(io::stdin().read_line(&mut enter));
-            sayln("white", "Launching browser..");
-            try!(TokenStore::initate_saml_auth(&config));
-            let mut token = String::new();
-            say("white", "Enter token: ");
-            try!(io::stdin().read_line(&mut token));
-            token.trim().to_string()
+        // The web UI doesn't say how long a SAML token is valid for
+        let (token, ttl) = if saml {
+            (try!(TokenStore::saml_token(&config, utils::open::item)), None)
         } else {
             // For automation purposes if the end-user provides the environment
             // variable `AUTOMATE_PASSWORD` we will consume it instead of prompting
@@ -141,22 +447,121 @@ impl TokenStore {
                 Some(p) => p,
                 None => try!(rpassword::prompt_password_stdout("Automate password: "))
             };
-            try!(http::token::request(&config, &pass))
+            let issued = try!(http::token::request(&config, &pass));
+            (issued.token, issued.ttl)
         };
         sayln("magenta", &format!("token: {}", &token));
-        try!(tstore.write_token(&api_server, &ent, &user, &token));
-        sayln("green", &format!("saved API token to: {}", tstore.path().display()));
+        match config.token_helper {
+            Some(ref cmd) => {
+                try!(helper::store(cmd, &api_server, &ent, &user, &token));
+                sayln("green", &format!("saved API token with: {}", cmd));
+            },
+            None => {
+                let mut tstore = try!(TokenStore::from_config(config));
+                try!(tstore.write_issued_token(&api_server, &ent, &user, &token, ttl));
+                // A token issued for the password was accepted by the
+                // server; a pasted SAML token is verified below
+                if !saml {
+                    try!(tstore.mark_verified(&api_server, &ent, &user));
+                }
+                sayln("green", &format!("saved API token to: {}", tstore.path().display()));
+            }
+        }
         if saml {
             try!(TokenStore::verify_token(&config));
         };
         Ok(token)
     }
 
-    fn web_token_url(config: &Config) -> Result<String, DeliveryError> {
+    /// Make sure the stored token is good for a while: request a new
+    /// one if it expires within the `token_expiry_warning` window,
+    /// verify it otherwise. Tokens without a known TTL, such as SAML
+    /// tokens and those of a `token_helper`, are only verified.
+    pub fn renew_token(config: &Config) -> DeliveryResult<String> {
+        if config.token_helper.is_none() {
+            let server = try!(config.api_host_and_port());
+            let ent = try!(config.enterprise());
+            let user = try!(config.user());
+            let window = config.token_expiry_warning.unwrap_or(DEFAULT_EXPIRY_WARNING);
+            let tstore = try!(TokenStore::from_config(config));
+            if let Some(left) = tstore.expires_within(&server, &ent, &user, window) {
+                if left <= 0 {
+                    sayln("yellow", "The API token has expired, renewing it");
+                } else {
+                    sayln("yellow", &format!("The API token expires in {}, renewing it",
+                                             format_duration(left)));
+                }
+                return TokenStore::request_token(config)
+            }
+        }
+        TokenStore::verify_token(config)
+    }
+
+    // Get a token through a SAML login in the browser, opened with
+    // `open`. With `saml_callback` set, the web UI hands the token
+    // back to a local listener; otherwise, or if that fails, the
+    // token is pasted.
+    fn saml_token<F>(config: &Config, open: F) -> DeliveryResult<String>
+        where F: Fn(&str) -> DeliveryResult<()>
+    {
+        if !config.saml_callback.unwrap_or(false) {
+            let mut enter = String::new();
+            say("red", "Press Enter to open a browser window to retrieve a new token.");
+            try!(io::stdin().read_line(&mut enter));
+            sayln("white", "Launching browser..");
+            try!(open(&try!(TokenStore::web_token_url(&config, None))));
+            return TokenStore::paste_token(config)
+        }
+        match TokenCallback::bind() {
+            Ok(callback) => {
+                let redirect = try!(callback.redirect_url());
+                let url = try!(TokenStore::web_token_url(&config, Some(&redirect)));
+                sayln("white", "Launching browser..");
+                if let Err(e) = open(&url) {
+                    debug!("Unable to open a browser: {:?}", e);
+                    sayln("yellow", &format!("Open {} to log in", url));
+                }
+                let timeout = config.saml_callback_timeout
+                    .unwrap_or(saml::DEFAULT_CALLBACK_TIMEOUT);
+                sayln("white", &format!("Waiting {}s for the token on {}", timeout, redirect));
+                if let Some(token) = try!(callback.wait_for_token(Duration::from_secs(timeout))) {
+                    sayln("green", "Got the token from the browser");
+                    return Ok(token)
+                }
+                sayln("yellow", "The browser didn't send the token; the web UI of this \
+                                 server may not support `redirect_uri`");
+            },
+            Err(e) => {
+                debug!("Unable to listen for the token: {:?}", e);
+                sayln("yellow", "Unable to listen for the token on 127.0.0.1");
+                try!(open(&try!(TokenStore::web_token_url(&config, None))));
+            }
+        }
+        TokenStore::paste_token(config)
+    }
+
+    // The token the user copies from the web UI
+    fn paste_token(config: &Config) -> DeliveryResult<String> {
+        if config.non_interactive.unwrap_or(false) {
+            let msg = "Unable to ask for the token due to --no-interactive flag".to_string();
+            return Err(DeliveryError{ kind: Kind::NoToken, detail: Some(msg) })
+        }
+        let mut token = String::new();
+        say("white", "Enter token: ");
+        try!(io::stdin().read_line(&mut token));
+        Ok(token.trim().to_string())
+    }
+
+    // The page of the web UI showing a new token; with `redirect`,
+    // the UI sends the token there rather than showing it
+    fn web_token_url(config: &Config, redirect: Option<&str>) -> Result<String, DeliveryError> {
         let host = try!(config.api_host_and_port());
         let ent = try!(config.enterprise());
         let proto = try!(config.api_protocol());
-        let path = "#/dashboard?token";
+        let path = match redirect {
+            Some(r) => format!("#/dashboard?token&redirect_uri={}", saml::percent_encode(r)),
+            None => "#/dashboard?token".to_string()
+        };
         Ok(TokenStore::format_web_token_url(&host, &ent, &proto, &path))
     }
 
@@ -165,56 +570,163 @@ impl TokenStore {
             proto, host, ent, path)
     }
 
-    fn initate_saml_auth(config: &Config) -> Result<(), DeliveryError> {
-        let url = try!(TokenStore::web_token_url(&config));
-        utils::open::item(&url)
-    }
-
     fn key(server: &str, ent: &str, user: &str) -> String {
         format!("{},{},{}", server, ent, user)
     }
 
-    fn set_token(&mut self,
-                 server: &str, ent: &str, user: &str,
-                 token: &str) -> Option<String> {
-        let key = TokenStore::key(server, ent, user);
-        self.tokens.insert(key, token.to_string())
+    // Apply `change` to the tokens and save them. The tokens are read
+    // again under the lock first, so that the tokens other processes
+    // saved since the store was opened are kept.
+    fn update<F, R>(&mut self, change: F) -> DeliveryResult<R>
+        where F: FnOnce(&mut BTreeMap<String, TokenEntry>) -> R
+    {
+        let _lock = try!(utils::lock_file(self.sibling_path("lock")));
+        if let Some(content) = try!(TokenStore::read_file(&self.path)) {
+            self.tokens = try!(TokenStore::decode(&self.path, self.key.as_ref(), &content));
+        }
+        let result = change(&mut self.tokens);
+        try!(self.write_config());
+        Ok(result)
+    }
+
+    fn sibling_path(&self, ext: &str) -> PathBuf {
+        TokenStore::sibling_of(&self.path, ext)
+    }
+
+    // `path` with `ext` appended to its file name
+    fn sibling_of(path: &PathBuf, ext: &str) -> PathBuf {
+        let mut name = path.file_name()
+            .map(|n| n.to_os_string())
+            .unwrap_or_else(OsString::new);
+        name.push(".");
+        name.push(ext);
+        path.with_file_name(name)
     }
 
+    // The tokens, then their metadata, are written to temporary files
+    // only their owner can read, which then replace the store files.
+    // Only call it with the lock held (see `update`).
     fn write_config(&self) -> Result<(), DeliveryError> {
-        let mut file = try!(File::create(&self.path));
-        for (k, v) in self.tokens.iter() {
-            let line = format!("{}|{}\n", k, v);
-            try!(file.write_all(line.as_bytes()));
+        let mut content = String::new();
+        let mut meta = String::new();
+        for entry in self.tokens.values() {
+            content.push_str(&entry.to_line());
+            content.push_str("\n");
+            if let Some(line) = entry.to_meta_line() {
+                meta.push_str(&line);
+                meta.push_str("\n");
+            }
         }
+        try!(self.replace_file(&self.path, &content));
+        let meta_path = self.sibling_path("meta");
+        if meta.is_empty() {
+            match fs::remove_file(&meta_path) {
+                Ok(_) => Ok(()),
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+                Err(e) => Err(DeliveryError::from(e))
+            }
+        } else {
+            self.replace_file(&meta_path, &meta)
+        }
+    }
+
+    fn replace_file(&self, path: &PathBuf, content: &str) -> DeliveryResult<()> {
+        let content = match self.key {
+            Some(ref key) => try!(cipher::encrypt(key, content)),
+            None => content.to_string()
+        };
+        let tmp_path = TokenStore::sibling_of(path, "tmp");
+        {
+            let mut file = try!(utils::create_private_file(&tmp_path));
+            try!(file.write_all(content.as_bytes()));
+            try!(file.sync_all());
+        }
+        try!(fs::rename(&tmp_path, path));
         Ok(())
     }
 
-    fn read_config(path: &PathBuf) -> Result<BTreeMap<String, String>, DeliveryError> {
-        let mut opener = OpenOptions::new();
-        opener.create(true);
-        opener.truncate(false);
-        opener.write(true);
-        opener.read(true);
-        let file = try!(opener.open(&path));
-        let reader = BufReader::new(file);
-        let mut map: BTreeMap<String, String> = BTreeMap::new();
-
-        for line in reader.lines() {
-            let real_line = try!(line);
-            let split = real_line.trim().split("|");
-            let items = split.collect::<Vec<&str>>();
-            if items.len() == 2 {
-                let key = items[0].to_string();
-                let token = items[1].to_string();
-                map.insert(key, token);
-            } else {
-                println!("skipping malformed line: {}", real_line);
+    // The content of the store; a missing file is an empty store
+    fn read_file(path: &PathBuf) -> Result<Option<String>, DeliveryError> {
+        match File::open(path) {
+            Ok(mut file) => {
+                let mut content = String::new();
+                try!(file.read_to_string(&mut content));
+                Ok(Some(content))
+            },
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(DeliveryError::from(e))
+        }
+    }
+
+    // The tokens of the store content read from `path`, along with
+    // their metadata from the `.meta` file next to it
+    fn decode(path: &PathBuf, key: Option<&StoreKey>,
+              content: &str) -> DeliveryResult<BTreeMap<String, TokenEntry>> {
+        let mut tokens = TokenStore::parse_config(&try!(TokenStore::plaintext(path, key, content)));
+        let meta_path = TokenStore::sibling_of(path, "meta");
+        if let Some(meta) = try!(TokenStore::read_file(&meta_path)) {
+            let meta = try!(TokenStore::plaintext(&meta_path, key, &meta));
+            TokenStore::parse_meta(&meta, &mut tokens);
+        }
+        Ok(tokens)
+    }
+
+    fn plaintext(path: &PathBuf, key: Option<&StoreKey>,
+                 content: &str) -> DeliveryResult<String> {
+        if !cipher::is_encrypted(content) {
+            return Ok(content.to_string())
+        }
+        match key {
+            Some(k) => cipher::decrypt(k, content),
+            None => Err(DeliveryError{ kind: Kind::TokenStoreLocked,
+                                       detail: Some(format!("{}", path.display())) })
+        }
+    }
+
+    // Set the metadata of the `.meta` file on the tokens it is about
+    fn parse_meta(content: &str, tokens: &mut BTreeMap<String, TokenEntry>) {
+        for line in content.lines() {
+            let items = line.trim().split("|").collect::<Vec<&str>>();
+            let entry = match tokens.get_mut(items[0]) {
+                Some(entry) => entry,
+                None => continue
+            };
+            if items.len() < 2 || items[1] != format!("sha={}", entry.digest()) {
+                debug!("Ignoring the metadata of another token of {}", items[0]);
+                continue
+            }
+            if !entry.set_meta(&items[2..]) {
+                sayln("yellow", &format!("skipping malformed metadata: {}", line));
             }
         }
-        Ok(map)
     }
 
+    fn parse_config(content: &str) -> BTreeMap<String, TokenEntry> {
+        let mut map: BTreeMap<String, TokenEntry> = BTreeMap::new();
+
+        for real_line in content.lines() {
+            match TokenEntry::from_line(real_line) {
+                Some(entry) => {
+                    let key = TokenStore::key(&entry.server, &entry.ent, &entry.user);
+                    map.insert(key, entry);
+                },
+                None => sayln("yellow", &format!("skipping malformed line: {}", real_line))
+            }
+        }
+        map
+    }
+
+}
+
+// `secs` as hours and minutes, such as `5h12m`
+fn format_duration(secs: i64) -> String {
+    if secs >= 60 * 60 {
+        format!("{}h{:02}m", secs / 3600, secs % 3600 / 60)
+    } else if secs >= 60 {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}s", secs)
+    }
 }
 
 #[cfg(test)]
@@ -248,6 +760,224 @@ mod tests {
         assert_eq!("127.0.0.1,acme,bob|beefbeef\n", content);
     }
 
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn only_the_owner_can_read_the_store_test() {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+        let tempdir = TempDir::new("t1").ok().expect("TempDir failed");
+        let tfile = tempdir.path().join_many(&["api-tokens"]);
+        File::create(&tfile).unwrap().write_all(b"127.0.0.1,acme,bob|beefbeef\n").unwrap();
+        fs::set_permissions(&tfile, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(utils::is_shared_file(&tfile));
+
+        let mut tstore = TokenStore::from_file(&tfile).unwrap();
+        tstore.write_token("127.0.0.1", "acme", "alice", "cafecafe").unwrap();
+        let mode = fs::metadata(&tfile).unwrap().permissions().mode();
+        assert_eq!(0o600, mode & 0o777);
+    }
+
+    #[test]
+    fn plaintext_store_is_encrypted_when_opened_with_a_key_test() {
+        let tempdir = TempDir::new("t1").ok().expect("TempDir failed");
+        let tfile = tempdir.path().join_many(&["api-tokens"]);
+        File::create(&tfile).unwrap().write_all(b"127.0.0.1,acme,bob|beefbeef\n").unwrap();
+
+        let key = StoreKey::new("open sesame");
+        let tstore = TokenStore::open(&tfile, Some(key.clone())).unwrap();
+        assert_eq!(&"beefbeef", tstore.lookup("127.0.0.1", "acme", "bob").unwrap());
+        let mut content = String::new();
+        File::open(&tfile).unwrap().read_to_string(&mut content).unwrap();
+        assert!(cipher::is_encrypted(&content));
+        assert!(!content.contains("beefbeef"));
+
+        // The encrypted store can't be read without the key
+        match TokenStore::from_file(&tfile) {
+            Err(DeliveryError{ kind: Kind::TokenStoreLocked, .. }) => {},
+            r => panic!("Unexpected result: {:?}", r)
+        }
+        let mut tstore = TokenStore::open(&tfile, Some(key)).unwrap();
+        assert_eq!(&"beefbeef", tstore.lookup("127.0.0.1", "acme", "bob").unwrap());
+
+        // So is the metadata of its tokens
+        tstore.mark_verified("127.0.0.1", "acme", "bob").unwrap();
+        let mut meta = String::new();
+        File::open(tempdir.path().join_many(&["api-tokens.meta"])).unwrap()
+            .read_to_string(&mut meta).unwrap();
+        assert!(cipher::is_encrypted(&meta));
+        assert!(!meta.contains("verified"));
+    }
+
+    #[test]
+    fn tokens_are_listed_and_removed_test() {
+        let tempdir = TempDir::new("t1").ok().expect("TempDir failed");
+        let tfile = tempdir.path().join_many(&["api-tokens"]);
+        File::create(&tfile).unwrap()
+            .write_all(b"earth,acme,kirk|beefbeef|verified=2016-10-17T12:00:00Z\n\
+                         earth,acme,spock|cafecafe\n\
+                         earth:8443,acme,sulu|deadbeef\n\
+                         vulcan,acme,spock|f00dcafe1234\n").unwrap();
+        let mut tstore = TokenStore::from_file(&tfile).unwrap();
+        let entries = tstore.entries();
+        assert_eq!(4, entries.len());
+        assert_eq!("kirk", entries[0].user);
+        assert_eq!(Some("2016-10-17T12:00:00Z".to_string()), entries[0].verified);
+        assert_eq!(None, entries[1].verified);
+        assert_eq!("earth:8443", entries[2].server);
+        assert_eq!("********1234", entries[3].masked_token());
+        assert_eq!("********", entries[0].masked_token());
+
+        let removed = tstore.remove_token("earth", "acme", "spock").unwrap();
+        assert_eq!("cafecafe", removed.unwrap().token);
+        assert_eq!(None, tstore.remove_token("earth", "acme", "spock").unwrap());
+
+        let removed = tstore.remove_server("earth").unwrap();
+        assert_eq!(vec!["kirk", "sulu"], removed.iter().map(|e| &e.user[..]).collect::<Vec<_>>());
+
+        let mut content = String::new();
+        File::open(&tfile).unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!("vulcan,acme,spock|f00dcafe1234\n", content);
+    }
+
+    #[test]
+    fn mark_verified_is_saved_test() {
+        let tempdir = TempDir::new("t1").ok().expect("TempDir failed");
+        let tfile = tempdir.path().join_many(&["api-tokens"]);
+        let mut tstore = TokenStore::from_file(&tfile).unwrap();
+        tstore.write_token("earth", "acme", "kirk", "beefbeef").unwrap();
+        tstore.mark_verified("earth", "acme", "kirk").unwrap();
+
+        let tstore = TokenStore::from_file(&tfile).unwrap();
+        let verified = tstore.entries()[0].verified.clone().expect("not verified");
+        assert!(verified.ends_with("Z"));
+        assert_eq!(&"beefbeef", tstore.lookup("earth", "acme", "kirk").unwrap());
+    }
+
+    #[test]
+    fn metadata_is_kept_next_to_the_tokens_test() {
+        let tempdir = TempDir::new("t1").ok().expect("TempDir failed");
+        let tfile = tempdir.path().join_many(&["api-tokens"]);
+        let mfile = tempdir.path().join_many(&["api-tokens.meta"]);
+        let mut tstore = TokenStore::from_file(&tfile).unwrap();
+        tstore.write_issued_token("earth", "acme", "kirk", "beefbeef", Some(7200)).unwrap();
+        tstore.write_token("earth", "acme", "spock", "cafecafe").unwrap();
+
+        // Older CLIs only understand `key|token` lines
+        let mut content = String::new();
+        File::open(&tfile).unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!("earth,acme,kirk|beefbeef\nearth,acme,spock|cafecafe\n", content);
+        let mut meta = String::new();
+        File::open(&mfile).unwrap().read_to_string(&mut meta).unwrap();
+        assert!(meta.starts_with("earth,acme,kirk|sha="), "meta: {:?}", meta);
+        assert!(meta.ends_with("|ttl=7200\n"), "meta: {:?}", meta);
+        assert_eq!(Some(7200), TokenStore::from_file(&tfile).unwrap().entries()[0].ttl);
+
+        // The metadata of a token an older CLI replaced is ignored
+        File::create(&tfile).unwrap().write_all(b"earth,acme,kirk|f00dcafe\n").unwrap();
+        let mut tstore = TokenStore::from_file(&tfile).unwrap();
+        assert_eq!(None, tstore.entries()[0].ttl);
+        assert_eq!(None, tstore.entries()[0].issued);
+
+        // and dropped with the next write
+        tstore.remove_token("earth", "acme", "kirk").unwrap();
+        assert!(!mfile.exists());
+    }
+
+    #[test]
+    fn metadata_on_token_lines_moves_to_the_meta_file_test() {
+        let tempdir = TempDir::new("t1").ok().expect("TempDir failed");
+        let tfile = tempdir.path().join_many(&["api-tokens"]);
+        File::create(&tfile).unwrap()
+            .write_all(b"earth,acme,kirk|beefbeef|issued=2016-10-17T12:00:00Z|ttl=3600\n")
+            .unwrap();
+        let mut tstore = TokenStore::from_file(&tfile).unwrap();
+        tstore.write_token("earth", "acme", "spock", "cafecafe").unwrap();
+
+        let mut content = String::new();
+        File::open(&tfile).unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!("earth,acme,kirk|beefbeef\nearth,acme,spock|cafecafe\n", content);
+        let tstore = TokenStore::from_file(&tfile).unwrap();
+        assert_eq!(Some(3600), tstore.entries()[0].ttl);
+        assert_eq!(Some("2016-10-17T12:00:00Z".to_string()), tstore.entries()[0].issued);
+    }
+
+    #[test]
+    fn concurrent_writers_keep_all_tokens_test() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::thread;
+        let tempdir = TempDir::new("t1").ok().expect("TempDir failed");
+        let tfile = tempdir.path().join_many(&["api-tokens"]);
+        let done = Arc::new(AtomicBool::new(false));
+
+        let reader = {
+            let (tfile, done) = (tfile.clone(), done.clone());
+            thread::spawn(move || {
+                while !done.load(Ordering::SeqCst) {
+                    if let Some(content) = TokenStore::read_file(&tfile).unwrap() {
+                        assert!(content.ends_with("\n"), "half written: {:?}", content);
+                        for line in content.lines() {
+                            assert!(TokenEntry::from_line(line).is_some(),
+                                    "half written: {:?}", content);
+                        }
+                    }
+                }
+            })
+        };
+        let writers: Vec<_> = (0..4).map(|w| {
+            let tfile = tfile.clone();
+            thread::spawn(move || {
+                for n in 0..10 {
+                    let mut tstore = TokenStore::from_file(&tfile).unwrap();
+                    let user = format!("writer{}-{}", w, n);
+                    tstore.write_token("127.0.0.1", "acme", &user, "beefbeef").unwrap();
+                }
+            })
+        }).collect();
+        for w in writers {
+            w.join().unwrap();
+        }
+        done.store(true, Ordering::SeqCst);
+        reader.join().unwrap();
+
+        let tstore = TokenStore::from_file(&tfile).unwrap();
+        assert_eq!(40, tstore.entries().len());
+        assert!(!tempdir.path().join_many(&["api-tokens.tmp"]).exists());
+    }
+
+    #[test]
+    fn issued_tokens_expire_test() {
+        let tempdir = TempDir::new("t1").ok().expect("TempDir failed");
+        let tfile = tempdir.path().join_many(&["api-tokens"]);
+        let mut tstore = TokenStore::from_file(&tfile).unwrap();
+        tstore.write_issued_token("earth", "acme", "kirk", "beefbeef", Some(7200)).unwrap();
+        tstore.write_issued_token("earth", "acme", "spock", "cafecafe", None).unwrap();
+
+        let tstore = TokenStore::from_file(&tfile).unwrap();
+        let kirk = tstore.entries()[0].clone();
+        assert_eq!(Some(7200), kirk.ttl);
+        assert!(kirk.issued.is_some());
+        let left = kirk.expires_in().unwrap();
+        assert!(left > 7100 && left <= 7200, "expires in {}", left);
+        assert_eq!(None, tstore.entries()[1].expires_in());
+
+        assert_eq!(None, tstore.expires_within("earth", "acme", "kirk", 3600));
+        assert_eq!(Some(left), tstore.expires_within("earth", "acme", "kirk", 86400));
+        assert_eq!(None, tstore.expires_within("earth", "acme", "spock", 86400));
+    }
+
+    #[test]
+    fn expired_token_test() {
+        let entry = TokenEntry::from_line("earth,acme,kirk|beefbeef|issued=2016-10-17T12:00:00Z\
+                                           |ttl=3600").unwrap();
+        assert_eq!("earth,acme,kirk|beefbeef", entry.to_line());
+        assert_eq!("earth,acme,kirk|sha=ea9d2e8d6f15b750|issued=2016-10-17T12:00:00Z|ttl=3600",
+                   entry.to_meta_line().unwrap());
+        assert!(entry.expires_in().unwrap() < 0);
+        assert_eq!("5h07m", format_duration(5 * 3600 + 7 * 60 + 12));
+        assert_eq!("12m", format_duration(12 * 60));
+    }
+
     #[test]
     fn web_token_url_test() {
         let mut config = Config::default()
@@ -257,8 +987,56 @@ mod tests {
             .set_api_port("80");
         config.non_interactive = Some(true);
 
-        let url = TokenStore::web_token_url(&config).unwrap();
+        let url = TokenStore::web_token_url(&config, None).unwrap();
 
         assert_eq!(url, "http://earth:80/e/ncc-1701/#/dashboard?token");
+
+        let url = TokenStore::web_token_url(&config, Some("http://127.0.0.1:4242/cafe")).unwrap();
+        assert_eq!(url, "http://earth:80/e/ncc-1701/#/dashboard?token\
+                         &redirect_uri=http%3A%2F%2F127.0.0.1%3A4242%2Fcafe");
+    }
+
+    #[test]
+    fn saml_token_from_the_callback_test() {
+        use std::io::prelude::*;
+        use std::net::TcpStream;
+        use std::thread;
+        let mut config = Config::default()
+            .set_enterprise("ncc-1701")
+            .set_server("earth");
+        config.saml_callback = Some(true);
+        config.saml_callback_timeout = Some(10);
+        config.non_interactive = Some(true);
+
+        // Plays the web UI: send the token to the redirect_uri
+        let token = TokenStore::saml_token(&config, |url| {
+            let redirect = url.split("redirect_uri=").nth(1).unwrap()
+                .replace("%3A", ":").replace("%2F", "/");
+            let rest = redirect.trim_left_matches("http://").to_string();
+            thread::spawn(move || {
+                let slash = rest.find('/').unwrap();
+                let mut stream = TcpStream::connect(&rest[..slash]).unwrap();
+                let request = format!("GET {}?token=cafe%2Bcafe HTTP/1.1\r\n\r\n", &rest[slash..]);
+                stream.write_all(request.as_bytes()).unwrap();
+                let mut answer = String::new();
+                stream.read_to_string(&mut answer).unwrap();
+            });
+            Ok(())
+        }).unwrap();
+        assert_eq!("cafe+cafe", token);
+    }
+
+    #[test]
+    fn saml_token_without_callback_nor_tty_test() {
+        let mut config = Config::default()
+            .set_enterprise("ncc-1701")
+            .set_server("earth");
+        config.saml_callback = Some(true);
+        config.saml_callback_timeout = Some(0);
+        config.non_interactive = Some(true);
+        match TokenStore::saml_token(&config, |_| Ok(())) {
+            Err(DeliveryError{ kind: Kind::NoToken, .. }) => {},
+            r => panic!("Unexpected result: {:?}", r)
+        }
     }
 }
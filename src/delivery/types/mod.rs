@@ -20,3 +20,16 @@ use std::result;
 
 pub type DeliveryResult<T> = result::Result<T, DeliveryError>;
 pub type ExitCode = i32;
+
+// Exit codes for change actions (approve, deliver) that were refused
+pub const EXIT_NOT_READY: ExitCode = 2;
+pub const EXIT_FORBIDDEN: ExitCode = 3;
+
+// Exit codes for watched changes that failed a phase or ran out of time
+pub const EXIT_PHASE_FAILED: ExitCode = 4;
+pub const EXIT_WATCH_TIMED_OUT: ExitCode = 6;
+
+// Exit codes of `delivery api` by HTTP status class
+pub const EXIT_HTTP_REDIRECTION: ExitCode = 3;
+pub const EXIT_HTTP_CLIENT_ERROR: ExitCode = 4;
+pub const EXIT_HTTP_SERVER_ERROR: ExitCode = 5;
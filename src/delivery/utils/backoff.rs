@@ -0,0 +1,80 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! An exponential backoff for polling and retrying requests.
+
+use std::time::Duration;
+
+/// Yields increasing delays, starting at `initial` and growing by
+/// `factor` on every step until reaching `max`.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    factor: f64,
+    current: Duration,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration, factor: f64) -> Backoff {
+        Backoff {
+            initial: initial,
+            max: max,
+            factor: factor,
+            current: initial,
+        }
+    }
+
+    /// Return the delay to wait for and advance to the next one.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.current;
+        let next_ms = (duration_ms(delay) as f64 * self.factor) as u64;
+        self.current = if next_ms > duration_ms(self.max) {
+            self.max
+        } else {
+            Duration::from_millis(next_ms)
+        };
+        delay
+    }
+
+    /// Start over from the initial delay.
+    pub fn reset(&mut self) {
+        self.current = self.initial;
+    }
+}
+
+fn duration_ms(d: Duration) -> u64 {
+    d.as_secs() * 1000 + (d.subsec_nanos() / 1_000_000) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Backoff;
+    use std::time::Duration;
+
+    #[test]
+    fn backoff_grows_until_max_and_resets() {
+        let mut b = Backoff::new(Duration::from_secs(2),
+                                 Duration::from_secs(5), 2.0);
+        assert_eq!(Duration::from_secs(2), b.next_delay());
+        assert_eq!(Duration::from_secs(4), b.next_delay());
+        assert_eq!(Duration::from_secs(5), b.next_delay());
+        assert_eq!(Duration::from_secs(5), b.next_delay());
+        b.reset();
+        assert_eq!(Duration::from_secs(2), b.next_delay());
+    }
+}
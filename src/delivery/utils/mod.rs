@@ -28,6 +28,8 @@ pub mod say;
 pub mod path_join_many;
 pub mod path_ext;
 pub mod open;
+pub mod backoff;
+pub mod select;
 
 use crypto::digest::Digest;
 use crypto::md5::Md5;
@@ -16,6 +16,12 @@
 //
 
 use term;
+use libc;
+use rustc_serialize::Encodable;
+use rustc_serialize::json::{self, Json, ToJson};
+use std::collections::BTreeMap;
+use std::cell::RefCell;
+use std::mem;
 use std::sync::mpsc::{Sender, Receiver};
 use std::sync::mpsc::channel;
 use std::thread::{self, JoinHandle};
@@ -26,6 +32,12 @@ use std::time::Duration;
 /// Because sometimes, you just want a global variable.
 static mut show_spinner: bool = true;
 static mut show_output:  bool = true;
+static mut json_output:  bool = false;
+
+thread_local! {
+    // The result object printed on stdout in json mode
+    static JSON_RESULT: RefCell<BTreeMap<String, Json>> = RefCell::new(BTreeMap::new())
+}
 
 pub struct Spinner {
     tx: Sender<isize>,
@@ -94,6 +106,86 @@ pub fn turn_off_spinner() {
     }
 }
 
+/// Clear the last `lines` lines written to the terminal so the caller
+/// can redraw them in place. Returns false when they can't be cleared,
+/// as when stdout is piped or the terminal can't move the cursor
+/// (`TERM=dumb`); the caller then appends its lines instead.
+pub fn clear_lines(lines: usize) -> bool {
+    unsafe {
+        if !show_output || json_output {
+            return false
+        }
+    }
+    // `term::stdout` only looks at $TERM, which is set in pipes too
+    if unsafe { libc::isatty(1) } == 0 {
+        return false
+    }
+    let mut t = match term::stdout() {
+        Some(t) => t,
+        None => return false
+    };
+    for _ in 0..lines {
+        if let Err(e) = clear_line(&mut *t) {
+            debug!("Unable to redraw the output: {}", e);
+            return false
+        }
+    }
+    io::stdout().flush().is_ok()
+}
+
+fn clear_line<T: term::Terminal + ?Sized>(t: &mut T) -> term::Result<()> {
+    try!(t.cursor_up());
+    try!(t.carriage_return());
+    t.delete_line()
+}
+
+/// Switch to the json output format: the messages of `say` go to
+/// stderr, and stdout is left for the result object of the command.
+pub fn turn_on_json_output() {
+    unsafe {
+        json_output = true;
+        show_spinner = false;
+    }
+}
+
+pub fn is_json_output() -> bool {
+    unsafe { json_output }
+}
+
+/// Set `key` in the result object of the command
+pub fn result_set<T: ?Sized + ToJson>(key: &str, value: &T) {
+    JSON_RESULT.with(|r| r.borrow_mut().insert(key.to_string(), value.to_json()));
+}
+
+/// Set `key` in the result object of the command to the json
+/// encoding of `value`
+pub fn result_set_encoded<T: Encodable>(key: &str, value: &T) {
+    match json::encode(value).map(|s| Json::from_str(&s)) {
+        Ok(Ok(j)) => result_set(key, &j),
+        _ => debug!("Unable to encode the {} result", key)
+    }
+}
+
+/// Append `value` to the list under `key` in the result object
+pub fn result_push<T: ?Sized + ToJson>(key: &str, value: &T) {
+    JSON_RESULT.with(|r| {
+        let mut result = r.borrow_mut();
+        let entry = result.entry(key.to_string()).or_insert(Json::Array(vec![]));
+        if let Json::Array(ref mut list) = *entry {
+            list.push(value.to_json());
+        }
+    })
+}
+
+/// Print the result object on stdout when in json mode
+pub fn emit_result() {
+    if !is_json_output() {
+        return
+    }
+    let result = JSON_RESULT.with(|r| mem::replace(&mut *r.borrow_mut(), BTreeMap::new()));
+    println!("{}", Json::Object(result).pretty());
+}
+
 fn say_term(mut t: Box<term::StdoutTerminal>, color: &str, to_say: &str) {
     let color_const = match color {
         "green" => term::color::BRIGHT_GREEN,
@@ -111,6 +203,9 @@ fn say_term(mut t: Box<term::StdoutTerminal>, color: &str, to_say: &str) {
 }
 
 pub fn say(color: &str, to_say: &str) {
+    if is_json_output() {
+        return say_stderr(to_say)
+    }
     match term::stdout() {
         Some(t) => {
             unsafe {
@@ -128,6 +223,17 @@ pub fn say(color: &str, to_say: &str) {
     }
 }
 
+fn say_stderr(to_say: &str) {
+    unsafe {
+        if show_output {
+            let mut stderr = io::stderr();
+            stderr.write_all(to_say.as_bytes()).ok().expect("Could not write to stderr");
+        } else {
+            debug!("{}", to_say)
+        }
+    }
+}
+
 pub fn sayln(color: &str, to_say: &str) {
     say(color, to_say);
     say(color, "\n");
@@ -0,0 +1,185 @@
+//
+// Copyright:: Copyright (c) 2016 Chef Software, Inc.
+// License:: Apache License, Version 2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Select parts of a JSON document with a path expression, a small
+//! subset of what jq offers:
+//!
+//! * `changes` is the field `changes` of an object,
+//! * `changes[0]` the first element of an array, `changes[-1]` the last,
+//! * `changes[].id` the `id` field of every element of an array,
+//!
+//! and the steps chain with dots, as in `orgs[0].projects[].name`.
+//! A missing field or element selects `null`.
+
+use errors::{DeliveryError, Kind};
+use rustc_serialize::json::Json;
+use types::DeliveryResult;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Field(String),
+    Index(i64),
+    Each
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector {
+    steps: Vec<Step>
+}
+
+impl Selector {
+    pub fn parse(expr: &str) -> DeliveryResult<Selector> {
+        let expr = expr.trim();
+        let expr = if expr.starts_with(".") { &expr[1..] } else { expr };
+        let mut steps = Vec::new();
+        if expr.is_empty() {
+            return Ok(Selector { steps: steps })
+        }
+        for part in expr.split('.') {
+            let (name, mut rest) = match part.find('[') {
+                Some(i) => (&part[..i], &part[i..]),
+                None => (part, "")
+            };
+            if (name.is_empty() && rest.is_empty()) || name.contains(']') {
+                return Err(invalid(expr))
+            }
+            if !name.is_empty() {
+                steps.push(Step::Field(name.to_string()));
+            }
+            while !rest.is_empty() {
+                let close = match rest.find(']') {
+                    Some(i) if rest.starts_with("[") => i,
+                    _ => return Err(invalid(expr))
+                };
+                let index = rest[1..close].trim();
+                if index.is_empty() {
+                    steps.push(Step::Each);
+                } else {
+                    match index.parse::<i64>() {
+                        Ok(i) => steps.push(Step::Index(i)),
+                        Err(_) => return Err(invalid(expr))
+                    }
+                }
+                rest = &rest[close + 1..];
+            }
+        }
+        Ok(Selector { steps: steps })
+    }
+
+    /// Select from `json`. Every `[]` step flattens its results, so
+    /// `a[].b[]` is a single array.
+    pub fn apply(&self, json: &Json) -> Json {
+        select(json, &self.steps)
+    }
+}
+
+fn select(json: &Json, steps: &[Step]) -> Json {
+    let (step, rest) = match steps.split_first() {
+        Some(s) => s,
+        None => return json.clone()
+    };
+    match *step {
+        Step::Field(ref name) => match json.find(name) {
+            Some(value) => select(value, rest),
+            None => Json::Null
+        },
+        Step::Index(i) => {
+            let found = json.as_array().and_then(|a| {
+                let index = if i < 0 { a.len() as i64 + i } else { i };
+                if index < 0 { None } else { a.get(index as usize) }
+            });
+            match found {
+                Some(value) => select(value, rest),
+                None => Json::Null
+            }
+        },
+        Step::Each => {
+            let elements = match json.as_array() {
+                Some(a) => a,
+                None => return Json::Null
+            };
+            let flatten = rest.iter().any(|s| *s == Step::Each);
+            let mut selected = Vec::new();
+            for element in elements.iter() {
+                match select(element, rest) {
+                    Json::Array(ref inner) if flatten => selected.extend(inner.iter().cloned()),
+                    value => selected.push(value)
+                }
+            }
+            Json::Array(selected)
+        }
+    }
+}
+
+fn invalid(expr: &str) -> DeliveryError {
+    let msg = format!("Invalid selection '{}'; expected a path such as \
+                       changes[].id", expr);
+    DeliveryError{ kind: Kind::OptionConstraint, detail: Some(msg) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_serialize::json::Json;
+
+    fn doc() -> Json {
+        Json::from_str("{\"changes\":[{\"id\":\"a\",\"tags\":[\"x\",\"y\"]},\
+                                      {\"id\":\"b\",\"tags\":[\"z\"]}],\
+                         \"org\":{\"name\":\"acme\"}}").unwrap()
+    }
+
+    fn select(expr: &str) -> Json {
+        Selector::parse(expr).unwrap().apply(&doc())
+    }
+
+    fn json(s: &str) -> Json {
+        Json::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn fields_and_indexes() {
+        assert_eq!(json("\"acme\""), select("org.name"));
+        assert_eq!(json("\"acme\""), select(".org.name"));
+        assert_eq!(json("\"b\""), select("changes[1].id"));
+        assert_eq!(json("\"b\""), select("changes[-1].id"));
+        assert_eq!(doc(), select(""));
+        assert_eq!(doc(), select("."));
+    }
+
+    #[test]
+    fn each_element() {
+        assert_eq!(json("[\"a\",\"b\"]"), select("changes[].id"));
+        assert_eq!(json("[\"x\",\"y\",\"z\"]"), select("changes[].tags[]"));
+        assert_eq!(json("[[\"x\",\"y\"],[\"z\"]]"), select("changes[].tags"));
+    }
+
+    #[test]
+    fn missing_selects_null() {
+        assert_eq!(Json::Null, select("nope"));
+        assert_eq!(Json::Null, select("changes[5]"));
+        assert_eq!(Json::Null, select("changes[-3]"));
+        assert_eq!(Json::Null, select("org[]"));
+    }
+
+    #[test]
+    fn invalid_expressions() {
+        assert!(Selector::parse("changes..id").is_err());
+        assert!(Selector::parse("changes[x]").is_err());
+        assert!(Selector::parse("changes[0").is_err());
+        assert!(Selector::parse("changes]").is_err());
+    }
+}
@@ -17,6 +17,10 @@
 
 use std::process::Command;
 use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::os::unix::io::AsRawFd;
 use errors::{DeliveryError, Kind};
 use libc;
 use utils::path_to_string;
@@ -102,6 +106,47 @@ pub fn chown_all<P: AsRef<Path>>(who: &str,
     }
 }
 
+// Create or truncate `path` as a file only its owner can read and
+// write. The mode of a file that already exists is fixed as well.
+pub fn create_private_file<P: AsRef<Path>>(path: P) -> io::Result<File> {
+    let path = path.as_ref();
+    let file = try!(OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .mode(0o600)
+                    .open(path));
+    try!(fs::set_permissions(path, fs::Permissions::from_mode(0o600)));
+    Ok(file)
+}
+
+// Open `path`, creating it if needed, and wait for an exclusive
+// advisory lock on it. The lock is released when the file is closed.
+pub fn lock_file<P: AsRef<Path>>(path: P) -> io::Result<File> {
+    let file = try!(OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .mode(0o600)
+                    .open(path));
+    loop {
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } == 0 {
+            return Ok(file)
+        }
+        let e = io::Error::last_os_error();
+        if e.kind() != io::ErrorKind::Interrupted {
+            return Err(e)
+        }
+    }
+}
+
+// Whether users other than the owner can access `path`
+pub fn is_shared_file<P: AsRef<Path>>(path: P) -> bool {
+    match fs::metadata(path) {
+        Ok(meta) => meta.permissions().mode() & 0o077 != 0,
+        Err(_) => false
+    }
+}
+
 pub fn privileged_process() -> bool {
     match unsafe { libc::getuid() } {
         0 => true,
@@ -116,6 +161,14 @@ pub fn make_command(cmd: &str) -> Command {
     Command::new(cmd)
 }
 
+// Build a command running the `cmd` command line through the shell,
+// for the commands users write in their config.
+pub fn shell_command(cmd: &str) -> Command {
+    let mut c = Command::new("sh");
+    c.arg("-c").arg(cmd);
+    c
+}
+
 /// Returns the absolute path for a given command, if it exists, by searching the `PATH`
 /// environment variable.
 ///
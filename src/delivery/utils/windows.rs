@@ -17,8 +17,11 @@
 
 use std::process::Command;
 use std::env;
-use std::fs;
+use std::fs::{self, File, OpenOptions};
 use std::io;
+use std::os::windows::fs::OpenOptionsExt;
+use std::thread;
+use std::time::Duration;
 use errors::{DeliveryError, Kind};
 use std::path::{Path, PathBuf};
 use std::convert::AsRef;
@@ -88,6 +91,38 @@ pub fn make_command(cmd: &str) -> Command {
     c
 }
 
+// Files get the permissions of their directory on Windows, so this
+// is `File::create`; see the unix version.
+pub fn create_private_file<P: AsRef<Path>>(path: P) -> io::Result<File> {
+    File::create(path)
+}
+
+// Windows has no advisory locks: the file is opened without sharing
+// it, waiting for whoever else has it open; see the unix version.
+pub fn lock_file<P: AsRef<Path>>(path: P) -> io::Result<File> {
+    // ERROR_SHARING_VIOLATION
+    let sharing_violation = Some(32);
+    loop {
+        match OpenOptions::new().write(true).create(true).share_mode(0).open(path.as_ref()) {
+            Err(ref e) if e.raw_os_error() == sharing_violation => {
+                thread::sleep(Duration::from_millis(50))
+            },
+            r => return r
+        }
+    }
+}
+
+// Permissions are not checked on Windows; see the unix version.
+pub fn is_shared_file<P: AsRef<Path>>(_path: P) -> bool {
+    false
+}
+
+// Build a command running the `cmd` command line through the shell,
+// for the commands users write in their config.
+pub fn shell_command(cmd: &str) -> Command {
+    make_command(cmd)
+}
+
 /// Returns the absolute path for a given command, if it exists, by searching the `PATH`
 /// environment variable.
 ///
@@ -10,6 +10,7 @@ use std::process::{Command, Output};
 use std::env;
 use rustc_serialize::json::Json;
 use delivery::utils::path_join_many::PathJoinMany;
+use delivery::token::TokenStore;
 
 // ** Functions used in tests **
 
@@ -302,3 +303,140 @@ test!(job_verify_dna_json {
         None => panic!("No delivery_builderl/build_user, {}", dna_data)
     };
 });
+
+// Runs `delivery token` and `delivery api` against the responses of
+// the `delivery_api` cassette instead of a Delivery server.
+test!(token_and_api_replayed_from_a_cassette {
+    let home = TempDir::new("home").unwrap();
+    let cassette = fixture_file("cassettes").join("delivery_api.json");
+    let mut token = delivery_cmd();
+    token.arg("token").arg("--raw")
+         .arg("-s").arg("earth").arg("-e").arg("acme").arg("-u").arg("kirk")
+         .env("HOME", home.path())
+         .env("AUTOMATE_PASSWORD", "sesame")
+         .env("DELIVERY_CASSETTE_REPLAY", &cassette);
+    let output = assert_command_successful(&mut token, &home.path());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("cafecafe"));
+    let tstore = panic_on_error!(TokenStore::from_file(&home.path().join_many(&[".delivery",
+                                                                                  "api-tokens"])));
+    assert_eq!(Some(&"cafecafe".to_string()), tstore.lookup("earth", "acme", "kirk"));
+    // The server accepted the password, so the new token is verified
+    let entry = tstore.entries()[0].clone();
+    assert!(entry.verified.is_some());
+    // and it expires after the ttl of the token response
+    assert!(entry.issued.is_some());
+    assert_eq!(Some(604800), entry.ttl);
+
+    let mut api = delivery_cmd();
+    api.arg("api").arg("get").arg("orgs")
+       .arg("-s").arg("earth").arg("-e").arg("acme").arg("-u").arg("kirk")
+       .arg("--select").arg("orgs[]")
+       .env("HOME", home.path())
+       .env("DELIVERY_CASSETTE_REPLAY", &cassette);
+    let output = assert_command_successful(&mut api, &home.path());
+    assert_eq!("enterprise_crew", String::from_utf8_lossy(&output.stdout).trim());
+});
+
+// The change the git remotes of the cassette tests claim to create
+const CASSETTE_CHANGE_ID: &'static str = "b39e1c3a-1cdc-4c7e-8a4d-0b9e4e1a2f57";
+
+/// Makes the git remote whose hooks are in `hooks_dir` answer pushes
+/// the way the Delivery server does, with the URL of the new change.
+fn setup_review_hook(hooks_dir: &Path, project: &str) {
+    let hook = hooks_dir.join("post-receive");
+    {
+        let mut f = panic_on_error!(File::create(&hook));
+        let script = format!("#!/bin/sh\necho https://localhost/e/family/#/organizations/\
+                              sepultura/projects/{}/changes/{}\n", project, CASSETTE_CHANGE_ID);
+        panic_on_error!(f.write_all(script.as_bytes()));
+    }
+    panic_on_error!(Command::new("chmod").arg("755").arg(&hook).output());
+}
+
+/// Returns a home directory holding a token of cavalera for the
+/// `family` enterprise of the localhost server.
+fn setup_home_with_token() -> TempDir {
+    let home = TempDir::new("home").unwrap();
+    let dot_delivery = home.path().join(".delivery");
+    panic_on_error!(fs::create_dir_all(&dot_delivery));
+    let mut f = panic_on_error!(File::create(&dot_delivery.join("api-tokens")));
+    panic_on_error!(f.write_all(b"localhost,family,cavalera|cafecafe\n"));
+    home
+}
+
+// Runs `delivery init` against the responses of the `init` cassette,
+// with a bare repository standing in for the git server.
+test!(init_replayed_from_a_cassette {
+    let home = setup_home_with_token();
+    let remote = TempDir::new("delivery-remote").unwrap();
+    panic_on_error!(git_command(&["init", "--bare", remote.path().to_str().unwrap()], remote.path()));
+    setup_review_hook(&remote.path().join("hooks"), "radar");
+
+    let workspace = TempDir::new("workspace").unwrap();
+    let project = workspace.path().join("radar");
+    panic_on_error!(fs::create_dir_all(&project));
+    let test_repo_path = fixture_file("test_repo");
+    panic_on_error!(copy_recursive(&test_repo_path.join(".delivery"), &project));
+    panic_on_error!(copy_recursive(&test_repo_path.join("README.md"), &project));
+    panic_on_error!(copy_recursive(&fixture_file("path_config.json"), &project.join_many(&[".delivery", "config.json"])));
+    panic_on_error!(git_command(&["init", project.to_str().unwrap()], &project));
+    // The home of the command is the one with the token, which has no
+    // git identity to commit DELIVERY.md with
+    panic_on_error!(git_command(&["config", "user.name", "Max Cavalera"], &project));
+    panic_on_error!(git_command(&["config", "user.email", "max@example.com"], &project));
+    panic_on_error!(git_command(&["add", "."], &project));
+    panic_on_error!(git_command(&["commit", "-a", "-m", "Initial Commit"], &project));
+    // The ssh remote init adds leads to the bare repository
+    panic_on_error!(git_command(&["config",
+                                  &format!("url.{}.insteadOf", remote.path().to_str().unwrap()),
+                                  "ssh://cavalera@family@localhost:8989/family/sepultura/radar"],
+                                &project));
+
+    let mut init = delivery_cmd();
+    init.arg("init").arg("--no-open").arg("--skip-build-cookbook")
+        .arg("-u").arg("cavalera").arg("-s").arg("localhost")
+        .arg("-e").arg("family").arg("-o").arg("sepultura")
+        .env("HOME", home.path())
+        .env("DELIVERY_CASSETTE_REPLAY", fixture_file("cassettes").join("init.json"));
+    assert_command_successful(&mut init, &project);
+
+    assert!(project.join("DELIVERY.md").is_file());
+    panic_on_error!(git_command(&["rev-parse", "--verify", "master"], remote.path()));
+    panic_on_error!(git_command(&["rev-parse", "--verify",
+                                  "_for/master/initialize-delivery-pipeline"], remote.path()));
+});
+
+// Runs `delivery review --edit` against the responses of the
+// `review_edit` cassette, with an editor that renames the change.
+test!(review_edit_replayed_from_a_cassette {
+    let home = setup_home_with_token();
+    let delivery_project_git = setup_mock_delivery_project_git("path_config.json");
+    setup_review_hook(&delivery_project_git.path().join_many(&[".git", "hooks"]), "radar");
+    // The project is named after its directory, as in the cassette
+    let workspace = TempDir::new("workspace").unwrap();
+    let local_project = workspace.path().join("radar");
+    panic_on_error!(git_command(&["clone",
+                                  delivery_project_git.path().to_str().unwrap(),
+                                  local_project.to_str().unwrap()], workspace.path()));
+    panic_on_error!(git_command(&["remote", "add", "delivery",
+                                  delivery_project_git.path().to_str().unwrap()], &local_project));
+    let mut setup = delivery_cmd();
+    setup.arg("setup")
+         .arg("--user").arg("cavalera")
+         .arg("--server").arg("localhost")
+         .arg("--ent").arg("family")
+         .arg("--org").arg("sepultura")
+         .arg("--for").arg("master")
+         .arg("--config-path").arg(local_project.to_str().unwrap());
+    assert_command_successful(&mut setup, &local_project);
+    setup_change(&local_project, "rust/test", "freaky");
+
+    let mut review = delivery_review_command("master");
+    review.arg("--edit")
+          .env("EDITOR", "sed -i -e s/freaky/funky/")
+          .env("HOME", home.path())
+          .env("DELIVERY_CASSETTE_REPLAY", fixture_file("cassettes").join("review_edit.json"));
+    let output = assert_command_successful(&mut review, &local_project);
+    assert!(String::from_utf8_lossy(&output.stdout).contains(CASSETTE_CHANGE_ID));
+    setup_checkout_branch(&delivery_project_git.path(), "_for/master/rust/test");
+});